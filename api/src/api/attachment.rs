@@ -0,0 +1,325 @@
+use std::{path::Path, sync::Arc};
+
+use bon::bon;
+use reqwest::multipart::{Form, Part};
+use tokio::io::AsyncWrite;
+
+use crate::{client::ClientInner, error::Result, models};
+
+pub struct Attachment {
+    pub(crate) inner: Arc<ClientInner>,
+}
+
+#[bon]
+impl Attachment {
+    pub(crate) fn new(inner: Arc<ClientInner>) -> Self {
+        Self { inner }
+    }
+
+    #[builder(finish_fn(name = send))]
+    pub async fn get(&self, guid: &str) -> Result<models::Attachment> {
+        let req = self.inner.http.get(format!(
+            "{}/api/v1/attachment/{}?password={}",
+            self.inner.server_url, guid, self.inner.password
+        ));
+        self.inner.request_data(req).await
+    }
+
+    #[builder(finish_fn(name = send))]
+    pub async fn get_count(&self) -> Result<models::AttachmentCount> {
+        let req = self.inner.http.get(format!(
+            "{}/api/v1/attachment/count?password={}",
+            self.inner.server_url, self.inner.password
+        ));
+        self.inner.request_data(req).await
+    }
+
+    /// Stream an attachment into `writer`, returning the number of bytes written.
+    ///
+    /// Images are converted to a web friendly format by the server unless `original`
+    /// is set. `width`, `height` and `quality` request a resized image.
+    #[builder(finish_fn(name = send))]
+    pub async fn download<W: AsyncWrite + Unpin + Send>(
+        &self,
+        guid: &str,
+        writer: &mut W,
+        on_progress: Option<&mut (dyn FnMut(models::TransferProgress) + Send)>,
+        #[builder(default)] original: bool,
+        width: Option<u32>,
+        height: Option<u32>,
+        quality: Option<u32>,
+    ) -> Result<u64> {
+        let req = self
+            .inner
+            .http
+            .get(format!(
+                "{}/api/v1/attachment/{}/download?password={}",
+                self.inner.server_url, guid, self.inner.password
+            ))
+            .query(&[
+                ("original", original.then(|| "true".to_string())),
+                ("width", width.map(|w| w.to_string())),
+                ("height", height.map(|h| h.to_string())),
+                ("quality", quality.map(|q| q.to_string())),
+            ]);
+        self.inner.request_stream(req, writer, on_progress).await
+    }
+
+    /// Stream the video part of a live photo into `writer`
+    #[builder(finish_fn(name = send))]
+    pub async fn download_live_photo<W: AsyncWrite + Unpin + Send>(
+        &self,
+        guid: &str,
+        writer: &mut W,
+        on_progress: Option<&mut (dyn FnMut(models::TransferProgress) + Send)>,
+    ) -> Result<u64> {
+        let req = self.inner.http.get(format!(
+            "{}/api/v1/attachment/{}/live?password={}",
+            self.inner.server_url, guid, self.inner.password
+        ));
+        self.inner.request_stream(req, writer, on_progress).await
+    }
+
+    /// Fetch a blurhash placeholder for an image attachment
+    #[builder(finish_fn(name = send))]
+    pub async fn get_blurhash(
+        &self,
+        guid: &str,
+        width: Option<u32>,
+        height: Option<u32>,
+        quality: Option<u32>,
+    ) -> Result<String> {
+        let req = self
+            .inner
+            .http
+            .get(format!(
+                "{}/api/v1/attachment/{}/blurhash?password={}",
+                self.inner.server_url, guid, self.inner.password
+            ))
+            .query(&[
+                ("width", width.map(|w| w.to_string())),
+                ("height", height.map(|h| h.to_string())),
+                ("quality", quality.map(|q| q.to_string())),
+            ]);
+        self.inner.request_data(req).await
+    }
+
+    /// Send a file as a new message. The file is streamed from disk.
+    #[builder(finish_fn(name = send))]
+    pub async fn send(
+        &self,
+        chat_guid: &models::ChatGuid,
+        temp_guid: &str,
+        path: &Path,
+        /// File name shown to the recipient, defaults to the name of `path`
+        name: Option<&str>,
+        method: Option<models::SendMethod>,
+        subject: Option<&str>,
+        effect_id: Option<&str>,
+        selected_message_guid: Option<&str>,
+        part_index: Option<u32>,
+        #[builder(default)] is_audio_message: bool,
+    ) -> Result<models::Message> {
+        let mut part = Part::file(path).await?;
+        let name = name
+            .map(str::to_string)
+            .or_else(|| path.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| temp_guid.to_string());
+        part = part.file_name(name.clone());
+
+        let mut form = Form::new()
+            .text("chatGuid", chat_guid.to_string())
+            .text("tempGuid", temp_guid.to_string())
+            .text("name", name)
+            .part("attachment", part);
+        if let Some(method) = method {
+            form = form.text("method", method.name().to_string());
+        }
+        if let Some(subject) = subject {
+            form = form.text("subject", subject.to_string());
+        }
+        if let Some(effect_id) = effect_id {
+            form = form.text("effectId", effect_id.to_string());
+        }
+        if let Some(selected_message_guid) = selected_message_guid {
+            form = form.text("selectedMessageGuid", selected_message_guid.to_string());
+        }
+        if let Some(part_index) = part_index {
+            form = form.text("partIndex", part_index.to_string());
+        }
+        if is_audio_message {
+            form = form.text("isAudioMessage", "true");
+        }
+
+        let req = self
+            .inner
+            .http
+            .post(format!(
+                "{}/api/v1/message/attachment?password={}",
+                self.inner.server_url, self.inner.password
+            ))
+            .multipart(form);
+        self.inner.request_data(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use httpmock::prelude::*;
+
+    use crate::client::tests::get_mock_client;
+    use crate::error::Error;
+
+    #[tokio::test]
+    async fn get() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/api/v1/attachment/at_0_3B1D8E4C-7C1A-4E3F-9D2B-1B6A1F0C2E55");
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(include_str!("../../test_data/attachment.json"));
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let attachment = client
+            .attachments()
+            .get()
+            .guid("at_0_3B1D8E4C-7C1A-4E3F-9D2B-1B6A1F0C2E55")
+            .send()
+            .await
+            .expect("Failed to get attachment");
+
+        mock.assert_async().await;
+        assert_eq!(attachment.mime_type.as_deref(), Some("image/jpeg"));
+        assert_eq!(attachment.total_bytes, 11);
+        assert!(!attachment.hide_attachment);
+    }
+
+    #[tokio::test]
+    async fn download() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/api/v1/attachment/at_0/download")
+                    .query_param("original", "true");
+                then.status(200)
+                    .header("content-type", "image/jpeg")
+                    .body("hello world");
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let reported = AtomicU64::new(0);
+        let mut on_progress = |progress: crate::models::TransferProgress| {
+            reported.store(progress.bytes_transferred, Ordering::SeqCst);
+        };
+        let mut buffer = Vec::new();
+        let written = client
+            .attachments()
+            .download()
+            .guid("at_0")
+            .writer(&mut buffer)
+            .on_progress(&mut on_progress)
+            .original(true)
+            .send()
+            .await
+            .expect("Failed to download attachment");
+
+        mock.assert_async().await;
+        assert_eq!(written, 11);
+        assert_eq!(buffer, b"hello world");
+        assert_eq!(reported.load(Ordering::SeqCst), 11);
+    }
+
+    #[tokio::test]
+    async fn download_error() {
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/v1/attachment/missing/download");
+                then.status(404)
+                    .header("content-type", "application/json")
+                    .body(r#"{"status": 404, "message": "Attachment does not exist!", "error": {"type": "Database Error", "message": "Attachment does not exist!"}}"#);
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let mut buffer = Vec::new();
+        let res = client
+            .attachments()
+            .download()
+            .guid("missing")
+            .writer(&mut buffer)
+            .send()
+            .await;
+
+        assert!(matches!(res, Err(Error::DatabaseError { .. })));
+        assert!(buffer.is_empty());
+    }
+
+    #[tokio::test]
+    async fn download_status_error() {
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/v1/attachment/missing/download");
+                then.status(404)
+                    .header("content-type", "text/html")
+                    .body("<html>Not Found</html>");
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let mut buffer = Vec::new();
+        let res = client
+            .attachments()
+            .download()
+            .guid("missing")
+            .writer(&mut buffer)
+            .send()
+            .await;
+
+        assert!(matches!(
+            res,
+            Err(Error::ApiError { status, .. }) if status == 404
+        ));
+        assert!(buffer.is_empty());
+    }
+
+    #[tokio::test]
+    async fn send() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/api/v1/message/attachment")
+                    .body_includes("name=\"tempGuid\"")
+                    .body_includes("attachment.json");
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(include_str!("../../test_data/message.json"));
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let path =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("test_data/attachment.json");
+        client
+            .attachments()
+            .send()
+            .chat_guid(&"iMessage;-;+12025550123".parse().unwrap())
+            .temp_guid("temp-1")
+            .path(&path)
+            .send()
+            .await
+            .expect("Failed to send attachment");
+
+        mock.assert_async().await;
+    }
+}
@@ -1,11 +1,24 @@
-use std::sync::Arc;
+use std::{collections::HashSet, path::Path, sync::Arc};
 
 use bon::bon;
 use chrono::{DateTime, Utc};
+use futures_util::{
+    StreamExt, TryStreamExt,
+    stream::{self, BoxStream},
+};
+use reqwest::multipart::{Form, Part};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
-use crate::{client::ClientInner, error::Result, models, utils::build_option_list};
+use crate::{
+    client::ClientInner,
+    error::{Error, Result},
+    models,
+    utils::build_option_list,
+};
+
+/// Items fetched per request by [`Chat::query_stream`] and [`Chat::get_messages_stream`]
+const DEFAULT_PAGE_SIZE: u32 = 100;
 
 pub struct Chat {
     pub(crate) inner: Arc<ClientInner>,
@@ -22,7 +35,7 @@ impl Chat {
         &self,
         #[builder(field)] with_last_message: bool,
         #[builder(field)] with_participants: bool,
-        guid: &str,
+        guid: &models::ChatGuid,
     ) -> Result<models::Chat> {
         let with_str = build_option_list! {
             with_last_message => "lastmessage",
@@ -43,7 +56,7 @@ impl Chat {
     }
 
     #[builder(finish_fn(name = send))]
-    pub async fn get_icon(&self, guid: &str) -> Result<Vec<u8>> {
+    pub async fn get_icon(&self, guid: &models::ChatGuid) -> Result<Vec<u8>> {
         let req = self.inner.http.get(format!(
             "{}/api/v1/chat/{}/icon?password={}",
             self.inner.server_url, guid, self.inner.password
@@ -66,42 +79,88 @@ impl Chat {
         #[builder(field)] with_attachment: bool,
         #[builder(field)] with_handle: bool,
         #[builder(field)] with_sms: bool,
-        guid: &str,
+        #[builder(field)] with_attributed_body: bool,
+        guid: &models::ChatGuid,
         after: Option<DateTime<Utc>>,
         before: Option<DateTime<Utc>>,
         limit: Option<u32>,
         offset: Option<u32>,
         sort: Option<models::SortOrder>,
     ) -> Result<Vec<models::Message>> {
-        let with_str = build_option_list! {
-            with_attachment => "attachment",
-            with_handle => "handle",
-            with_sms => "sms",
-        }
-        .join(",");
-        let with = if with_str.is_empty() {
-            None
-        } else {
-            Some(with_str)
+        let query = MessageQuery {
+            inner: Arc::clone(&self.inner),
+            guid: guid.clone(),
+            with: build_option_list! {
+                with_attachment => "attachment",
+                with_handle => "handle",
+                with_sms => "sms",
+                with_attributed_body => "attributedBody",
+            },
         };
-        let req = self
-            .inner
-            .http
-            .get(format!(
-                "{}/api/v1/chat/{}/message?password={}",
-                self.inner.server_url, guid, self.inner.password
-            ))
-            .query(&[
-                ("with", with),
-                ("after", after.map(|d| d.timestamp().to_string())),
-                ("before", before.map(|d| d.timestamp().to_string())),
-                ("limit", limit.map(|l| l.to_string())),
-                ("offset", offset.map(|o| o.to_string())),
-                ("sort", sort.map(|s| s.name().to_string())),
-            ]);
-        self.inner.request_data(req).await
+        query
+            .page(
+                after.map(|d| d.timestamp_millis()),
+                before.map(|d| d.timestamp_millis()),
+                limit,
+                offset,
+                sort,
+            )
+            .await
+    }
+
+    /// Every message in a chat between `after` and `before`, fetching `page_size` at a
+    /// time as the stream is polled.
+    ///
+    /// Each page starts from the date of the last message seen rather than an offset, so
+    /// messages that arrive while it runs don't shift the pages and cause duplicates or
+    /// skips.
+    #[builder(finish_fn(name = send))]
+    pub fn get_messages_stream(
+        &self,
+        #[builder(field)] with_attachment: bool,
+        #[builder(field)] with_handle: bool,
+        #[builder(field)] with_sms: bool,
+        #[builder(field)] with_attributed_body: bool,
+        guid: &models::ChatGuid,
+        after: Option<DateTime<Utc>>,
+        before: Option<DateTime<Utc>>,
+        #[builder(default = DEFAULT_PAGE_SIZE)] page_size: u32,
+        #[builder(default = models::SortOrder::Descending)] sort: models::SortOrder,
+    ) -> BoxStream<'static, Result<models::Message>> {
+        let cursor = MessageCursor {
+            query: MessageQuery {
+                inner: Arc::clone(&self.inner),
+                guid: guid.clone(),
+                with: build_option_list! {
+                    with_attachment => "attachment",
+                    with_handle => "handle",
+                    with_sms => "sms",
+                    with_attributed_body => "attributedBody",
+                },
+            },
+            sort,
+            page_size,
+            after: after.map(|d| d.timestamp_millis()),
+            before: before.map(|d| d.timestamp_millis()),
+            date: None,
+            seen: HashSet::new(),
+            done: false,
+        };
+        stream::try_unfold(cursor, |mut cursor| async move {
+            let page = cursor.next_page().await?;
+            Ok::<_, Error>(page.map(|page| (page, cursor)))
+        })
+        .map_ok(|messages| stream::iter(messages.into_iter().map(Ok)))
+        .try_flatten()
+        .boxed()
     }
 
+    /// Query chats a page at a time.
+    ///
+    /// The server only sorts, the `archived`, `service` and `guids` filters are applied
+    /// after fetching, so more pages are fetched until `limit` chats match or none are
+    /// left. `offset` counts every chat on the server, including those filtered out,
+    /// so use [`Chat::query_stream`] to page through filtered chats.
     #[builder(finish_fn(name = send))]
     pub async fn query(
         &self,
@@ -114,97 +173,560 @@ impl Chat {
         #[builder(field)] with_message_payload_data: bool,
         limit: Option<u32>,
         offset: Option<u32>,
-        sort: Option<String>, // TODO: This should be an enum
+        sort: Option<models::ChatSort>,
+        /// Only archived chats, or only chats that aren't
+        archived: Option<bool>,
+        /// Only chats over this service, by their GUID
+        service: Option<models::Service>,
+        guids: Option<&[models::ChatGuid]>,
     ) -> Result<Vec<models::Chat>> {
-        let with = build_option_list! {
-            with_last_message => "lastmessage",
-            with_participants => "participants",
-            with_sms => "sms",
-            with_archived => "archived",
-            with_message_attributed_body => "message.attributed-body",
-            with_message_info_summary => "message.message-info-summary",
-            with_message_payload_data => "message.payload-data",
+        let query = ChatQuery {
+            inner: Arc::clone(&self.inner),
+            with: build_option_list! {
+                with_last_message => "lastmessage",
+                with_participants => "participants",
+                with_sms => "sms",
+                with_archived => "archived",
+                with_message_attributed_body => "message.attributed-body",
+                with_message_info_summary => "message.message-info-summary",
+                with_message_payload_data => "message.payload-data",
+            },
+            sort,
+            archived,
+            service,
+            guids: guids.map(<[_]>::to_vec),
         };
+        let Some(limit) = limit else {
+            let (_, chats) = query.page(None, offset).await?;
+            return Ok(chats);
+        };
+
+        let mut chats = Vec::new();
+        let mut offset = offset.unwrap_or(0);
+        loop {
+            let (fetched, page) = query.page(Some(limit), Some(offset)).await?;
+            chats.extend(page);
+            offset += fetched as u32;
+            if chats.len() >= limit as usize || fetched < limit as usize {
+                break;
+            }
+        }
+        chats.truncate(limit as usize);
+        Ok(chats)
+    }
+
+    /// Query every chat, fetching `page_size` at a time as the stream is polled
+    #[builder(finish_fn(name = send))]
+    pub fn query_stream(
+        &self,
+        #[builder(field)] with_last_message: bool,
+        #[builder(field)] with_participants: bool,
+        #[builder(field)] with_sms: bool,
+        #[builder(field)] with_archived: bool,
+        #[builder(field)] with_message_attributed_body: bool,
+        #[builder(field)] with_message_info_summary: bool,
+        #[builder(field)] with_message_payload_data: bool,
+        #[builder(default = DEFAULT_PAGE_SIZE)] page_size: u32,
+        sort: Option<models::ChatSort>,
+        /// Only archived chats, or only chats that aren't
+        archived: Option<bool>,
+        /// Only chats over this service, by their GUID
+        service: Option<models::Service>,
+        guids: Option<&[models::ChatGuid]>,
+    ) -> BoxStream<'static, Result<models::Chat>> {
+        let query = Arc::new(ChatQuery {
+            inner: Arc::clone(&self.inner),
+            with: build_option_list! {
+                with_last_message => "lastmessage",
+                with_participants => "participants",
+                with_sms => "sms",
+                with_archived => "archived",
+                with_message_attributed_body => "message.attributed-body",
+                with_message_info_summary => "message.message-info-summary",
+                with_message_payload_data => "message.payload-data",
+            },
+            sort,
+            archived,
+            service,
+            guids: guids.map(<[_]>::to_vec),
+        });
+        stream::try_unfold(Some(0), move |offset| {
+            let query = Arc::clone(&query);
+            async move {
+                let Some(offset) = offset else {
+                    return Ok::<_, Error>(None);
+                };
+                let (fetched, chats) = query.page(Some(page_size), Some(offset)).await?;
+                let next = (fetched >= page_size as usize).then_some(offset + page_size);
+                Ok(Some((chats, next)))
+            }
+        })
+        .map_ok(|chats| stream::iter(chats.into_iter().map(Ok)))
+        .try_flatten()
+        .boxed()
+    }
+
+    /// Show the typing indicator to the other participants. Requires the Private API.
+    #[builder(finish_fn(name = send))]
+    pub async fn start_typing(&self, guid: &models::ChatGuid) -> Result<()> {
+        let req = self.inner.http.post(format!(
+            "{}/api/v1/chat/{}/typing?password={}",
+            self.inner.server_url, guid, self.inner.password
+        ));
+        self.inner.request(req).await
+    }
+
+    /// Hide the typing indicator again. Requires the Private API.
+    #[builder(finish_fn(name = send))]
+    pub async fn stop_typing(&self, guid: &models::ChatGuid) -> Result<()> {
+        let req = self.inner.http.delete(format!(
+            "{}/api/v1/chat/{}/typing?password={}",
+            self.inner.server_url, guid, self.inner.password
+        ));
+        self.inner.request(req).await
+    }
+
+    /// Mark every message in the chat as read, sending read receipts if they are
+    /// enabled. Requires the Private API.
+    #[builder(finish_fn(name = send))]
+    pub async fn mark_read(&self, guid: &models::ChatGuid) -> Result<()> {
+        let req = self.inner.http.post(format!(
+            "{}/api/v1/chat/{}/read?password={}",
+            self.inner.server_url, guid, self.inner.password
+        ));
+        self.inner.request(req).await
+    }
+
+    /// Requires the Private API
+    #[builder(finish_fn(name = send))]
+    pub async fn mark_unread(&self, guid: &models::ChatGuid) -> Result<()> {
+        let req = self.inner.http.post(format!(
+            "{}/api/v1/chat/{}/unread?password={}",
+            self.inner.server_url, guid, self.inner.password
+        ));
+        self.inner.request(req).await
+    }
+
+    /// Start a chat with `addresses` by sending it a first message.
+    ///
+    /// Returns the existing chat if there already is one with these participants.
+    /// `service` is iMessage by default.
+    #[builder(finish_fn(name = send))]
+    pub async fn create(
+        &self,
+        addresses: &[&str],
+        message: &str,
+        service: Option<models::Service>,
+        method: Option<models::SendMethod>,
+        temp_guid: Option<&str>,
+    ) -> Result<models::Chat> {
         let req = self
             .inner
             .http
             .post(format!(
-                "{}/api/v1/chat/query?password={}",
+                "{}/api/v1/chat/new?password={}",
                 self.inner.server_url, self.inner.password
             ))
             .json(&json!({
-                "with": with,
-                "limit": limit,
-                "offset": offset,
-                "sort": sort,
+                "addresses": addresses,
+                "message": message,
+                "service": service,
+                "method": method,
+                "tempGuid": temp_guid,
             }));
         self.inner.request_data(req).await
     }
+
+    /// Rename a group chat. Requires the Private API.
+    #[builder(finish_fn(name = send))]
+    pub async fn rename(
+        &self,
+        guid: &models::ChatGuid,
+        display_name: &str,
+    ) -> Result<models::Chat> {
+        let req = self
+            .inner
+            .http
+            .put(format!(
+                "{}/api/v1/chat/{}?password={}",
+                self.inner.server_url, guid, self.inner.password
+            ))
+            .json(&json!({ "displayName": display_name }));
+        self.inner.request_data(req).await
+    }
+
+    /// Add someone to a group chat. Requires the Private API.
+    #[builder(finish_fn(name = send))]
+    pub async fn add_participant(
+        &self,
+        guid: &models::ChatGuid,
+        address: &str,
+    ) -> Result<models::Chat> {
+        let req = self
+            .inner
+            .http
+            .post(format!(
+                "{}/api/v1/chat/{}/participant/add?password={}",
+                self.inner.server_url, guid, self.inner.password
+            ))
+            .json(&json!({ "address": address }));
+        self.inner.request_data(req).await
+    }
+
+    /// Remove someone from a group chat. Requires the Private API.
+    #[builder(finish_fn(name = send))]
+    pub async fn remove_participant(
+        &self,
+        guid: &models::ChatGuid,
+        address: &str,
+    ) -> Result<models::Chat> {
+        let req = self
+            .inner
+            .http
+            .post(format!(
+                "{}/api/v1/chat/{}/participant/remove?password={}",
+                self.inner.server_url, guid, self.inner.password
+            ))
+            .json(&json!({ "address": address }));
+        self.inner.request_data(req).await
+    }
+
+    /// Set the photo of a group chat from an image file. Requires the Private API.
+    #[builder(finish_fn(name = send))]
+    pub async fn set_icon(&self, guid: &models::ChatGuid, path: &Path) -> Result<()> {
+        let form = Form::new().part("icon", Part::file(path).await?);
+        let req = self
+            .inner
+            .http
+            .post(format!(
+                "{}/api/v1/chat/{}/icon?password={}",
+                self.inner.server_url, guid, self.inner.password
+            ))
+            .multipart(form);
+        self.inner.request(req).await
+    }
+
+    /// Remove the photo of a group chat. Requires the Private API.
+    #[builder(finish_fn(name = send))]
+    pub async fn remove_icon(&self, guid: &models::ChatGuid) -> Result<()> {
+        let req = self.inner.http.delete(format!(
+            "{}/api/v1/chat/{}/icon?password={}",
+            self.inner.server_url, guid, self.inner.password
+        ));
+        self.inner.request(req).await
+    }
+
+    /// Leave a group chat. Requires the Private API.
+    #[builder(finish_fn(name = send))]
+    pub async fn leave(&self, guid: &models::ChatGuid) -> Result<()> {
+        let req = self.inner.http.post(format!(
+            "{}/api/v1/chat/{}/leave?password={}",
+            self.inner.server_url, guid, self.inner.password
+        ));
+        self.inner.request(req).await
+    }
+
+    /// Delete a chat and its messages from the server's Mac. Requires the Private API.
+    #[builder(finish_fn(name = send))]
+    pub async fn delete(&self, guid: &models::ChatGuid) -> Result<()> {
+        let req = self.inner.http.delete(format!(
+            "{}/api/v1/chat/{}?password={}",
+            self.inner.server_url, guid, self.inner.password
+        ));
+        self.inner.request(req).await
+    }
 }
 
 // Custom builder methods
-#[allow(unused)]
 impl<'f1, 'f2, S: chat_get_builder::State> ChatGetBuilder<'f1, 'f2, S> {
-    fn with_last_message(mut self) -> Self {
+    pub fn with_last_message(mut self) -> Self {
         self.with_last_message = true;
         self
     }
-    fn with_participants(mut self) -> Self {
+    pub fn with_participants(mut self) -> Self {
         self.with_participants = true;
         self
     }
 }
-#[allow(unused)]
 impl<'f1, 'f2, S: chat_get_messages_builder::State> ChatGetMessagesBuilder<'f1, 'f2, S> {
-    fn with_attachment(mut self) -> Self {
+    pub fn with_attachment(mut self) -> Self {
+        self.with_attachment = true;
+        self
+    }
+    pub fn with_handle(mut self) -> Self {
+        self.with_handle = true;
+        self
+    }
+    pub fn with_sms(mut self) -> Self {
+        self.with_sms = true;
+        self
+    }
+    pub fn with_attributed_body(mut self) -> Self {
+        self.with_attributed_body = true;
+        self
+    }
+}
+impl<'f1, 'f2, S: chat_get_messages_stream_builder::State>
+    ChatGetMessagesStreamBuilder<'f1, 'f2, S>
+{
+    pub fn with_attachment(mut self) -> Self {
         self.with_attachment = true;
         self
     }
-    fn with_handle(mut self) -> Self {
+    pub fn with_handle(mut self) -> Self {
         self.with_handle = true;
         self
     }
-    fn with_sms(mut self) -> Self {
+    pub fn with_sms(mut self) -> Self {
         self.with_sms = true;
         self
     }
+    pub fn with_attributed_body(mut self) -> Self {
+        self.with_attributed_body = true;
+        self
+    }
 }
-#[allow(unused)]
-impl<'f1, S: chat_query_builder::State> ChatQueryBuilder<'f1, S> {
-    fn with_last_message(mut self) -> Self {
+impl<'f1, 'f2, S: chat_query_builder::State> ChatQueryBuilder<'f1, 'f2, S> {
+    pub fn with_last_message(mut self) -> Self {
         self.with_last_message = true;
         self
     }
-    fn with_participants(mut self) -> Self {
+    pub fn with_participants(mut self) -> Self {
         self.with_participants = true;
         self
     }
-    fn with_sms(mut self) -> Self {
+    pub fn with_sms(mut self) -> Self {
         self.with_sms = true;
         self
     }
-    fn with_archived(mut self) -> Self {
+    pub fn with_archived(mut self) -> Self {
         self.with_archived = true;
         self
     }
-    fn with_message_attributed_body(mut self) -> Self {
+    pub fn with_message_attributed_body(mut self) -> Self {
         self.with_message_attributed_body = true;
         self
     }
-    fn with_message_info_summary(mut self) -> Self {
+    pub fn with_message_info_summary(mut self) -> Self {
         self.with_message_info_summary = true;
         self
     }
-    fn with_message_payload_data(mut self) -> Self {
+    pub fn with_message_payload_data(mut self) -> Self {
         self.with_message_payload_data = true;
         self
     }
 }
+impl<'f1, 'f2, S: chat_query_stream_builder::State> ChatQueryStreamBuilder<'f1, 'f2, S> {
+    pub fn with_last_message(mut self) -> Self {
+        self.with_last_message = true;
+        self
+    }
+    pub fn with_participants(mut self) -> Self {
+        self.with_participants = true;
+        self
+    }
+    pub fn with_sms(mut self) -> Self {
+        self.with_sms = true;
+        self
+    }
+    pub fn with_archived(mut self) -> Self {
+        self.with_archived = true;
+        self
+    }
+    pub fn with_message_attributed_body(mut self) -> Self {
+        self.with_message_attributed_body = true;
+        self
+    }
+    pub fn with_message_info_summary(mut self) -> Self {
+        self.with_message_info_summary = true;
+        self
+    }
+    pub fn with_message_payload_data(mut self) -> Self {
+        self.with_message_payload_data = true;
+        self
+    }
+}
+
+/// The options shared by [`Chat::get_messages`] and [`Chat::get_messages_stream`]
+struct MessageQuery {
+    inner: Arc<ClientInner>,
+    guid: models::ChatGuid,
+    with: Vec<&'static str>,
+}
+
+impl MessageQuery {
+    async fn page(
+        &self,
+        after: Option<i64>,
+        before: Option<i64>,
+        limit: Option<u32>,
+        offset: Option<u32>,
+        sort: Option<models::SortOrder>,
+    ) -> Result<Vec<models::Message>> {
+        let with = if self.with.is_empty() {
+            None
+        } else {
+            Some(self.with.join(","))
+        };
+        let req = self
+            .inner
+            .http
+            .get(format!(
+                "{}/api/v1/chat/{}/message?password={}",
+                self.inner.server_url, self.guid, self.inner.password
+            ))
+            .query(&[
+                ("with", with),
+                ("after", after.map(|d| d.to_string())),
+                ("before", before.map(|d| d.to_string())),
+                ("limit", limit.map(|l| l.to_string())),
+                ("offset", offset.map(|o| o.to_string())),
+                ("sort", sort.map(|s| s.name().to_string())),
+            ]);
+        self.inner.request_data(req).await
+    }
+}
+
+/// Where [`Chat::get_messages_stream`] is in a chat's history
+struct MessageCursor {
+    query: MessageQuery,
+    sort: models::SortOrder,
+    page_size: u32,
+    /// The bounds of the whole stream in milliseconds
+    after: Option<i64>,
+    before: Option<i64>,
+    /// Date of the last message returned
+    date: Option<i64>,
+    /// Messages returned from `date`, the next page starts there again in case more
+    /// were sent in the same millisecond
+    seen: HashSet<String>,
+    done: bool,
+}
+
+impl MessageCursor {
+    /// The next messages in order, `None` at the end of the history
+    async fn next_page(&mut self) -> Result<Option<Vec<models::Message>>> {
+        while !self.done {
+            // Bounds are exclusive, step one millisecond back onto `date`
+            let (after, before) = match (self.sort, self.date) {
+                (models::SortOrder::Descending, Some(date)) => (self.after, Some(date + 1)),
+                (models::SortOrder::Ascending, Some(date)) => (Some(date - 1), self.before),
+                (_, None) => (self.after, self.before),
+            };
+            let page = self
+                .query
+                .page(after, before, Some(self.page_size), None, Some(self.sort))
+                .await?;
+            self.done = page.is_empty() || page.len() < self.page_size as usize;
+
+            let fresh: Vec<_> = page
+                .into_iter()
+                .filter(|message| !self.seen.contains(&message.guid))
+                .collect();
+            let Some(last) = fresh.last() else {
+                // A full page from a single millisecond, move past it
+                if let Some(date) = &mut self.date {
+                    *date += match self.sort {
+                        models::SortOrder::Descending => -1,
+                        models::SortOrder::Ascending => 1,
+                    };
+                }
+                self.seen.clear();
+                continue;
+            };
+
+            if self.date != Some(last.date_created) {
+                self.date = Some(last.date_created);
+                self.seen.clear();
+            }
+            self.seen.extend(
+                fresh
+                    .iter()
+                    .filter(|message| message.date_created == last.date_created)
+                    .map(|message| message.guid.clone()),
+            );
+            return Ok(Some(fresh));
+        }
+        Ok(None)
+    }
+}
+
+/// The options shared by [`Chat::query`] and [`Chat::query_stream`]
+struct ChatQuery {
+    inner: Arc<ClientInner>,
+    with: Vec<&'static str>,
+    sort: Option<models::ChatSort>,
+    archived: Option<bool>,
+    service: Option<models::Service>,
+    guids: Option<Vec<models::ChatGuid>>,
+}
+
+impl ChatQuery {
+    /// Fetch a page, returning how many chats the server sent along with the ones
+    /// that pass the filters
+    async fn page(
+        &self,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<(usize, Vec<models::Chat>)> {
+        let mut with = self.with.clone();
+        // Archived chats are left out unless asked for
+        if self.archived.is_some() && !with.contains(&"archived") {
+            with.push("archived");
+        }
+        let req = self
+            .inner
+            .http
+            .post(format!(
+                "{}/api/v1/chat/query?password={}",
+                self.inner.server_url, self.inner.password
+            ))
+            .json(&json!({
+                "with": with,
+                "limit": limit,
+                "offset": offset,
+                "sort": self.sort.map(|sort| sort.name().to_string()),
+                "order": self.sort.map(|sort| sort.order().name().to_string()),
+            }));
+        let chats: Vec<models::Chat> = self.inner.request_data(req).await?;
+        let fetched = chats.len();
+        Ok((
+            fetched,
+            chats
+                .into_iter()
+                .filter(|chat| self.matches(chat))
+                .collect(),
+        ))
+    }
+
+    fn matches(&self, chat: &models::Chat) -> bool {
+        self.archived
+            .is_none_or(|archived| chat.is_archived == archived)
+            && self
+                .service
+                .as_ref()
+                .is_none_or(|service| chat.guid.service() == *service)
+            && self
+                .guids
+                .as_ref()
+                .is_none_or(|guids| guids.contains(&chat.guid))
+    }
+}
 
 #[cfg(test)]
 mod tests {
+    use chrono::DateTime;
+    use futures_util::TryStreamExt;
+    use httpmock::{Mock, prelude::*};
+
     use crate::client::Client;
-    use crate::client::tests::get_test_client;
+    use crate::client::tests::{get_mock_client, get_test_client};
+    use crate::error::Error;
+    use crate::models::{ChatGuid, ChatSort, ChatStyle, Service, SortOrder};
+
+    fn guid(guid: &str) -> ChatGuid {
+        guid.parse().unwrap()
+    }
 
     #[tokio::test]
     async fn query() {
@@ -228,10 +750,512 @@ mod tests {
         let res = client
             .chats()
             .get_messages()
-            .guid("SMS;-;+12023896015")
+            .guid(&guid("SMS;-;+12023896015"))
             .send()
             .await;
 
         res.expect("Failed to get messages");
     }
+
+    #[tokio::test]
+    async fn mark_read() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/api/v1/chat/iMessage;-;+12025550123/read");
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(r#"{"status": 200, "message": "Successfully marked chat as read!"}"#);
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        client
+            .chats()
+            .mark_read()
+            .guid(&guid("iMessage;-;+12025550123"))
+            .send()
+            .await
+            .expect("Failed to mark chat as read");
+
+        mock.assert_async().await;
+    }
+
+    /// A mock for a request answered with a message and no data
+    async fn mock_ok<'a>(server: &'a MockServer, method: Method, path: &str) -> Mock<'a> {
+        server
+            .mock_async(|when, then| {
+                when.method(method).path(path);
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(r#"{"status": 200, "message": "Success"}"#);
+            })
+            .await
+    }
+
+    #[tokio::test]
+    async fn create() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST).path("/api/v1/chat/new").json_body_includes(
+                    r#"{"addresses": ["+12025550123", "ann@example.com"], "message": "Hi all", "service": "iMessage"}"#,
+                );
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(include_str!("../../test_data/chat.json"));
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let chat = client
+            .chats()
+            .create()
+            .addresses(&["+12025550123", "ann@example.com"])
+            .message("Hi all")
+            .service(Service::IMessage)
+            .send()
+            .await
+            .expect("Failed to create chat");
+
+        mock.assert_async().await;
+        assert_eq!(chat.guid, "iMessage;+;chat482910376451");
+        assert_eq!(chat.style, ChatStyle::Group);
+        assert_eq!(chat.participants.len(), 2);
+        assert_eq!(chat.participants[0].service, Service::IMessage);
+    }
+
+    #[tokio::test]
+    async fn query_filters() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/api/v1/chat/query")
+                    .json_body_includes(
+                        r#"{"with": ["participants", "archived"], "sort": "lastmessage", "order": "DESC"}"#,
+                    );
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(include_str!("../../test_data/chat_list.json"));
+            })
+            .await;
+        let client = get_mock_client(&server);
+        let chats = client.chats();
+        let query = |archived: bool| {
+            chats
+                .query()
+                .with_participants()
+                .sort(ChatSort::LastMessage(SortOrder::Descending))
+                .archived(archived)
+        };
+
+        let unarchived = query(false).send().await.expect("Failed to query chats");
+        assert_eq!(unarchived.len(), 2);
+        assert!(unarchived.iter().all(|chat| !chat.is_archived));
+
+        let sms = query(true)
+            .service(Service::Sms)
+            .send()
+            .await
+            .expect("Failed to query chats");
+        assert_eq!(sms.len(), 1);
+        assert_eq!(sms[0].guid, "SMS;-;+12025550187");
+
+        let listed = query(false)
+            .guids(&[
+                guid("iMessage;-;ann@example.com"),
+                guid("SMS;-;+12025550187"),
+            ])
+            .send()
+            .await
+            .expect("Failed to query chats");
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].guid, "iMessage;-;ann@example.com");
+
+        mock.assert_calls_async(3).await;
+    }
+
+    #[tokio::test]
+    async fn query_fills_limit() {
+        let server = MockServer::start_async().await;
+        let page = |offset: u32| {
+            server.mock_async(move |when, then| {
+                when.method(POST)
+                    .path("/api/v1/chat/query")
+                    .json_body_includes(format!(r#"{{"limit": 2, "offset": {offset}}}"#));
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(include_str!("../../test_data/chat_list.json"));
+            })
+        };
+        // Each page holds a single SMS chat
+        let first = page(0).await;
+        let second = page(3).await;
+        let client = get_mock_client(&server);
+
+        let chats = client
+            .chats()
+            .query()
+            .limit(2)
+            .service(Service::Sms)
+            .send()
+            .await
+            .expect("Failed to query chats");
+
+        first.assert_async().await;
+        second.assert_async().await;
+        assert_eq!(chats.len(), 2);
+        assert!(chats.iter().all(|chat| chat.guid.service() == Service::Sms));
+    }
+
+    #[tokio::test]
+    async fn query_sort() {
+        let server = MockServer::start_async().await;
+        let mock = |order: &'static str| {
+            server.mock_async(move |when, then| {
+                when.method(POST)
+                    .path("/api/v1/chat/query")
+                    .json_body_includes(format!(
+                        r#"{{"sort": "lastmessage", "order": "{order}"}}"#
+                    ));
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(include_str!("../../test_data/chat_list.json"));
+            })
+        };
+        let newest_first = mock("DESC").await;
+        let oldest_first = mock("ASC").await;
+        let client = get_mock_client(&server);
+
+        for order in [SortOrder::Descending, SortOrder::Ascending] {
+            client
+                .chats()
+                .query()
+                .sort(ChatSort::LastMessage(order))
+                .send()
+                .await
+                .expect("Failed to query chats");
+        }
+
+        newest_first.assert_async().await;
+        oldest_first.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn query_stream() {
+        let server = MockServer::start_async().await;
+        let first = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/api/v1/chat/query")
+                    .json_body_includes(r#"{"limit": 3, "offset": 0}"#);
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(include_str!("../../test_data/chat_list.json"));
+            })
+            .await;
+        let last = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/api/v1/chat/query")
+                    .json_body_includes(r#"{"limit": 3, "offset": 3}"#);
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(r#"{"status": 200, "message": "Success", "data": []}"#);
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let chats: Vec<_> = client
+            .chats()
+            .query_stream()
+            .page_size(3)
+            .service(Service::IMessage)
+            .send()
+            .try_collect()
+            .await
+            .expect("Failed to query chats");
+
+        first.assert_async().await;
+        last.assert_async().await;
+        let guids: Vec<_> = chats.iter().map(|chat| chat.guid.as_str()).collect();
+        assert_eq!(
+            guids,
+            ["iMessage;+;chat482910376451", "iMessage;-;ann@example.com"]
+        );
+    }
+
+    /// Copies of the message fixture with the given GUIDs and dates
+    fn message_page(messages: &[(&str, i64)]) -> String {
+        let mut page: serde_json::Value =
+            serde_json::from_str(include_str!("../../test_data/message_list.json")).unwrap();
+        let template = page["data"][0].clone();
+        page["data"] = messages
+            .iter()
+            .map(|&(guid, date)| {
+                let mut message = template.clone();
+                message["guid"] = guid.into();
+                message["dateCreated"] = date.into();
+                message
+            })
+            .collect();
+        page.to_string()
+    }
+
+    async fn mock_page<'a>(
+        server: &'a MockServer,
+        bound: (&str, Option<&str>),
+        messages: &[(&str, i64)],
+    ) -> Mock<'a> {
+        let body = message_page(messages);
+        server
+            .mock_async(|when, then| {
+                let when = when
+                    .method(GET)
+                    .path("/api/v1/chat/iMessage;-;ann@example.com/message")
+                    .query_param("limit", "2");
+                match bound {
+                    (name, Some(value)) => when.query_param(name, value),
+                    (name, None) => when.query_param_missing(name),
+                };
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(body);
+            })
+            .await
+    }
+
+    #[tokio::test]
+    async fn get_messages_stream() {
+        let server = MockServer::start_async().await;
+        let first = mock_page(&server, ("before", None), &[("c", 300), ("b", 200)]).await;
+        // "a" was sent in the same millisecond as "b", so the page is asked for twice
+        let same = mock_page(&server, ("before", Some("201")), &[("b", 200), ("a", 200)]).await;
+        let last = mock_page(&server, ("before", Some("200")), &[("z", 100)]).await;
+        let client = get_mock_client(&server);
+
+        let messages: Vec<_> = client
+            .chats()
+            .get_messages_stream()
+            .guid(&guid("iMessage;-;ann@example.com"))
+            .page_size(2)
+            .send()
+            .try_collect()
+            .await
+            .expect("Failed to get messages");
+
+        first.assert_async().await;
+        same.assert_calls_async(2).await;
+        last.assert_async().await;
+        let guids: Vec<_> = messages
+            .iter()
+            .map(|message| message.guid.as_str())
+            .collect();
+        assert_eq!(guids, ["c", "b", "a", "z"]);
+    }
+
+    #[tokio::test]
+    async fn get_messages_stream_ascending() {
+        let server = MockServer::start_async().await;
+        let first = mock_page(&server, ("after", Some("50")), &[("a", 100), ("b", 200)]).await;
+        let second = mock_page(&server, ("after", Some("199")), &[("b", 200), ("c", 300)]).await;
+        let last = mock_page(&server, ("after", Some("299")), &[("c", 300)]).await;
+        let client = get_mock_client(&server);
+
+        let messages: Vec<_> = client
+            .chats()
+            .get_messages_stream()
+            .guid(&guid("iMessage;-;ann@example.com"))
+            .after(DateTime::from_timestamp_millis(50).unwrap())
+            .page_size(2)
+            .sort(SortOrder::Ascending)
+            .send()
+            .try_collect()
+            .await
+            .expect("Failed to get messages");
+
+        first.assert_async().await;
+        second.assert_async().await;
+        last.assert_async().await;
+        let guids: Vec<_> = messages
+            .iter()
+            .map(|message| message.guid.as_str())
+            .collect();
+        assert_eq!(guids, ["a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn rename() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(PUT)
+                    .path("/api/v1/chat/iMessage;+;chat482910376451")
+                    .json_body_includes(r#"{"displayName": "Weekend Plans"}"#);
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(include_str!("../../test_data/chat.json"));
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let chat = client
+            .chats()
+            .rename()
+            .guid(&guid("iMessage;+;chat482910376451"))
+            .display_name("Weekend Plans")
+            .send()
+            .await
+            .expect("Failed to rename chat");
+
+        mock.assert_async().await;
+        assert_eq!(chat.display_name, "Weekend Plans");
+    }
+
+    #[tokio::test]
+    async fn participants() {
+        let server = MockServer::start_async().await;
+        let add = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/api/v1/chat/iMessage;+;chat482910376451/participant/add")
+                    .json_body_includes(r#"{"address": "ann@example.com"}"#);
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(include_str!("../../test_data/chat.json"));
+            })
+            .await;
+        let remove = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/api/v1/chat/iMessage;+;chat482910376451/participant/remove")
+                    .json_body_includes(r#"{"address": "ann@example.com"}"#);
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(include_str!("../../test_data/chat.json"));
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        client
+            .chats()
+            .add_participant()
+            .guid(&guid("iMessage;+;chat482910376451"))
+            .address("ann@example.com")
+            .send()
+            .await
+            .expect("Failed to add participant");
+        client
+            .chats()
+            .remove_participant()
+            .guid(&guid("iMessage;+;chat482910376451"))
+            .address("ann@example.com")
+            .send()
+            .await
+            .expect("Failed to remove participant");
+
+        add.assert_async().await;
+        remove.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn set_icon() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/api/v1/chat/iMessage;+;chat482910376451/icon")
+                    .body_includes("name=\"icon\"");
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(r#"{"status": 200, "message": "Successfully set group chat icon!"}"#);
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("test_data/chat.json");
+        client
+            .chats()
+            .set_icon()
+            .guid(&guid("iMessage;+;chat482910376451"))
+            .path(&path)
+            .send()
+            .await
+            .expect("Failed to set icon");
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn remove_icon_leave_and_delete() {
+        let server = MockServer::start_async().await;
+        let remove_icon = mock_ok(
+            &server,
+            DELETE,
+            "/api/v1/chat/iMessage;+;chat482910376451/icon",
+        )
+        .await;
+        let leave = mock_ok(
+            &server,
+            POST,
+            "/api/v1/chat/iMessage;+;chat482910376451/leave",
+        )
+        .await;
+        let delete = mock_ok(&server, DELETE, "/api/v1/chat/iMessage;+;chat482910376451").await;
+        let client = get_mock_client(&server);
+        let chats = client.chats();
+        let chat_guid = guid("iMessage;+;chat482910376451");
+
+        chats
+            .remove_icon()
+            .guid(&chat_guid)
+            .send()
+            .await
+            .expect("Failed to remove icon");
+        chats
+            .leave()
+            .guid(&chat_guid)
+            .send()
+            .await
+            .expect("Failed to leave chat");
+        chats
+            .delete()
+            .guid(&chat_guid)
+            .send()
+            .await
+            .expect("Failed to delete chat");
+
+        remove_icon.assert_async().await;
+        leave.assert_async().await;
+        delete.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn typing_without_helper() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(DELETE)
+                    .path("/api/v1/chat/iMessage;-;+12025550123/typing");
+                then.status(400)
+                    .header("content-type", "application/json")
+                    .body(
+                        r#"{"status": 400, "message": "Bad Request", "error": {"type": "Validation Error", "message": "iMessage Private API Helper is not connected!"}}"#,
+                    );
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let result = client
+            .chats()
+            .stop_typing()
+            .guid(&guid("iMessage;-;+12025550123"))
+            .send()
+            .await;
+
+        mock.assert_async().await;
+        assert!(matches!(result, Err(Error::PrivateApiDisabled { .. })));
+    }
 }
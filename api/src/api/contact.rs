@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use bon::bon;
+use serde_json::json;
+
+use crate::{client::ClientInner, error::Result, models, utils::build_option_list};
+
+pub struct Contact {
+    pub(crate) inner: Arc<ClientInner>,
+}
+
+#[bon]
+impl Contact {
+    pub(crate) fn new(inner: Arc<ClientInner>) -> Self {
+        Self { inner }
+    }
+
+    /// All contacts in the server's address book
+    #[builder(finish_fn(name = send))]
+    pub async fn get(&self, #[builder(field)] with_avatars: bool) -> Result<Vec<models::Contact>> {
+        let extra_properties = build_option_list! {
+            with_avatars => "avatar",
+        }
+        .join(",");
+        let extra_properties = if extra_properties.is_empty() {
+            None
+        } else {
+            Some(extra_properties)
+        };
+        let req = self
+            .inner
+            .http
+            .get(format!(
+                "{}/api/v1/contact?password={}",
+                self.inner.server_url, self.inner.password
+            ))
+            .query(&[("extraProperties", extra_properties)]);
+        self.inner.request_data(req).await
+    }
+
+    /// Contacts owning any of `addresses`
+    #[builder(finish_fn(name = send))]
+    pub async fn query(&self, addresses: &[&str]) -> Result<Vec<models::Contact>> {
+        let req = self
+            .inner
+            .http
+            .post(format!(
+                "{}/api/v1/contact/query?password={}",
+                self.inner.server_url, self.inner.password
+            ))
+            .json(&json!({ "addresses": addresses }));
+        self.inner.request_data(req).await
+    }
+}
+
+impl<'f1, S: contact_get_builder::State> ContactGetBuilder<'f1, S> {
+    pub fn with_avatars(mut self) -> Self {
+        self.with_avatars = true;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+
+    use crate::client::tests::get_mock_client;
+
+    #[tokio::test]
+    async fn get() {
+        let server = MockServer::start_async().await;
+        let with_avatars = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/api/v1/contact")
+                    .query_param("extraProperties", "avatar");
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(include_str!("../../test_data/contact_list.json"));
+            })
+            .await;
+        let without_avatars = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/api/v1/contact")
+                    .query_param_missing("extraProperties");
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(include_str!("../../test_data/contact_list.json"));
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        client
+            .contacts()
+            .get()
+            .send()
+            .await
+            .expect("Failed to get contacts");
+        without_avatars.assert_async().await;
+
+        let contacts = client
+            .contacts()
+            .get()
+            .with_avatars()
+            .send()
+            .await
+            .expect("Failed to get contacts");
+
+        with_avatars.assert_async().await;
+        assert_eq!(contacts.len(), 2);
+        assert_eq!(contacts[0].name().as_deref(), Some("Jane Doe"));
+        assert_eq!(contacts[0].phone_numbers[0].address, "+12025550123");
+        assert!(contacts[0].avatar.is_some());
+        assert_eq!(contacts[1].name().as_deref(), Some("Bob"));
+    }
+
+    #[tokio::test]
+    async fn query() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/api/v1/contact/query")
+                    .json_body_includes(r#"{"addresses": ["+12025550123"]}"#);
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(include_str!("../../test_data/contact_list.json"));
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let contacts = client
+            .contacts()
+            .query()
+            .addresses(&["+12025550123"])
+            .send()
+            .await
+            .expect("Failed to query contacts");
+
+        mock.assert_async().await;
+        assert!(!contacts.is_empty());
+    }
+}
@@ -0,0 +1,217 @@
+use std::sync::Arc;
+
+use bon::bon;
+use serde_json::json;
+
+use crate::{client::ClientInner, error::Result, models};
+
+pub struct Handle {
+    pub(crate) inner: Arc<ClientInner>,
+}
+
+#[bon]
+impl Handle {
+    pub(crate) fn new(inner: Arc<ClientInner>) -> Self {
+        Self { inner }
+    }
+
+    /// List handles, optionally only those matching `address`
+    #[builder(finish_fn(name = send))]
+    pub async fn query(
+        &self,
+        address: Option<&str>,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<Vec<models::Handle>> {
+        let req = self
+            .inner
+            .http
+            .post(format!(
+                "{}/api/v1/handle/query?password={}",
+                self.inner.server_url, self.inner.password
+            ))
+            .json(&json!({
+                "address": address,
+                "limit": limit,
+                "offset": offset,
+            }));
+        self.inner.request_data(req).await
+    }
+
+    #[builder(finish_fn(name = send))]
+    pub async fn get(&self, guid: &str) -> Result<models::Handle> {
+        let req = self.inner.http.get(format!(
+            "{}/api/v1/handle/{}?password={}",
+            self.inner.server_url, guid, self.inner.password
+        ));
+        self.inner.request_data(req).await
+    }
+
+    #[builder(finish_fn(name = send))]
+    pub async fn get_count(&self) -> Result<models::HandleCount> {
+        let req = self.inner.http.get(format!(
+            "{}/api/v1/handle/count?password={}",
+            self.inner.server_url, self.inner.password
+        ));
+        self.inner.request_data(req).await
+    }
+
+    /// Whether the contact behind `address` has a Focus mode silencing notifications.
+    ///
+    /// Requires the Private API, [`models::FocusStatus::Unknown`] is returned otherwise.
+    #[builder(finish_fn(name = send))]
+    pub async fn get_focus_status(&self, address: &str) -> Result<models::FocusStatus> {
+        let req = self.inner.http.get(format!(
+            "{}/api/v1/handle/{}/focus?password={}",
+            self.inner.server_url, address, self.inner.password
+        ));
+        let focus: models::HandleFocus = self.inner.request_data(req).await?;
+        Ok(focus.status)
+    }
+
+    /// Whether `address` is registered with iMessage
+    #[builder(finish_fn(name = send))]
+    pub async fn get_imessage_availability(&self, address: &str) -> Result<bool> {
+        self.availability("imessage", address).await
+    }
+
+    /// Whether `address` is registered with FaceTime
+    #[builder(finish_fn(name = send))]
+    pub async fn get_facetime_availability(&self, address: &str) -> Result<bool> {
+        self.availability("facetime", address).await
+    }
+}
+
+impl Handle {
+    async fn availability(&self, service: &str, address: &str) -> Result<bool> {
+        let req = self
+            .inner
+            .http
+            .get(format!(
+                "{}/api/v1/handle/availability/{}?password={}",
+                self.inner.server_url, service, self.inner.password
+            ))
+            .query(&[("address", address)]);
+        let availability: models::HandleAvailability = self.inner.request_data(req).await?;
+        Ok(availability.available)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+
+    use crate::{client::tests::get_mock_client, models::FocusStatus};
+
+    #[tokio::test]
+    async fn query() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/api/v1/handle/query")
+                    .json_body_includes(r#"{"limit": 100}"#);
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(include_str!("../../test_data/handle_list.json"));
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let handles = client
+            .handles()
+            .query()
+            .limit(100)
+            .send()
+            .await
+            .expect("Failed to query handles");
+
+        mock.assert_async().await;
+        assert_eq!(handles.len(), 2);
+        assert_eq!(handles[1].address, "jane@example.com");
+    }
+
+    #[tokio::test]
+    async fn get() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/api/v1/handle/iMessage;-;+12025550123");
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(include_str!("../../test_data/handle.json"));
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let handle = client
+            .handles()
+            .get()
+            .guid("iMessage;-;+12025550123")
+            .send()
+            .await
+            .expect("Failed to get handle");
+
+        mock.assert_async().await;
+        assert_eq!(handle.address, "+12025550123");
+        assert_eq!(
+            handle.formatted_address.as_deref(),
+            Some("+1 (202) 555-0123")
+        );
+    }
+
+    #[tokio::test]
+    async fn focus_status() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/v1/handle/+12025550123/focus");
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(
+                        r#"{"status": 200, "message": "Success", "data": {"status": "silenced"}}"#,
+                    );
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let status = client
+            .handles()
+            .get_focus_status()
+            .address("+12025550123")
+            .send()
+            .await
+            .expect("Failed to get focus status");
+
+        mock.assert_async().await;
+        assert_eq!(status, FocusStatus::Silenced);
+    }
+
+    #[tokio::test]
+    async fn imessage_availability() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/api/v1/handle/availability/imessage")
+                    .query_param("address", "jane@example.com");
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(r#"{"status": 200, "message": "Success", "data": {"available": true}}"#);
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let available = client
+            .handles()
+            .get_imessage_availability()
+            .address("jane@example.com")
+            .send()
+            .await
+            .expect("Failed to check availability");
+
+        mock.assert_async().await;
+        assert!(available);
+    }
+}
@@ -0,0 +1,568 @@
+use std::sync::Arc;
+
+use bon::bon;
+use chrono::{DateTime, Utc};
+use serde_json::json;
+
+use crate::{client::ClientInner, error::Result, models, utils::build_option_list};
+
+pub struct Message {
+    pub(crate) inner: Arc<ClientInner>,
+}
+
+#[bon]
+impl Message {
+    pub(crate) fn new(inner: Arc<ClientInner>) -> Self {
+        Self { inner }
+    }
+
+    #[builder(finish_fn(name = send))]
+    pub async fn get(
+        &self,
+        #[builder(field)] with_chats: bool,
+        #[builder(field)] with_chat_participants: bool,
+        #[builder(field)] with_attachment: bool,
+        #[builder(field)] with_handle: bool,
+        guid: &str,
+    ) -> Result<models::Message> {
+        let with_str = build_option_list! {
+            with_chats => "chats",
+            with_chat_participants => "chats.participants",
+            with_attachment => "attachment",
+            with_handle => "handle",
+        }
+        .join(",");
+        let with = if with_str.is_empty() {
+            None
+        } else {
+            Some(with_str)
+        };
+        let req = self
+            .inner
+            .http
+            .get(format!(
+                "{}/api/v1/message/{}?password={}",
+                self.inner.server_url, guid, self.inner.password
+            ))
+            .query(&[("with", with)]);
+        self.inner.request_data(req).await
+    }
+
+    #[builder(finish_fn(name = send))]
+    pub async fn query(
+        &self,
+        #[builder(field)] with_chat: bool,
+        #[builder(field)] with_chat_participants: bool,
+        #[builder(field)] with_attachment: bool,
+        #[builder(field)] with_handle: bool,
+        #[builder(field)] with_attributed_body: bool,
+        #[builder(field)] with_message_summary_info: bool,
+        #[builder(field)] with_payload_data: bool,
+        chat_guid: Option<&models::ChatGuid>,
+        after: Option<DateTime<Utc>>,
+        before: Option<DateTime<Utc>>,
+        limit: Option<u32>,
+        offset: Option<u32>,
+        sort: Option<models::SortOrder>,
+        #[builder(default)] filters: Vec<models::QueryFilter>,
+    ) -> Result<Vec<models::Message>> {
+        let with = build_option_list! {
+            with_chat => "chat",
+            with_chat_participants => "chat.participants",
+            with_attachment => "attachment",
+            with_handle => "handle",
+            with_attributed_body => "attributedBody",
+            with_message_summary_info => "messageSummaryInfo",
+            with_payload_data => "payloadData",
+        };
+        let req = self
+            .inner
+            .http
+            .post(format!(
+                "{}/api/v1/message/query?password={}",
+                self.inner.server_url, self.inner.password
+            ))
+            .json(&json!({
+                "with": with,
+                "where": filters,
+                "chatGuid": chat_guid,
+                "after": after.map(|d| d.timestamp_millis()),
+                "before": before.map(|d| d.timestamp_millis()),
+                "limit": limit,
+                "offset": offset,
+                "sort": sort.map(|s| s.name().to_string()),
+            }));
+        self.inner.request_data(req).await
+    }
+
+    /// Search the text of the messages on the server, newest first. Includes the chat,
+    /// sender and attachments of each message.
+    ///
+    /// Only the plain `text` is searched, not the attributed body.
+    #[builder(finish_fn(name = send))]
+    pub async fn search(
+        &self,
+        term: &str,
+        chat_guid: Option<&models::ChatGuid>,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<Vec<models::Message>> {
+        // `%` and `_` in the term are matched literally
+        let pattern = format!(
+            "%{}%",
+            term.replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        self.query()
+            .with_chat()
+            .with_chat_participants()
+            .with_attachment()
+            .with_handle()
+            .filters(vec![
+                models::QueryFilter::new("message.text LIKE :term ESCAPE '\\'")
+                    .arg("term", pattern),
+            ])
+            .maybe_chat_guid(chat_guid)
+            .maybe_limit(limit)
+            .maybe_offset(offset)
+            .sort(models::SortOrder::Descending)
+            .send()
+            .await
+    }
+
+    #[builder(finish_fn(name = send))]
+    pub async fn count(
+        &self,
+        after: Option<DateTime<Utc>>,
+        before: Option<DateTime<Utc>>,
+    ) -> Result<models::MessageCount> {
+        let req = self
+            .inner
+            .http
+            .get(format!(
+                "{}/api/v1/message/count?password={}",
+                self.inner.server_url, self.inner.password
+            ))
+            .query(&[
+                ("after", after.map(|d| d.timestamp_millis().to_string())),
+                ("before", before.map(|d| d.timestamp_millis().to_string())),
+            ]);
+        self.inner.request_data(req).await
+    }
+
+    /// Count messages that were updated (delivered, read, edited, ...) after a given date
+    #[builder(finish_fn(name = send))]
+    pub async fn get_updated_count(
+        &self,
+        after: DateTime<Utc>,
+        before: Option<DateTime<Utc>>,
+    ) -> Result<models::MessageCount> {
+        let req = self
+            .inner
+            .http
+            .get(format!(
+                "{}/api/v1/message/count/updated?password={}",
+                self.inner.server_url, self.inner.password
+            ))
+            .query(&[
+                ("after", Some(after.timestamp_millis().to_string())),
+                ("before", before.map(|d| d.timestamp_millis().to_string())),
+            ]);
+        self.inner.request_data(req).await
+    }
+
+    /// Count messages sent by the account signed in on the server
+    #[builder(finish_fn(name = send))]
+    pub async fn get_my_count(&self) -> Result<models::MessageCount> {
+        let req = self.inner.http.get(format!(
+            "{}/api/v1/message/count/me?password={}",
+            self.inner.server_url, self.inner.password
+        ));
+        self.inner.request_data(req).await
+    }
+
+    /// Send a plain text message.
+    ///
+    /// `temp_guid` is a client generated identifier the server echoes back on the
+    /// resulting message, so an optimistic local copy can be matched to it.
+    #[builder(finish_fn(name = send))]
+    pub async fn send_text(
+        &self,
+        chat_guid: &models::ChatGuid,
+        temp_guid: &str,
+        message: &str,
+        method: Option<models::SendMethod>,
+        subject: Option<&str>,
+        effect_id: Option<&str>,
+        selected_message_guid: Option<&str>,
+        part_index: Option<u32>,
+    ) -> Result<models::Message> {
+        let req = self
+            .inner
+            .http
+            .post(format!(
+                "{}/api/v1/message/text?password={}",
+                self.inner.server_url, self.inner.password
+            ))
+            .json(&json!({
+                "chatGuid": chat_guid,
+                "tempGuid": temp_guid,
+                "message": message,
+                "method": method,
+                "subject": subject,
+                "effectId": effect_id,
+                "selectedMessageGuid": selected_message_guid,
+                "partIndex": part_index,
+            }));
+        self.inner.request_data(req).await
+    }
+
+    /// Add or remove a tapback on a message, returning the reaction message.
+    ///
+    /// Requires the Private API.
+    #[builder(finish_fn(name = send))]
+    pub async fn react(
+        &self,
+        chat_guid: &models::ChatGuid,
+        /// The message being reacted to
+        message_guid: &str,
+        reaction: models::Reaction,
+        /// Take back an earlier `reaction` instead of adding it
+        #[builder(default)]
+        remove: bool,
+        part_index: Option<u32>,
+    ) -> Result<models::Message> {
+        let tapback = models::Tapback {
+            reaction,
+            removed: remove,
+        };
+        let req = self
+            .inner
+            .http
+            .post(format!(
+                "{}/api/v1/message/react?password={}",
+                self.inner.server_url, self.inner.password
+            ))
+            .json(&json!({
+                "chatGuid": chat_guid,
+                "selectedMessageGuid": message_guid,
+                "reaction": tapback.associated_type(),
+                "partIndex": part_index,
+            }));
+        self.inner.request_data(req).await
+    }
+
+    /// Replace the text of a sent message.
+    ///
+    /// `fallback_text` is shown to recipients on devices that do not support edits,
+    /// by default "Edited to “…”". Requires the Private API.
+    #[builder(finish_fn(name = send))]
+    pub async fn edit(
+        &self,
+        guid: &str,
+        text: &str,
+        fallback_text: Option<&str>,
+        #[builder(default)] part_index: u32,
+    ) -> Result<models::Message> {
+        let fallback_text = fallback_text
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("Edited to “{}”", text));
+        let req = self
+            .inner
+            .http
+            .post(format!(
+                "{}/api/v1/message/{}/edit?password={}",
+                self.inner.server_url, guid, self.inner.password
+            ))
+            .json(&json!({
+                "editedMessage": text,
+                "backwardsCompatibilityMessage": fallback_text,
+                "partIndex": part_index,
+            }));
+        self.inner.request_data(req).await
+    }
+
+    /// Retract a sent message. Requires the Private API.
+    #[builder(finish_fn(name = send))]
+    pub async fn unsend(
+        &self,
+        guid: &str,
+        #[builder(default)] part_index: u32,
+    ) -> Result<models::Message> {
+        let req = self
+            .inner
+            .http
+            .post(format!(
+                "{}/api/v1/message/{}/unsend?password={}",
+                self.inner.server_url, guid, self.inner.password
+            ))
+            .json(&json!({ "partIndex": part_index }));
+        self.inner.request_data(req).await
+    }
+}
+
+// Custom builder methods
+impl<'f1, 'f2, S: message_get_builder::State> MessageGetBuilder<'f1, 'f2, S> {
+    pub fn with_chats(mut self) -> Self {
+        self.with_chats = true;
+        self
+    }
+    pub fn with_chat_participants(mut self) -> Self {
+        self.with_chat_participants = true;
+        self
+    }
+    pub fn with_attachment(mut self) -> Self {
+        self.with_attachment = true;
+        self
+    }
+    pub fn with_handle(mut self) -> Self {
+        self.with_handle = true;
+        self
+    }
+}
+impl<'f1, 'f2, S: message_query_builder::State> MessageQueryBuilder<'f1, 'f2, S> {
+    pub fn with_chat(mut self) -> Self {
+        self.with_chat = true;
+        self
+    }
+    pub fn with_chat_participants(mut self) -> Self {
+        self.with_chat_participants = true;
+        self
+    }
+    pub fn with_attachment(mut self) -> Self {
+        self.with_attachment = true;
+        self
+    }
+    pub fn with_handle(mut self) -> Self {
+        self.with_handle = true;
+        self
+    }
+    pub fn with_attributed_body(mut self) -> Self {
+        self.with_attributed_body = true;
+        self
+    }
+    pub fn with_message_summary_info(mut self) -> Self {
+        self.with_message_summary_info = true;
+        self
+    }
+    pub fn with_payload_data(mut self) -> Self {
+        self.with_payload_data = true;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+
+    use crate::client::tests::get_mock_client;
+    use crate::error::Error;
+    use crate::models::{ChatGuid, Reaction, SendMethod};
+
+    fn chat_guid() -> ChatGuid {
+        "iMessage;-;+12025550123".parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn get() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/api/v1/message/9E8C1F3A-1E52-4F39-8C51-6A1C8B4B0D11")
+                    .query_param("with", "chats,handle");
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(include_str!("../../test_data/message.json"));
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let message = client
+            .messages()
+            .get()
+            .guid("9E8C1F3A-1E52-4F39-8C51-6A1C8B4B0D11")
+            .with_chats()
+            .with_handle()
+            .send()
+            .await
+            .expect("Failed to get message");
+
+        mock.assert_async().await;
+        assert_eq!(message.text.as_deref(), Some("Hello from the mock server"));
+        assert_eq!(
+            message.handle.map(|handle| handle.address).as_deref(),
+            Some("+12025550123")
+        );
+    }
+
+    #[tokio::test]
+    async fn query() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/api/v1/message/query")
+                    .json_body_includes(
+                        r#"{"chatGuid": "iMessage;-;+12025550123", "limit": 1, "sort": "DESC"}"#,
+                    );
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(include_str!("../../test_data/message_list.json"));
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let messages = client
+            .messages()
+            .query()
+            .chat_guid(&chat_guid())
+            .limit(1)
+            .sort(crate::models::SortOrder::Descending)
+            .send()
+            .await
+            .expect("Failed to query messages");
+
+        mock.assert_async().await;
+        assert_eq!(messages.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn search() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/api/v1/message/query")
+                    .json_body_includes(
+                        r#"{"where": [{"statement": "message.text LIKE :term ESCAPE '\\'", "args": {"term": "%50\\% off%"}}], "sort": "DESC"}"#,
+                    );
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(include_str!("../../test_data/message_list.json"));
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let messages = client
+            .messages()
+            .search()
+            .term("50% off")
+            .send()
+            .await
+            .expect("Failed to search messages");
+
+        mock.assert_async().await;
+        assert_eq!(messages.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn count() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/v1/message/count/me");
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(r#"{"status": 200, "message": "Success", "data": {"total": 42}}"#);
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let count = client
+            .messages()
+            .get_my_count()
+            .send()
+            .await
+            .expect("Failed to count messages");
+
+        mock.assert_async().await;
+        assert_eq!(count.total, 42);
+    }
+
+    #[tokio::test]
+    async fn send_text() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST).path("/api/v1/message/text").json_body_includes(
+                    r#"{"chatGuid": "iMessage;-;+12025550123", "tempGuid": "temp-1", "method": "private-api"}"#,
+                );
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(include_str!("../../test_data/message.json"));
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        client
+            .messages()
+            .send_text()
+            .chat_guid(&chat_guid())
+            .temp_guid("temp-1")
+            .message("Hello from the mock server")
+            .method(SendMethod::PrivateApi)
+            .send()
+            .await
+            .expect("Failed to send message");
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn react() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST).path("/api/v1/message/react").json_body_includes(
+                    r#"{"chatGuid": "iMessage;-;+12025550123", "selectedMessageGuid": "9E8C1F3A-1E52-4F39-8C51-6A1C8B4B0D11", "reaction": "-love"}"#,
+                );
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(include_str!("../../test_data/message.json"));
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        client
+            .messages()
+            .react()
+            .chat_guid(&chat_guid())
+            .message_guid("9E8C1F3A-1E52-4F39-8C51-6A1C8B4B0D11")
+            .reaction(Reaction::Love)
+            .remove(true)
+            .send()
+            .await
+            .expect("Failed to react");
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn unsend_without_private_api() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/api/v1/message/9E8C1F3A-1E52-4F39-8C51-6A1C8B4B0D11/unsend");
+                then.status(400)
+                    .header("content-type", "application/json")
+                    .body(
+                        r#"{"status": 400, "message": "iMessage Private API is not enabled!", "error": {"type": "Server Error", "message": "iMessage Private API is not enabled!"}}"#,
+                    );
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let result = client
+            .messages()
+            .unsend()
+            .guid("9E8C1F3A-1E52-4F39-8C51-6A1C8B4B0D11")
+            .send()
+            .await;
+
+        mock.assert_async().await;
+        assert!(matches!(result, Err(Error::PrivateApiDisabled { .. })));
+    }
+}
@@ -1,2 +1,12 @@
+mod attachment;
 mod chat;
+mod contact;
+mod handle;
+mod message;
+mod server;
+pub use attachment::Attachment;
 pub use chat::Chat;
+pub use contact::Contact;
+pub use handle::Handle;
+pub use message::Message;
+pub use server::Server;
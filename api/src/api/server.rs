@@ -0,0 +1,226 @@
+use std::sync::Arc;
+
+use bon::bon;
+
+use crate::{client::ClientInner, error::Result, models};
+
+pub struct Server {
+    pub(crate) inner: Arc<ClientInner>,
+}
+
+#[bon]
+impl Server {
+    pub(crate) fn new(inner: Arc<ClientInner>) -> Self {
+        Self { inner }
+    }
+
+    /// Versions, network details and whether the Private API is available
+    #[builder(finish_fn(name = send))]
+    pub async fn info(&self) -> Result<models::ServerInfo> {
+        let req = self.inner.http.get(format!(
+            "{}/api/v1/server/info?password={}",
+            self.inner.server_url, self.inner.password
+        ));
+        self.inner.request_data(req).await
+    }
+
+    #[builder(finish_fn(name = send))]
+    pub async fn totals(&self) -> Result<models::ServerTotals> {
+        let req = self.inner.http.get(format!(
+            "{}/api/v1/server/statistics/totals?password={}",
+            self.inner.server_url, self.inner.password
+        ));
+        self.inner.request_data(req).await
+    }
+
+    #[builder(finish_fn(name = send))]
+    pub async fn media_totals(&self) -> Result<models::MediaTotals> {
+        let req = self.inner.http.get(format!(
+            "{}/api/v1/server/statistics/media?password={}",
+            self.inner.server_url, self.inner.password
+        ));
+        self.inner.request_data(req).await
+    }
+
+    /// The last `count` lines of the server log, 100 by default
+    #[builder(finish_fn(name = send))]
+    pub async fn logs(&self, count: Option<u32>) -> Result<String> {
+        let req = self
+            .inner
+            .http
+            .get(format!(
+                "{}/api/v1/server/logs?password={}",
+                self.inner.server_url, self.inner.password
+            ))
+            .query(&[("count", count)]);
+        self.inner.request_data(req).await
+    }
+
+    /// Restart the server's services without relaunching the app
+    #[builder(finish_fn(name = send))]
+    pub async fn soft_restart(&self) -> Result<()> {
+        let req = self.inner.http.get(format!(
+            "{}/api/v1/server/restart/soft?password={}",
+            self.inner.server_url, self.inner.password
+        ));
+        self.inner.request(req).await
+    }
+
+    /// Relaunch the server app, it is unreachable until it has started again
+    #[builder(finish_fn(name = send))]
+    pub async fn restart(&self) -> Result<()> {
+        let req = self.inner.http.get(format!(
+            "{}/api/v1/server/restart/hard?password={}",
+            self.inner.server_url, self.inner.password
+        ));
+        self.inner.request(req).await
+    }
+
+    #[builder(finish_fn(name = send))]
+    pub async fn check_for_update(&self) -> Result<models::UpdateCheck> {
+        let req = self.inner.http.get(format!(
+            "{}/api/v1/server/update/check?password={}",
+            self.inner.server_url, self.inner.password
+        ));
+        self.inner.request_data(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+
+    use crate::client::tests::get_mock_client;
+
+    #[tokio::test]
+    async fn info() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/v1/server/info");
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(include_str!("../../test_data/server_info.json"));
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let info = client
+            .server()
+            .info()
+            .send()
+            .await
+            .expect("Failed to get server info");
+
+        mock.assert_async().await;
+        assert_eq!(info.server_version, "1.9.9");
+        assert!(info.private_api);
+        assert!(info.helper_connected);
+        assert_eq!(info.detected_icloud.as_deref(), Some("jane@icloud.com"));
+    }
+
+    #[tokio::test]
+    async fn totals() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/v1/server/statistics/totals");
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(
+                        r#"{"status": 200, "message": "Success", "data": {"handles": 120, "messages": 48211, "chats": 87, "attachments": 3120}}"#,
+                    );
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let totals = client
+            .server()
+            .totals()
+            .send()
+            .await
+            .expect("Failed to get totals");
+
+        mock.assert_async().await;
+        assert_eq!(totals.messages, 48211);
+    }
+
+    #[tokio::test]
+    async fn logs() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/api/v1/server/logs")
+                    .query_param("count", "2");
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(
+                        r#"{"status": 200, "message": "Success", "data": "[Server] Started\n[Server] Ready"}"#,
+                    );
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let logs = client
+            .server()
+            .logs()
+            .count(2)
+            .send()
+            .await
+            .expect("Failed to get logs");
+
+        mock.assert_async().await;
+        assert_eq!(logs.lines().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn soft_restart() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/v1/server/restart/soft");
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(r#"{"status": 200, "message": "Successfully restarted services!"}"#);
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        client
+            .server()
+            .soft_restart()
+            .send()
+            .await
+            .expect("Failed to restart");
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn check_for_update() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/v1/server/update/check");
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(
+                        r#"{"status": 200, "message": "Success", "data": {"available": true, "current": "1.9.8", "metadata": {"version": "1.9.9", "release_date": "2025-01-10", "release_name": "v1.9.9", "release_notes": "Bug fixes"}}}"#,
+                    );
+            })
+            .await;
+        let client = get_mock_client(&server);
+
+        let update = client
+            .server()
+            .check_for_update()
+            .send()
+            .await
+            .expect("Failed to check for update");
+
+        mock.assert_async().await;
+        assert!(update.available);
+        assert_eq!(update.metadata.unwrap().version, "1.9.9");
+    }
+}
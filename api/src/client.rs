@@ -1,18 +1,19 @@
 use bon::{Builder, bon, builder};
-use reqwest::{Client as HttpClient, header::HeaderValue};
+use reqwest::{Client as HttpClient, header::CONTENT_TYPE};
 use serde::Deserialize;
 use std::sync::Arc;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 
 use crate::{
     api,
     error::{Error, Result},
-    models::response::Response,
+    models::{TransferProgress, response::Response},
+    websocket::{self, EventHandler, WebSocketHandle},
 };
 
 // use crate::{
 //     api::{ChatApi, MessageApi, AttachmentApi, ContactApi, ServerApi},
 //     config::ClientConfig,
-//     websocket::{EventHandler, WebSocketHandle},
 // };
 
 /// Main BlueBubbles API client
@@ -46,7 +47,7 @@ impl ClientInner {
         // Extract data or convert error
         match (json_response.data, json_response.error) {
             (Some(data), _) => Ok(data),
-            (None, Some(err)) => Err(Error::from(err)),
+            (None, Some(err)) => Err(Error::from_response(json_response.message, err)),
             (None, None) => Err(Error::UnexpectedResponse(format!(
                 "No data or error: {}",
                 json_response.message
@@ -65,7 +66,7 @@ impl ClientInner {
 
         // Extract data or convert error
         if let Some(error) = json_response.error {
-            Err(Error::from(error))
+            Err(Error::from_response(json_response.message, error))
         } else {
             Ok(())
         }
@@ -74,30 +75,10 @@ impl ClientInner {
     pub(crate) async fn request_bytes(&self, request: reqwest::RequestBuilder) -> Result<Vec<u8>> {
         let response = request.send().await.map_err(|e| Error::HTTPError(e))?;
 
-        let content_type = response.headers().get("Content-Type");
-
-        dbg!(&content_type);
-
-        if let Some(content_type) = content_type
-            && content_type
-                .to_str()
-                .expect("Invalid Content-Type")
-                .contains("application/json")
-        {
-            // Parse JSON response
-            let api_response = response.bytes().await.map_err(|e| Error::HTTPError(e))?;
-            let mut deserializer = serde_json::Deserializer::from_slice(&api_response);
-            let json_response: Response = serde_path_to_error::deserialize(&mut deserializer)
-                .map_err(|e| Error::DeserializationError(e))?;
-
-            // Extract data or convert error
-            if let Some(error) = json_response.error {
-                Err(Error::from(error))
-            } else {
-                Err(Error::UnexpectedResponse(
-                    "Expected JSON response".to_string(),
-                ))
-            }
+        if is_json(&response) {
+            Err(Self::json_error(response).await)
+        } else if let Some(error) = status_error(&response) {
+            Err(error)
         } else {
             Ok(response
                 .bytes()
@@ -106,6 +87,79 @@ impl ClientInner {
                 .to_vec())
         }
     }
+
+    /// Stream a binary response body into `writer` chunk by chunk instead of buffering it.
+    ///
+    /// Returns the number of bytes written.
+    pub(crate) async fn request_stream<W: AsyncWrite + Unpin>(
+        &self,
+        request: reqwest::RequestBuilder,
+        writer: &mut W,
+        mut on_progress: Option<&mut (dyn FnMut(TransferProgress) + Send)>,
+    ) -> Result<u64> {
+        let mut response = request.send().await.map_err(Error::HTTPError)?;
+
+        if is_json(&response) {
+            return Err(Self::json_error(response).await);
+        }
+        // An error page must not end up in the file being written
+        if let Some(error) = status_error(&response) {
+            return Err(error);
+        }
+
+        let total_bytes = response.content_length();
+        let mut bytes_transferred = 0;
+        while let Some(chunk) = response.chunk().await.map_err(Error::HTTPError)? {
+            writer.write_all(&chunk).await?;
+            bytes_transferred += chunk.len() as u64;
+            if let Some(on_progress) = on_progress.as_mut() {
+                on_progress(TransferProgress {
+                    bytes_transferred,
+                    total_bytes,
+                });
+            }
+        }
+        writer.flush().await?;
+
+        Ok(bytes_transferred)
+    }
+
+    /// Convert a JSON body received where binary data was expected into an error
+    async fn json_error(response: reqwest::Response) -> Error {
+        let api_response = match response.bytes().await {
+            Ok(bytes) => bytes,
+            Err(e) => return Error::HTTPError(e),
+        };
+        let mut deserializer = serde_json::Deserializer::from_slice(&api_response);
+        let json_response: Response = match serde_path_to_error::deserialize(&mut deserializer) {
+            Ok(json_response) => json_response,
+            Err(e) => return Error::DeserializationError(e),
+        };
+
+        // Extract data or convert error
+        if let Some(error) = json_response.error {
+            Error::from_response(json_response.message, error)
+        } else {
+            Error::UnexpectedResponse("Expected binary response".to_string())
+        }
+    }
+}
+
+fn is_json(response: &reqwest::Response) -> bool {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.contains("application/json"))
+}
+
+/// An error for a failed response that doesn't carry a JSON error body
+fn status_error(response: &reqwest::Response) -> Option<Error> {
+    let status = response.status();
+    (!status.is_success()).then(|| Error::ApiError {
+        status,
+        message: status.canonical_reason().unwrap_or_default().to_string(),
+    })
 }
 
 impl Into<Arc<ClientInner>> for Client {
@@ -128,8 +182,9 @@ impl Client {
         ClientInner::builder()
     }
 
+    /// Check that the server is reachable and the password is valid
     #[builder(finish_fn(name = send))]
-    async fn ping(&self) -> Result<String> {
+    pub async fn ping(&self) -> Result<String> {
         let response = self.inner.http.get(format!(
             "{}/api/v1/ping?password={}",
             self.inner.server_url, self.inner.password
@@ -142,36 +197,38 @@ impl Client {
         api::Chat::new(Arc::clone(&self.inner))
     }
 
-    // /// Access the messages API namespace
-    // pub fn messages(&self) -> MessageApi {
-    //     MessageApi::new(Arc::clone(&self.inner))
-    // }
-
-    // /// Access the attachments API namespace
-    // pub fn attachments(&self) -> AttachmentApi {
-    //     AttachmentApi::new(Arc::clone(&self.inner))
-    // }
-
-    // /// Access the contacts API namespace
-    // pub fn contacts(&self) -> ContactApi {
-    //     ContactApi::new(Arc::clone(&self.inner))
-    // }
-
-    // /// Access the server API namespace
-    // pub fn server(&self) -> ServerApi {
-    //     ServerApi::new(Arc::clone(&self.inner))
-    // }
-
-    // /// Connect to WebSocket and start receiving events
-    // pub async fn connect_websocket<H>(
-    //     &self,
-    //     handler: H,
-    // ) -> Result<WebSocketHandle>
-    // where
-    //     H: EventHandler + 'static,
-    // {
-    //     websocket::connect(Arc::clone(&self.inner), handler).await
-    // }
+    /// Access the messages API namespace
+    pub fn messages(&self) -> api::Message {
+        api::Message::new(Arc::clone(&self.inner))
+    }
+
+    /// Access the attachments API namespace
+    pub fn attachments(&self) -> api::Attachment {
+        api::Attachment::new(Arc::clone(&self.inner))
+    }
+
+    /// Access the handles API namespace
+    pub fn handles(&self) -> api::Handle {
+        api::Handle::new(Arc::clone(&self.inner))
+    }
+
+    /// Access the contacts API namespace
+    pub fn contacts(&self) -> api::Contact {
+        api::Contact::new(Arc::clone(&self.inner))
+    }
+
+    /// Access the server API namespace
+    pub fn server(&self) -> api::Server {
+        api::Server::new(Arc::clone(&self.inner))
+    }
+
+    /// Connect to the real-time event socket and start receiving events
+    pub async fn connect_websocket<H>(&self, handler: H) -> Result<WebSocketHandle>
+    where
+        H: EventHandler + 'static,
+    {
+        websocket::connect(Arc::clone(&self.inner), handler).await
+    }
 }
 
 #[cfg(test)]
@@ -191,6 +248,13 @@ pub(crate) mod tests {
         client
     }
 
+    pub fn get_mock_client(server: &httpmock::MockServer) -> Client {
+        Client::builder()
+            .server_url(server.base_url())
+            .password("password")
+            .build()
+    }
+
     #[tokio::test]
     async fn ping() {
         let client = get_test_client();
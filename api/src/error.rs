@@ -38,11 +38,20 @@ pub enum Error {
     #[error("Gateway timeout: {message}")]
     GatewayTimeout { message: String },
 
+    /// The action needs the Private API, which is disabled on the server or whose
+    /// helper is not connected
+    #[error("Private API unavailable: {message}")]
+    PrivateApiDisabled { message: String },
+
     // === HTTP/Network Errors ===
     /// HTTP request failed
     #[error("HTTP request failed: {0}")]
     HTTPError(#[from] reqwest::Error),
 
+    /// WebSocket connection failed
+    #[error("WebSocket error: {0}")]
+    WebSocketError(#[from] reqwest_websocket::Error),
+
     /// JSON deserialization failed
     #[error("JSON deserialization failed: {0}")]
     DeserializationError(#[from] serde_path_to_error::Error<serde_json::Error>),
@@ -51,11 +60,42 @@ pub enum Error {
     #[error("API error ({status}): {message}")]
     ApiError { status: StatusCode, message: String },
 
+    /// Reading or writing local data failed
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+
     /// Unexpected response from API
     #[error("Unexpected response: {0}")]
     UnexpectedResponse(String),
 }
 
+impl Error {
+    /// Convert an error response, `message` is the top level message of the response.
+    ///
+    /// The server reports a disabled Private API with a generic error type, so it is
+    /// recognised by its message.
+    pub(crate) fn from_response(
+        message: String,
+        err: crate::models::response::ErrorResponse,
+    ) -> Self {
+        if let Some(message) = [&err.message, &message]
+            .into_iter()
+            .find(|message| is_private_api_disabled(message))
+        {
+            return Error::PrivateApiDisabled {
+                message: message.clone(),
+            };
+        }
+        Error::from(err)
+    }
+}
+
+fn is_private_api_disabled(message: &str) -> bool {
+    let message = message.to_lowercase();
+    message.contains("private api is not enabled")
+        || message.contains("private api helper is not connected")
+}
+
 // Helper to convert a raw api error to the crate error type
 impl From<crate::models::response::ErrorResponse> for Error {
     fn from(err: crate::models::response::ErrorResponse) -> Self {
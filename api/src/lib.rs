@@ -3,3 +3,5 @@ pub mod client;
 pub mod error;
 pub mod models;
 mod utils;
+pub mod vcard;
+pub mod websocket;
@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+/// Represents an attachment in a message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    #[serde(rename = "originalROWID")]
+    pub original_row_id: i64,
+    pub guid: String,
+    pub uti: Option<String>,
+    pub mime_type: Option<String>,
+    pub transfer_name: Option<String>,
+    #[serde(default)]
+    pub total_bytes: i64,
+    pub transfer_state: Option<i64>,
+    pub is_outgoing: Option<bool>,
+    #[serde(default)]
+    pub hide_attachment: bool,
+    #[serde(default)]
+    pub is_sticker: bool,
+    pub original_guid: Option<String>,
+    #[serde(default)]
+    pub has_live_photo: bool,
+    pub height: Option<i64>,
+    pub width: Option<i64>,
+    pub metadata: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentCount {
+    pub total: i64,
+}
+
+/// Progress of a streaming attachment transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferProgress {
+    /// Bytes written so far
+    pub bytes_transferred: u64,
+    /// Total size of the transfer, if the server reported a `Content-Length`
+    pub total_bytes: Option<u64>,
+}
@@ -0,0 +1,270 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+
+/// A message body as sent by the server: the full string and the attributes of each
+/// range of it, decoded from the `NSAttributedString` Messages.app stores
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttributedBody {
+    pub string: String,
+    #[serde(default)]
+    pub runs: Vec<AttributedRun>,
+}
+
+/// Attributes of a range of an [`AttributedBody`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttributedRun {
+    /// Start and length, in UTF-16 code units
+    pub range: (usize, usize),
+    #[serde(default)]
+    pub attributes: Map<String, Value>,
+}
+
+/// A run of message text sharing the same meaning and style
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextSegment {
+    pub text: String,
+    pub kind: SegmentKind,
+    pub style: TextStyle,
+    /// Index of the message part the segment belongs to
+    pub part: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SegmentKind {
+    Text,
+    /// A mention of someone, `address` is their handle
+    Mention {
+        address: String,
+    },
+    Link {
+        url: String,
+    },
+    /// Where an attachment is placed in the text, the text is a placeholder character
+    Attachment {
+        guid: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+    pub effect: Option<TextEffect>,
+}
+
+/// Animated text effects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEffect {
+    Big,
+    Small,
+    Shake,
+    Nod,
+    Explode,
+    Ripple,
+    Bloom,
+    Jitter,
+}
+
+const PART: &str = "__kIMMessagePartAttributeName";
+const MENTION: &str = "__kIMMentionConfirmedMention";
+const LINK: &str = "__kIMLinkAttributeName";
+const ATTACHMENT: &str = "__kIMFileTransferGUIDAttributeName";
+const BOLD: &str = "__kIMTextBoldAttributeName";
+const ITALIC: &str = "__kIMTextItalicAttributeName";
+const UNDERLINE: &str = "__kIMTextUnderlineAttributeName";
+const STRIKETHROUGH: &str = "__kIMTextStrikethroughAttributeName";
+const EFFECT: &str = "__kIMTextEffectAttributeName";
+
+impl TextEffect {
+    fn from_id(id: i64) -> Option<Self> {
+        Some(match id {
+            4 => Self::Ripple,
+            5 => Self::Big,
+            6 => Self::Bloom,
+            8 => Self::Nod,
+            9 => Self::Shake,
+            10 => Self::Jitter,
+            11 => Self::Small,
+            12 => Self::Explode,
+            _ => return None,
+        })
+    }
+}
+
+impl TextSegment {
+    pub fn plain(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            kind: SegmentKind::Text,
+            style: TextStyle::default(),
+            part: 0,
+        }
+    }
+}
+
+impl AttributedBody {
+    /// Split the body into segments, `None` if a run does not fit the string
+    pub fn segments(&self) -> Option<Vec<TextSegment>> {
+        let units: Vec<u16> = self.string.encode_utf16().collect();
+        let text = |start: usize, end: usize| String::from_utf16(units.get(start..end)?).ok();
+
+        let mut segments = Vec::new();
+        let mut position = 0;
+        for run in &self.runs {
+            let (start, length) = run.range;
+            let end = start.checked_add(length)?;
+            // Text no run covers keeps the part of the run before it
+            if start > position {
+                segments.push(TextSegment {
+                    part: segments
+                        .last()
+                        .map_or(0, |segment: &TextSegment| segment.part),
+                    ..TextSegment::plain(text(position, start)?)
+                });
+            }
+            if end <= position {
+                continue;
+            }
+            segments.push(segment(text(start.max(position), end)?, &run.attributes));
+            position = end;
+        }
+        if position < units.len() {
+            segments.push(TextSegment {
+                part: segments.last().map_or(0, |segment| segment.part),
+                ..TextSegment::plain(text(position, units.len())?)
+            });
+        }
+        Some(segments)
+    }
+}
+
+fn segment(text: String, attributes: &Map<String, Value>) -> TextSegment {
+    let string = |key| attributes.get(key).and_then(string_value);
+    let flag = |key| attributes.get(key).is_some_and(truthy);
+
+    let kind = if let Some(guid) = string(ATTACHMENT) {
+        SegmentKind::Attachment { guid }
+    } else if let Some(address) = string(MENTION) {
+        SegmentKind::Mention { address }
+    } else if let Some(url) = string(LINK) {
+        SegmentKind::Link { url }
+    } else {
+        SegmentKind::Text
+    };
+
+    TextSegment {
+        text,
+        kind,
+        style: TextStyle {
+            bold: flag(BOLD),
+            italic: flag(ITALIC),
+            underline: flag(UNDERLINE),
+            strikethrough: flag(STRIKETHROUGH),
+            effect: attributes
+                .get(EFFECT)
+                .and_then(Value::as_i64)
+                .and_then(TextEffect::from_id),
+        },
+        part: attributes
+            .get(PART)
+            .and_then(Value::as_u64)
+            .and_then(|part| part.try_into().ok())
+            .unwrap_or(0),
+    }
+}
+
+/// A string attribute, links can be archived `NSURL` objects
+fn string_value(value: &Value) -> Option<String> {
+    match value {
+        Value::String(string) => Some(string.clone()),
+        Value::Object(object) => ["NS.relative", "url", "absoluteString"]
+            .iter()
+            .find_map(|key| object.get(*key)?.as_str())
+            .map(str::to_string),
+        _ => None,
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Bool(flag) => *flag,
+        Value::Number(number) => number.as_f64().is_some_and(|number| number != 0.0),
+        _ => false,
+    }
+}
+
+/// Read `attributedBody`, leaving it unset when the server sent something else so the
+/// message falls back to its plain text
+pub(crate) fn deserialize_lenient<'de, D>(
+    deserializer: D,
+) -> Result<Option<Vec<AttributedBody>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<Value>::deserialize(deserializer)?;
+    Ok(value.and_then(|value| serde_json::from_value(value).ok()))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn segments() {
+        let body: AttributedBody = serde_json::from_value(json!({
+            "string": "Hey 👋 @Ann \u{fffc} see https://example.com",
+            "runs": [
+                { "range": [0, 7], "attributes": { PART: 0, BOLD: 1 } },
+                { "range": [7, 4], "attributes": { PART: 0, MENTION: "ann@example.com" } },
+                { "range": [11, 1], "attributes": { PART: 0 } },
+                { "range": [12, 1], "attributes": { PART: 1, ATTACHMENT: "at_1_ABC" } },
+                { "range": [13, 5], "attributes": { PART: 2, EFFECT: 9 } },
+                {
+                    "range": [18, 19],
+                    "attributes": { PART: 2, LINK: { "NS.relative": "https://example.com" } }
+                },
+            ]
+        }))
+        .unwrap();
+        let segments = body.segments().unwrap();
+
+        assert_eq!(segments.len(), 6);
+        assert_eq!(segments[0].text, "Hey 👋 ");
+        assert!(segments[0].style.bold);
+        assert_eq!(
+            segments[1].kind,
+            SegmentKind::Mention {
+                address: "ann@example.com".to_string()
+            }
+        );
+        assert_eq!(segments[1].text, "@Ann");
+        assert_eq!(
+            segments[3].kind,
+            SegmentKind::Attachment {
+                guid: "at_1_ABC".to_string()
+            }
+        );
+        assert_eq!(segments[3].part, 1);
+        assert_eq!(segments[4].style.effect, Some(TextEffect::Shake));
+        assert_eq!(
+            segments[5].kind,
+            SegmentKind::Link {
+                url: "https://example.com".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn out_of_range_run() {
+        let body: AttributedBody = serde_json::from_value(json!({
+            "string": "Hi",
+            "runs": [{ "range": [0, 5], "attributes": {} }]
+        }))
+        .unwrap();
+        assert!(body.segments().is_none());
+    }
+}
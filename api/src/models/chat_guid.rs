@@ -0,0 +1,186 @@
+use std::{fmt, ops::Deref, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{ChatStyle, Service};
+
+/// The GUID of a chat, e.g. `iMessage;-;+12025550123` or `SMS;+;chat123456789`.
+///
+/// It is made of the service, `+` for group chats or `-` for direct ones, and the
+/// identifier, separated by `;`. Newer versions of macOS use `any` as the service for
+/// chats that can go over either.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ChatGuid(String);
+
+/// A string that is not a chat GUID
+#[derive(Debug, Clone, Error)]
+#[error("Invalid chat GUID: {0}")]
+pub struct InvalidChatGuid(pub String);
+
+impl ChatGuid {
+    pub fn new(service: Service, style: ChatStyle, identifier: &str) -> Self {
+        let style = match style {
+            ChatStyle::Group => '+',
+            ChatStyle::Direct => '-',
+        };
+        Self(format!("{};{};{}", service.name(), style, identifier))
+    }
+
+    fn parts(&self) -> (&str, &str, &str) {
+        let mut parts = self.0.splitn(3, ';');
+        // Checked when parsed
+        (
+            parts.next().unwrap_or_default(),
+            parts.next().unwrap_or_default(),
+            parts.next().unwrap_or_default(),
+        )
+    }
+
+    /// The service, [`Service::Other`] for `any`
+    pub fn service(&self) -> Service {
+        Service::from_name(self.parts().0)
+    }
+
+    pub fn style(&self) -> ChatStyle {
+        match self.parts().1 {
+            "+" => ChatStyle::Group,
+            _ => ChatStyle::Direct,
+        }
+    }
+
+    /// The address of the other person for direct chats, an identifier like
+    /// `chat123456789` for group chats
+    pub fn identifier(&self) -> &str {
+        self.parts().2
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for ChatGuid {
+    type Err = InvalidChatGuid;
+
+    fn from_str(guid: &str) -> Result<Self, Self::Err> {
+        let mut parts = guid.splitn(3, ';');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(service), Some("+" | "-"), Some(identifier))
+                if !service.is_empty() && !identifier.is_empty() =>
+            {
+                Ok(Self(guid.to_string()))
+            }
+            _ => Err(InvalidChatGuid(guid.to_string())),
+        }
+    }
+}
+
+impl TryFrom<String> for ChatGuid {
+    type Error = InvalidChatGuid;
+
+    fn try_from(guid: String) -> Result<Self, Self::Error> {
+        guid.parse()
+    }
+}
+
+impl From<ChatGuid> for String {
+    fn from(guid: ChatGuid) -> Self {
+        guid.0
+    }
+}
+
+impl fmt::Display for ChatGuid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Deref for ChatGuid {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for ChatGuid {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for ChatGuid {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for ChatGuid {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<String> for ChatGuid {
+    fn eq(&self, other: &String) -> bool {
+        &self.0 == other
+    }
+}
+
+impl PartialEq<ChatGuid> for String {
+    fn eq(&self, other: &ChatGuid) -> bool {
+        self == &other.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let direct: ChatGuid = "SMS;-;+12023896015".parse().unwrap();
+        assert_eq!(direct.service(), Service::Sms);
+        assert_eq!(direct.style(), ChatStyle::Direct);
+        assert_eq!(direct.identifier(), "+12023896015");
+
+        let group: ChatGuid = "iMessage;+;chat482910376451".parse().unwrap();
+        assert_eq!(group.service(), Service::IMessage);
+        assert_eq!(group.style(), ChatStyle::Group);
+        assert_eq!(group.identifier(), "chat482910376451");
+
+        // Identifiers can contain the separator
+        let odd: ChatGuid = "any;-;a;b@example.com".parse().unwrap();
+        assert_eq!(odd.service(), Service::Other("any".to_string()));
+        assert_eq!(odd.identifier(), "a;b@example.com");
+    }
+
+    #[test]
+    fn invalid() {
+        for guid in [
+            "",
+            "iMessage",
+            "iMessage;+",
+            "iMessage;x;chat1",
+            ";-;+1202",
+            "SMS;-;",
+        ] {
+            assert!(guid.parse::<ChatGuid>().is_err(), "{guid} was accepted");
+        }
+        assert!(serde_json::from_str::<ChatGuid>("\"nope\"").is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let guid = ChatGuid::new(Service::Rcs, ChatStyle::Direct, "+12025550123");
+        assert_eq!(guid, "RCS;-;+12025550123");
+        let json = serde_json::to_string(&guid).unwrap();
+        assert_eq!(json, "\"RCS;-;+12025550123\"");
+        assert_eq!(serde_json::from_str::<ChatGuid>(&json).unwrap(), guid);
+
+        let any = ChatGuid::new(Service::from_name("any"), ChatStyle::Direct, "+12025550123");
+        assert_eq!(any, "any;-;+12025550123");
+    }
+}
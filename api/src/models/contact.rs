@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+/// A contact from the server's address book or an imported vCard
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Contact {
+    pub id: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub display_name: Option<String>,
+    pub nickname: Option<String>,
+    #[serde(default)]
+    pub phone_numbers: Vec<ContactAddress>,
+    #[serde(default)]
+    pub emails: Vec<ContactAddress>,
+    /// Base64 encoded image, only included when requested with `with_avatars`
+    pub avatar: Option<String>,
+    /// Where the contact came from, e.g. `api`, `db` or `vcf`
+    pub source_type: Option<String>,
+}
+
+impl Contact {
+    /// The name to show for the contact: its display name, otherwise first and last
+    /// name, otherwise its nickname
+    pub fn name(&self) -> Option<String> {
+        let non_empty = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+
+        if let Some(display_name) = non_empty(&self.display_name) {
+            return Some(display_name);
+        }
+        let full_name = [non_empty(&self.first_name), non_empty(&self.last_name)]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !full_name.is_empty() {
+            return Some(full_name);
+        }
+        non_empty(&self.nickname)
+    }
+
+    /// All phone numbers and emails of the contact
+    pub fn addresses(&self) -> impl Iterator<Item = &str> {
+        self.phone_numbers
+            .iter()
+            .chain(&self.emails)
+            .map(|address| address.address.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContactAddress {
+    pub address: String,
+    pub id: Option<String>,
+}
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+
+/// An address (phone number or email) that messages are sent from or to
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Handle {
+    #[serde(rename = "originalROWID")]
+    pub original_row_id: i64,
+    pub address: String,
+    /// The service the address was reached through
+    pub service: Service,
+    pub uncanonicalized_id: Option<String>,
+    /// Two letter country code used to interpret local phone numbers
+    pub country: Option<String>,
+    /// The address formatted for display by the server, if it provides one
+    pub formatted_address: Option<String>,
+}
+
+/// The network messages go over
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Service {
+    IMessage,
+    Sms,
+    Rcs,
+    /// A service this client does not know about, such as `any` used by newer versions
+    /// of macOS, kept as the server named it
+    Other(String),
+}
+
+impl Service {
+    pub fn name(&self) -> &str {
+        match self {
+            Service::IMessage => "iMessage",
+            Service::Sms => "SMS",
+            Service::Rcs => "RCS",
+            Service::Other(name) => name,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "iMessage" => Service::IMessage,
+            "SMS" => Service::Sms,
+            "RCS" => Service::Rcs,
+            _ => Service::Other(name.to_string()),
+        }
+    }
+}
+
+impl Default for Service {
+    fn default() -> Self {
+        Service::Other(String::new())
+    }
+}
+
+impl From<String> for Service {
+    fn from(name: String) -> Self {
+        match Service::from_name(&name) {
+            Service::Other(_) => Service::Other(name),
+            service => service,
+        }
+    }
+}
+
+impl From<Service> for String {
+    fn from(service: Service) -> Self {
+        match service {
+            Service::Other(name) => name,
+            service => service.name().to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HandleCount {
+    pub total: i64,
+}
+
+/// Focus (Do Not Disturb) status shared by a handle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FocusStatus {
+    /// The contact has notifications silenced
+    Silenced,
+    None,
+    /// The server could not determine the status, e.g. without the Private API
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct HandleFocus {
+    pub status: FocusStatus,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct HandleAvailability {
+    pub available: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Service;
+
+    #[test]
+    fn service_round_trip() {
+        for name in ["iMessage", "SMS", "RCS", "any"] {
+            let json = format!("\"{name}\"");
+            let service: Service = serde_json::from_str(&json).unwrap();
+            assert_eq!(service.name(), name);
+            assert_eq!(serde_json::to_string(&service).unwrap(), json);
+        }
+        assert_eq!(
+            serde_json::from_str::<Service>("\"any\"").unwrap(),
+            Service::Other("any".to_string())
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    Attachment, AttributedBody, Chat, Handle, SegmentKind, Tapback, TextSegment, attributed_body,
+};
+
+/// Represents a message in a chat
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Message {
+    #[serde(rename = "originalROWID")]
+    pub original_row_id: i64,
+    pub guid: String,
+    /// The temporary GUID the message was sent with, only set on messages sent through the API
+    pub temp_guid: Option<String>,
+    pub text: Option<String>,
+    /// The formatted body, only included when requested with `with_attributed_body`.
+    /// Use [`Message::body`] to read it.
+    #[serde(default, deserialize_with = "attributed_body::deserialize_lenient")]
+    pub attributed_body: Option<Vec<AttributedBody>>,
+    /// The sender, only included when requested with `with_handle`
+    pub handle: Option<Handle>,
+    pub handle_id: i64,
+    pub other_handle: i64,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    pub subject: Option<String>,
+    pub error: i64,
+    pub date_created: i64,
+    pub date_read: Option<i64>,
+    pub date_delivered: Option<i64>,
+    pub is_delivered: bool,
+    pub is_from_me: bool,
+    pub has_dd_results: bool,
+    pub is_archived: bool,
+    pub item_type: i64,
+    pub group_title: Option<String>,
+    pub group_action_type: i64,
+    pub balloon_bundle_id: Option<String>,
+    pub associated_message_guid: Option<String>,
+    pub associated_message_type: Option<String>,
+    pub expressive_send_style_id: Option<String>,
+    pub thread_originator_guid: Option<String>,
+    pub has_payload_data: bool,
+    pub country: Option<String>,
+    pub is_delayed: bool,
+    pub is_auto_reply: bool,
+    pub is_system_message: bool,
+    pub is_service_message: bool,
+    pub is_forward: bool,
+    pub thread_originator_part: Option<String>,
+    pub is_corrupt: bool,
+    pub date_played: Option<i64>,
+    pub cache_roomnames: Option<String>,
+    pub is_spam: bool,
+    pub is_expired: bool,
+    pub time_expressive_send_played: Option<i64>,
+    pub is_audio_message: bool,
+    pub reply_to_guid: Option<String>,
+    pub share_status: i64,
+    pub share_direction: i64,
+    pub was_delivered_quietly: bool,
+    pub did_notify_recipient: bool,
+    #[serde(default)]
+    pub chats: Vec<Chat>,
+    pub message_summary_info: Option<String>,
+    pub payload_data: Option<String>,
+    pub date_edited: Option<i64>,
+    pub date_retracted: Option<i64>,
+    pub part_count: i64,
+}
+
+impl Message {
+    /// The reaction this message carries, if it is a tapback on another message
+    pub fn tapback(&self) -> Option<Tapback> {
+        Tapback::from_associated_type(self.associated_message_type.as_deref()?)
+    }
+
+    /// Whether this is a tapback, folded onto the message it targets with
+    /// [`Tapbacks`](super::Tapbacks) instead of being shown on its own
+    pub fn is_tapback(&self) -> bool {
+        self.tapback().is_some() && self.associated_message_target().is_some()
+    }
+
+    /// The body as segments of formatted text, mentions, links and attachment
+    /// placeholders. Falls back to `text` when there is no attributed body or it
+    /// cannot be read.
+    pub fn body(&self) -> Vec<TextSegment> {
+        let segments = self.attributed_body.as_deref().and_then(|bodies| {
+            let mut segments = Vec::new();
+            for body in bodies {
+                segments.extend(body.segments()?);
+            }
+            Some(segments)
+        });
+        match segments {
+            Some(segments) if !segments.is_empty() => segments,
+            _ => self
+                .text
+                .iter()
+                .filter(|text| !text.is_empty())
+                .map(TextSegment::plain)
+                .collect(),
+        }
+    }
+
+    /// The text of the body without attachment placeholders
+    pub fn body_text(&self) -> String {
+        self.body()
+            .into_iter()
+            .filter(|segment| !matches!(segment.kind, SegmentKind::Attachment { .. }))
+            .map(|segment| segment.text)
+            .collect()
+    }
+
+    /// GUID and part index of the message this one is associated with, e.g. the
+    /// message a tapback was placed on.
+    ///
+    /// The server prefixes the GUID with the part, `p:1/GUID`, or `bp:GUID` for the
+    /// whole message. Without a prefix the part is read from `thread_originator_part`.
+    pub fn associated_message_target(&self) -> Option<(&str, u32)> {
+        let associated = self.associated_message_guid.as_deref()?;
+        if let Some(rest) = associated.strip_prefix("p:")
+            && let Some((part, guid)) = rest.split_once('/')
+        {
+            return Some((guid, part.parse().unwrap_or(0)));
+        }
+        let guid = associated.strip_prefix("bp:").unwrap_or(associated);
+        Some((guid, self.thread_originator_part_index().unwrap_or(0)))
+    }
+
+    /// Index of the part of the thread originator this message replies to, parsed
+    /// from `thread_originator_part` (`index:start:length`)
+    pub fn thread_originator_part_index(&self) -> Option<u32> {
+        self.thread_originator_part
+            .as_deref()?
+            .split(':')
+            .next()?
+            .parse()
+            .ok()
+    }
+}
+
+/// Message totals returned by the count endpoints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageCount {
+    pub total: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Reaction;
+
+    #[test]
+    fn tapback_target() {
+        let message = Message {
+            associated_message_guid: Some("p:1/9E8C1F3A-1E52-4F39-8C51-6A1C8B4B0D11".to_string()),
+            associated_message_type: Some("-like".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            message.associated_message_target(),
+            Some(("9E8C1F3A-1E52-4F39-8C51-6A1C8B4B0D11", 1))
+        );
+        let tapback = message.tapback().unwrap();
+        assert_eq!(tapback.reaction, Reaction::Like);
+        assert!(tapback.removed);
+
+        let message = Message {
+            associated_message_guid: Some("bp:9E8C1F3A-1E52-4F39-8C51-6A1C8B4B0D11".to_string()),
+            thread_originator_part: Some("2:0:5".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            message.associated_message_target(),
+            Some(("9E8C1F3A-1E52-4F39-8C51-6A1C8B4B0D11", 2))
+        );
+        assert!(message.tapback().is_none());
+    }
+
+    #[test]
+    fn body_falls_back_to_text() {
+        let mut response: serde_json::Value =
+            serde_json::from_str(include_str!("../../test_data/message.json")).unwrap();
+        response["data"]["attributedBody"] = "not an attributed body".into();
+        let message: Message = serde_json::from_value(response["data"].take()).unwrap();
+        assert!(message.attributed_body.is_none());
+        assert_eq!(
+            message.body(),
+            vec![TextSegment::plain("Hello from the mock server")]
+        );
+
+        let message = Message {
+            text: None,
+            attributed_body: Some(vec![AttributedBody {
+                string: "Hi there".to_string(),
+                runs: Vec::new(),
+            }]),
+            ..message
+        };
+        assert_eq!(message.body_text(), "Hi there");
+    }
+}
@@ -1,6 +1,24 @@
+mod attachment;
+mod attributed_body;
 mod chat;
+mod chat_guid;
+mod contact;
+mod handle;
+mod message;
+mod reaction;
 mod request;
 pub(crate) mod response;
+mod server;
 
+pub use attachment::*;
+pub use attributed_body::{
+    AttributedBody, AttributedRun, SegmentKind, TextEffect, TextSegment, TextStyle,
+};
 pub use chat::*;
+pub use chat_guid::*;
+pub use contact::*;
+pub use handle::*;
+pub use message::*;
+pub use reaction::*;
 pub use request::*;
+pub use server::*;
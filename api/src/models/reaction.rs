@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::Message;
+
+/// A tapback that can be placed on a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Reaction {
+    Love,
+    Like,
+    Dislike,
+    Laugh,
+    Emphasize,
+    Question,
+}
+
+impl Reaction {
+    pub const ALL: [Reaction; 6] = [
+        Reaction::Love,
+        Reaction::Like,
+        Reaction::Dislike,
+        Reaction::Laugh,
+        Reaction::Emphasize,
+        Reaction::Question,
+    ];
+
+    pub fn name(&self) -> &str {
+        match self {
+            Reaction::Love => "love",
+            Reaction::Like => "like",
+            Reaction::Dislike => "dislike",
+            Reaction::Laugh => "laugh",
+            Reaction::Emphasize => "emphasize",
+            Reaction::Question => "question",
+        }
+    }
+}
+
+/// A reaction added to or removed from a message, as described by a message's
+/// `associated_message_type`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Tapback {
+    pub reaction: Reaction,
+    /// Whether this takes back an earlier reaction
+    pub removed: bool,
+}
+
+impl Tapback {
+    /// Parse an `associated_message_type` such as `love` or `-love`. Other associated
+    /// messages, e.g. stickers, are not tapbacks.
+    pub fn from_associated_type(associated_type: &str) -> Option<Self> {
+        let (removed, name) = match associated_type.strip_prefix('-') {
+            Some(name) => (true, name),
+            None => (false, associated_type),
+        };
+        let reaction = Reaction::ALL
+            .into_iter()
+            .find(|reaction| reaction.name() == name)?;
+        Some(Self { reaction, removed })
+    }
+
+    /// The `associated_message_type` the server expects when sending this tapback
+    pub fn associated_type(&self) -> String {
+        if self.removed {
+            format!("-{}", self.reaction.name())
+        } else {
+            self.reaction.name().to_string()
+        }
+    }
+}
+
+/// The tapbacks of a conversation, kept apart from the messages they are placed on
+#[derive(Debug, Default)]
+pub struct Tapbacks {
+    /// Tapback messages by the GUID of their target, oldest first
+    by_target: HashMap<String, Vec<Message>>,
+}
+
+/// One reaction currently shown on a part of a message
+#[derive(Debug)]
+pub struct FoldedReaction<'a> {
+    pub part: u32,
+    pub reaction: Reaction,
+    /// The tapbacks still in effect, oldest first
+    pub from: Vec<&'a Message>,
+}
+
+/// Who placed a tapback, everyone has at most one reaction per message part
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum Reactor {
+    Me,
+    Handle(i64),
+}
+
+impl Tapbacks {
+    pub fn clear(&mut self) {
+        self.by_target.clear();
+    }
+
+    /// Add a tapback or a newer copy of it, returning the GUID of the message it targets
+    pub fn insert(&mut self, message: Message) -> Option<String> {
+        let target = message.associated_message_target()?.0.to_string();
+        let tapbacks = self.by_target.entry(target.clone()).or_default();
+        tapbacks.retain(|existing| existing.guid != message.guid);
+        let index =
+            tapbacks.partition_point(|existing| existing.date_created <= message.date_created);
+        tapbacks.insert(index, message);
+        Some(target)
+    }
+
+    /// Replay the tapbacks on a message: a newer reaction replaces the sender's previous
+    /// one on that part and a removal (`-love`) takes it back.
+    pub fn fold(&self, target_guid: &str) -> Vec<FoldedReaction<'_>> {
+        let Some(tapbacks) = self.by_target.get(target_guid) else {
+            return Vec::new();
+        };
+
+        let mut current: HashMap<(u32, Reactor), (Reaction, &Message)> = HashMap::new();
+        for message in tapbacks {
+            let (Some(tapback), Some((_, part))) =
+                (message.tapback(), message.associated_message_target())
+            else {
+                continue;
+            };
+            let reactor = if message.is_from_me {
+                Reactor::Me
+            } else {
+                Reactor::Handle(message.handle_id)
+            };
+            let key = (part, reactor);
+
+            if !tapback.removed {
+                current.insert(key, (tapback.reaction, message));
+            } else if current
+                .get(&key)
+                .is_some_and(|(reaction, _)| *reaction == tapback.reaction)
+            {
+                current.remove(&key);
+            }
+        }
+
+        let mut in_effect: Vec<_> = current
+            .into_iter()
+            .map(|((part, _), (reaction, message))| (part, reaction, message))
+            .collect();
+        in_effect.sort_by_key(|(part, _, message)| (*part, message.date_created));
+
+        let mut folded: Vec<FoldedReaction> = Vec::new();
+        for (part, reaction, message) in in_effect {
+            match folded
+                .iter_mut()
+                .find(|folded| folded.part == part && folded.reaction == reaction)
+            {
+                Some(folded) => folded.from.push(message),
+                None => folded.push(FoldedReaction {
+                    part,
+                    reaction,
+                    from: vec![message],
+                }),
+            }
+        }
+        folded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET: &str = "9E8C1F3A-1E52-4F39-8C51-6A1C8B4B0D11";
+
+    /// A tapback from `handle_id`, or from me for 0, on a part of [`TARGET`]
+    fn tapback(guid: &str, part: u32, associated_type: &str, handle_id: i64, date: i64) -> Message {
+        Message {
+            guid: guid.to_string(),
+            associated_message_guid: Some(format!("p:{part}/{TARGET}")),
+            associated_message_type: Some(associated_type.to_string()),
+            handle_id,
+            is_from_me: handle_id == 0,
+            date_created: date,
+            ..Default::default()
+        }
+    }
+
+    fn folded(tapbacks: &Tapbacks) -> Vec<(u32, Reaction, Vec<&str>)> {
+        tapbacks
+            .fold(TARGET)
+            .into_iter()
+            .map(|folded| {
+                let from = folded
+                    .from
+                    .iter()
+                    .map(|message| message.guid.as_str())
+                    .collect();
+                (folded.part, folded.reaction, from)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn add_then_remove() {
+        let mut tapbacks = Tapbacks::default();
+        assert_eq!(
+            tapbacks.insert(tapback("a", 0, "love", 7, 100)).as_deref(),
+            Some(TARGET)
+        );
+        assert_eq!(folded(&tapbacks), [(0, Reaction::Love, vec!["a"])]);
+
+        // Taking back a different reaction leaves it in place
+        tapbacks.insert(tapback("b", 0, "-like", 7, 200));
+        assert_eq!(folded(&tapbacks), [(0, Reaction::Love, vec!["a"])]);
+
+        tapbacks.insert(tapback("c", 0, "-love", 7, 300));
+        assert!(folded(&tapbacks).is_empty());
+    }
+
+    #[test]
+    fn newer_tapback_replaces() {
+        let mut tapbacks = Tapbacks::default();
+        // Inserted out of order, the newer one still wins
+        tapbacks.insert(tapback("b", 0, "laugh", 7, 200));
+        tapbacks.insert(tapback("a", 0, "love", 7, 100));
+        assert_eq!(folded(&tapbacks), [(0, Reaction::Laugh, vec!["b"])]);
+
+        // A newer copy of the same tapback isn't counted twice
+        tapbacks.insert(tapback("b", 0, "laugh", 7, 200));
+        assert_eq!(folded(&tapbacks), [(0, Reaction::Laugh, vec!["b"])]);
+    }
+
+    #[test]
+    fn reactors_on_same_part() {
+        let mut tapbacks = Tapbacks::default();
+        tapbacks.insert(tapback("a", 0, "like", 7, 100));
+        tapbacks.insert(tapback("b", 0, "like", 0, 200));
+        tapbacks.insert(tapback("c", 0, "question", 9, 300));
+        assert_eq!(
+            folded(&tapbacks),
+            [
+                (0, Reaction::Like, vec!["a", "b"]),
+                (0, Reaction::Question, vec!["c"]),
+            ]
+        );
+
+        // Removing mine leaves theirs
+        tapbacks.insert(tapback("d", 0, "-like", 0, 400));
+        assert_eq!(
+            folded(&tapbacks),
+            [
+                (0, Reaction::Like, vec!["a"]),
+                (0, Reaction::Question, vec!["c"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn parts_fold_separately() {
+        let mut tapbacks = Tapbacks::default();
+        tapbacks.insert(tapback("a", 1, "love", 7, 100));
+        tapbacks.insert(tapback("b", 0, "love", 7, 200));
+        tapbacks.insert(tapback("c", 1, "-love", 7, 300));
+        tapbacks.insert(tapback("d", 2, "emphasize", 7, 400));
+        assert_eq!(
+            folded(&tapbacks),
+            [
+                (0, Reaction::Love, vec!["b"]),
+                (2, Reaction::Emphasize, vec!["d"]),
+            ]
+        );
+        assert!(tapbacks.fold("another message").is_empty());
+    }
+
+    #[test]
+    fn parse_associated_type() {
+        let added = Tapback::from_associated_type("laugh").unwrap();
+        assert_eq!(added.reaction, Reaction::Laugh);
+        assert!(!added.removed);
+
+        let removed = Tapback::from_associated_type("-love").unwrap();
+        assert_eq!(removed.reaction, Reaction::Love);
+        assert!(removed.removed);
+        assert_eq!(removed.associated_type(), "-love");
+
+        assert!(Tapback::from_associated_type("sticker").is_none());
+    }
+}
@@ -1,3 +1,8 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SortOrder {
     Ascending,
     Descending,
@@ -11,3 +16,71 @@ impl SortOrder {
         }
     }
 }
+
+/// What the chat query endpoint sorts by, and in which direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatSort {
+    /// By the date of the last message, `Descending` for most recently active first
+    LastMessage(SortOrder),
+}
+
+impl ChatSort {
+    pub fn name(&self) -> &str {
+        match self {
+            ChatSort::LastMessage(_) => "lastmessage",
+        }
+    }
+
+    pub fn order(&self) -> SortOrder {
+        match self {
+            ChatSort::LastMessage(order) => *order,
+        }
+    }
+}
+
+/// How the server should deliver an outgoing message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SendMethod {
+    /// Send through AppleScript automation of Messages.app
+    AppleScript,
+    /// Send through the Private API helper bundle
+    PrivateApi,
+}
+
+impl SendMethod {
+    pub fn name(&self) -> &str {
+        match self {
+            SendMethod::AppleScript => "apple-script",
+            SendMethod::PrivateApi => "private-api",
+        }
+    }
+}
+
+/// A raw `where` clause for the message query endpoint.
+///
+/// The statement is a TypeORM condition against the `message` table, with
+/// named parameters supplied through `args`.
+///
+/// ```rust,ignore
+/// let filter = QueryFilter::new("message.text LIKE :text").arg("text", "%lunch%");
+/// ```
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryFilter {
+    pub statement: String,
+    pub args: HashMap<String, serde_json::Value>,
+}
+
+impl QueryFilter {
+    pub fn new(statement: impl Into<String>) -> Self {
+        Self {
+            statement: statement.into(),
+            args: HashMap::new(),
+        }
+    }
+
+    pub fn arg(mut self, name: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        self.args.insert(name.into(), value.into());
+        self
+    }
+}
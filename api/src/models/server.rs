@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+/// Details about the server and the Mac it runs on.
+///
+/// Unlike most endpoints these fields are snake case.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerInfo {
+    pub computer_id: Option<String>,
+    /// macOS version, e.g. `14.2.1`
+    pub os_version: String,
+    pub server_version: String,
+    /// Whether the Private API is enabled in the server settings
+    #[serde(default)]
+    pub private_api: bool,
+    /// Whether the Private API helper bundle is injected and connected
+    #[serde(default)]
+    pub helper_connected: bool,
+    /// How the server is exposed, e.g. `Cloudflare`, `Ngrok` or `Dynamic DNS`
+    pub proxy_service: Option<String>,
+    /// The iCloud account signed in on the Mac
+    pub detected_icloud: Option<String>,
+    /// The account iMessage is signed in with
+    pub detected_imessage: Option<String>,
+    #[serde(default)]
+    pub local_ipv4s: Vec<String>,
+    #[serde(default)]
+    pub local_ipv6s: Vec<String>,
+}
+
+/// Number of items in the Messages database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerTotals {
+    pub handles: i64,
+    pub messages: i64,
+    pub chats: i64,
+    pub attachments: i64,
+}
+
+/// Number of attachments by kind
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaTotals {
+    pub images: i64,
+    pub videos: i64,
+    pub locations: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCheck {
+    pub available: bool,
+    /// The version currently installed
+    pub current: String,
+    /// The newer release, when one is available
+    pub metadata: Option<UpdateMetadata>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateMetadata {
+    pub version: String,
+    pub release_date: Option<String>,
+    pub release_name: Option<String>,
+    pub release_notes: Option<String>,
+}
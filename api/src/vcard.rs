@@ -0,0 +1,262 @@
+//! Minimal vCard reader, enough to import the names, numbers, emails and photos
+//! exported by common address books (versions 2.1, 3.0 and 4.0).
+
+use std::path::Path;
+
+use crate::{
+    error::Result,
+    models::{Contact, ContactAddress},
+};
+
+/// Read the contacts in a vCard (`.vcf`) file
+pub async fn parse_file(path: &Path) -> Result<Vec<Contact>> {
+    let text = tokio::fs::read_to_string(path).await?;
+    Ok(parse(&text))
+}
+
+/// Every contact in `text`, cards without a name or address are skipped
+pub fn parse(text: &str) -> Vec<Contact> {
+    let mut contacts = Vec::new();
+    let mut current: Option<Contact> = None;
+
+    for line in unfold(text) {
+        let Some(property) = Property::parse(&line) else {
+            continue;
+        };
+        match property.name.as_str() {
+            "BEGIN" if property.value.eq_ignore_ascii_case("VCARD") => {
+                current = Some(Contact {
+                    source_type: Some("vcf".to_string()),
+                    ..Default::default()
+                });
+            }
+            "END" if property.value.eq_ignore_ascii_case("VCARD") => {
+                if let Some(contact) = current.take()
+                    && (contact.name().is_some() || contact.addresses().next().is_some())
+                {
+                    contacts.push(contact);
+                }
+            }
+            _ => {
+                if let Some(contact) = current.as_mut() {
+                    property.apply(contact);
+                }
+            }
+        }
+    }
+
+    contacts
+}
+
+/// Join folded lines. Continuations start with whitespace, or in version 2.1 follow
+/// a quoted-printable line ending in `=`.
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+        match lines.last_mut() {
+            Some(previous) if line.starts_with([' ', '\t']) => previous.push_str(&line[1..]),
+            Some(previous) if is_soft_break(previous) => {
+                previous.pop();
+                previous.push_str(line);
+            }
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+fn is_soft_break(line: &str) -> bool {
+    line.ends_with('=') && line.to_ascii_uppercase().contains("QUOTED-PRINTABLE")
+}
+
+struct Property {
+    /// Upper case name without the group prefix, e.g. `TEL` for `item1.TEL`
+    name: String,
+    /// Upper case parameters, e.g. `TYPE=CELL` or `ENCODING=B`
+    params: Vec<String>,
+    value: String,
+}
+
+impl Property {
+    fn parse(line: &str) -> Option<Self> {
+        let (head, value) = line.split_once(':')?;
+        let mut parts = head.split(';');
+        let name = parts.next()?;
+        let name = name.rsplit('.').next().unwrap_or(name).to_ascii_uppercase();
+        let params: Vec<String> = parts.map(str::to_ascii_uppercase).collect();
+
+        let value = if params
+            .iter()
+            .any(|param| param.contains("QUOTED-PRINTABLE"))
+        {
+            decode_quoted_printable(value)
+        } else {
+            value.to_string()
+        };
+
+        Some(Self {
+            name,
+            params,
+            value,
+        })
+    }
+
+    fn apply(&self, contact: &mut Contact) {
+        match self.name.as_str() {
+            "FN" => contact.display_name = Some(unescape(&self.value)),
+            "N" => {
+                // Family; Given; Additional; Prefixes; Suffixes
+                let mut parts = split_unescaped(&self.value, ';').into_iter();
+                contact.last_name = parts.next().filter(|name| !name.is_empty());
+                contact.first_name = parts.next().filter(|name| !name.is_empty());
+            }
+            "NICKNAME" => {
+                contact.nickname = split_unescaped(&self.value, ',').into_iter().next();
+            }
+            "TEL" => {
+                let number = self.value.strip_prefix("tel:").unwrap_or(&self.value);
+                contact.phone_numbers.push(address(number));
+            }
+            "EMAIL" => {
+                let email = self.value.strip_prefix("mailto:").unwrap_or(&self.value);
+                contact.emails.push(address(email));
+            }
+            "PHOTO" => contact.avatar = self.photo(),
+            _ => {}
+        }
+    }
+
+    /// The base64 data of an embedded photo, linked photos are not fetched
+    fn photo(&self) -> Option<String> {
+        let data = if let Some(uri) = self.value.strip_prefix("data:") {
+            uri.split_once("base64,")?.1
+        } else if self
+            .params
+            .iter()
+            .any(|param| matches!(param.as_str(), "ENCODING=B" | "ENCODING=BASE64" | "BASE64"))
+        {
+            &self.value
+        } else {
+            return None;
+        };
+        Some(data.chars().filter(|c| !c.is_whitespace()).collect())
+    }
+}
+
+fn address(value: &str) -> ContactAddress {
+    ContactAddress {
+        address: unescape(value).trim().to_string(),
+        id: None,
+    }
+}
+
+fn unescape(value: &str) -> String {
+    split_unescaped(value, '\0').concat()
+}
+
+/// Split `value` on `separator` where it is not escaped with a backslash, unescaping
+/// the parts
+fn split_unescaped(value: &str, separator: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        let part = parts.last_mut().unwrap();
+        match c {
+            '\\' => match chars.next() {
+                Some('n' | 'N') => part.push('\n'),
+                Some(escaped) => part.push(escaped),
+                None => {}
+            },
+            c if c == separator => parts.push(String::new()),
+            c => part.push(c),
+        }
+    }
+    parts
+}
+
+fn decode_quoted_printable(value: &str) -> String {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut input = value.bytes();
+    while let Some(byte) = input.next() {
+        if byte != b'=' {
+            bytes.push(byte);
+            continue;
+        }
+        let hex = [input.next(), input.next()];
+        match hex {
+            [Some(high), Some(low)] => {
+                match u8::from_str_radix(&String::from_utf8_lossy(&[high, low]), 16) {
+                    Ok(decoded) => bytes.push(decoded),
+                    Err(_) => bytes.extend([b'=', high, low]),
+                }
+            }
+            _ => bytes.extend([Some(b'='), hex[0], hex[1]].into_iter().flatten()),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    #[test]
+    fn version_3() {
+        let contacts = parse(
+            "BEGIN:VCARD\r\n\
+            VERSION:3.0\r\n\
+            N:Doe;Jane;;;\r\n\
+            FN:Jane Doe\r\n\
+            item1.TEL;type=CELL:+1 (202) 555-0123\r\n\
+            EMAIL;type=INTERNET:jane@example.com\r\n\
+            PHOTO;ENCODING=b;TYPE=PNG:iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADU\r\n \
+            lEQVR42mNk+M9QDwADhgGAWjR9awAAAABJRU5ErkJggg==\r\n\
+            END:VCARD\r\n",
+        );
+
+        assert_eq!(contacts.len(), 1);
+        let jane = &contacts[0];
+        assert_eq!(jane.name().as_deref(), Some("Jane Doe"));
+        assert_eq!(jane.first_name.as_deref(), Some("Jane"));
+        assert_eq!(jane.phone_numbers[0].address, "+1 (202) 555-0123");
+        assert_eq!(jane.emails[0].address, "jane@example.com");
+        assert!(jane.avatar.as_deref().unwrap().ends_with("ErkJggg=="));
+    }
+
+    #[test]
+    fn version_4() {
+        let contacts = parse(
+            "BEGIN:VCARD\n\
+            VERSION:4.0\n\
+            FN:Smith\\, John\n\
+            TEL;VALUE=uri;TYPE=cell:tel:+44-20-7946-0958\n\
+            PHOTO:data:image/jpeg;base64,/9j/4AAQ\n\
+            END:VCARD\n\
+            BEGIN:VCARD\n\
+            VERSION:4.0\n\
+            NOTE:No name or address\n\
+            END:VCARD\n",
+        );
+
+        assert_eq!(contacts.len(), 1);
+        assert_eq!(contacts[0].name().as_deref(), Some("Smith, John"));
+        assert_eq!(contacts[0].phone_numbers[0].address, "+44-20-7946-0958");
+        assert_eq!(contacts[0].avatar.as_deref(), Some("/9j/4AAQ"));
+    }
+
+    #[test]
+    fn version_2_1_quoted_printable() {
+        let contacts = parse(
+            "BEGIN:VCARD\n\
+            VERSION:2.1\n\
+            N;CHARSET=UTF-8;ENCODING=QUOTED-PRINTABLE:M=C3=BCller;J=C3=B6=\n\
+            rg;;;\n\
+            TEL;CELL:+49301234567\n\
+            END:VCARD\n",
+        );
+
+        assert_eq!(contacts.len(), 1);
+        assert_eq!(contacts[0].name().as_deref(), Some("Jörg Müller"));
+    }
+}
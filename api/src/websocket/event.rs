@@ -0,0 +1,160 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::models;
+
+/// A real-time event pushed by the BlueBubbles server
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum Event {
+    /// A message was sent or received
+    NewMessage(Box<models::Message>),
+    /// A message was delivered, read, edited or unsent
+    UpdatedMessage(Box<models::Message>),
+    /// An outgoing message failed to send, `error` holds the reason code
+    MessageSendError(Box<models::Message>),
+    /// A participant started or stopped typing
+    TypingIndicator(TypingIndicator),
+    /// A chat was marked as read or unread on another device
+    ChatReadStatusChanged(ChatReadStatus),
+    /// A group chat was renamed
+    GroupNameChange(Box<models::Message>),
+    /// A participant was added to a group chat
+    ParticipantAdded(Box<models::Message>),
+    /// A participant was removed from a group chat
+    ParticipantRemoved(Box<models::Message>),
+    /// A participant left a group chat
+    ParticipantLeft(Box<models::Message>),
+    /// A group chat icon was changed
+    GroupIconChanged(Box<models::Message>),
+    /// A group chat icon was removed
+    GroupIconRemoved(Box<models::Message>),
+    /// The server is now reachable at a different URL
+    NewServer(String),
+    /// An event this client does not know about, or one that failed to decode
+    Unknown { name: String, data: Value },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypingIndicator {
+    /// The GUID of the chat the typing participant is in
+    #[serde(rename = "guid")]
+    pub chat_guid: models::ChatGuid,
+    /// Whether the indicator should be shown
+    pub display: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatReadStatus {
+    pub chat_guid: models::ChatGuid,
+    pub read: bool,
+}
+
+impl Event {
+    /// Decode a Socket.IO event by name.
+    ///
+    /// Payloads that don't match the expected shape are returned as [`Event::Unknown`]
+    /// so a single malformed event can't take down the connection.
+    pub(crate) fn decode(name: String, data: Value) -> Self {
+        fn message(data: &Value) -> serde_json::Result<Box<models::Message>> {
+            serde_json::from_value(data.clone())
+        }
+
+        let event = match name.as_str() {
+            "new-message" => message(&data).map(Event::NewMessage),
+            "updated-message" => message(&data).map(Event::UpdatedMessage),
+            "message-send-error" => message(&data).map(Event::MessageSendError),
+            "typing-indicator" => serde_json::from_value(data.clone()).map(Event::TypingIndicator),
+            "chat-read-status-changed" => {
+                serde_json::from_value(data.clone()).map(Event::ChatReadStatusChanged)
+            }
+            "group-name-change" => message(&data).map(Event::GroupNameChange),
+            "participant-added" => message(&data).map(Event::ParticipantAdded),
+            "participant-removed" => message(&data).map(Event::ParticipantRemoved),
+            "participant-left" => message(&data).map(Event::ParticipantLeft),
+            "group-icon-changed" => message(&data).map(Event::GroupIconChanged),
+            "group-icon-removed" => message(&data).map(Event::GroupIconRemoved),
+            "new-server" => serde_json::from_value(data.clone()).map(Event::NewServer),
+            _ => return Event::Unknown { name, data },
+        };
+
+        event.unwrap_or_else(|e| {
+            tracing::warn!("Failed to decode {} event: {}", name, e);
+            Event::Unknown { name, data }
+        })
+    }
+}
+
+/// State changes of the real-time connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConnectionState {
+    /// The socket is connected and authenticated. Sent again after every reconnect,
+    /// events that happened while disconnected are not replayed.
+    Connected,
+    /// The connection dropped
+    Disconnected { reason: String },
+    /// A reconnect will be attempted after `delay`
+    Reconnecting { attempt: u32, delay: Duration },
+    /// The server rejected the password, no further reconnects will be attempted
+    AuthenticationFailed { message: String },
+    /// The connection was closed through its handle
+    Closed,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn decode_new_message() {
+        let response: Value =
+            serde_json::from_str(include_str!("../../test_data/message.json")).unwrap();
+        let event = Event::decode("new-message".to_string(), response["data"].clone());
+
+        let Event::NewMessage(message) = event else {
+            panic!("Expected new message event, got {event:?}");
+        };
+        assert_eq!(message.guid, "9E8C1F3A-1E52-4F39-8C51-6A1C8B4B0D11");
+    }
+
+    #[test]
+    fn decode_typing_indicator() {
+        let event = Event::decode(
+            "typing-indicator".to_string(),
+            json!({"display": true, "guid": "iMessage;-;+12025550123"}),
+        );
+
+        let Event::TypingIndicator(indicator) = event else {
+            panic!("Expected typing indicator event, got {event:?}");
+        };
+        assert_eq!(indicator.chat_guid, "iMessage;-;+12025550123");
+        assert!(indicator.display);
+    }
+
+    #[test]
+    fn decode_read_status() {
+        let event = Event::decode(
+            "chat-read-status-changed".to_string(),
+            json!({"chatGuid": "iMessage;-;+12025550123", "read": false}),
+        );
+
+        assert!(matches!(
+            event,
+            Event::ChatReadStatusChanged(ChatReadStatus { read: false, .. })
+        ));
+    }
+
+    #[test]
+    fn decode_malformed() {
+        let event = Event::decode("new-message".to_string(), json!({"guid": 5}));
+
+        assert!(matches!(event, Event::Unknown { name, .. } if name == "new-message"));
+    }
+}
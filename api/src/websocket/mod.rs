@@ -0,0 +1,331 @@
+//! Real-time events over the BlueBubbles Socket.IO endpoint
+
+mod event;
+mod packet;
+
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use reqwest_websocket::{Message as WsMessage, RequestBuilderExt, WebSocket};
+use tokio::{sync::watch, task::JoinHandle};
+
+pub use event::{ChatReadStatus, ConnectionState, Event, TypingIndicator};
+use packet::{Handshake, Packet, SocketPacket};
+
+use crate::{
+    client::ClientInner,
+    error::{Error, Result},
+};
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Receives events from a real-time connection
+#[async_trait]
+pub trait EventHandler: Send + Sync {
+    async fn on_event(&self, event: Event);
+
+    async fn on_state_change(&self, _state: ConnectionState) {}
+}
+
+/// Handle to a running real-time connection.
+///
+/// The connection is kept alive and reconnected in the background until the
+/// handle is closed or dropped.
+#[derive(Debug)]
+pub struct WebSocketHandle {
+    shutdown: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+impl WebSocketHandle {
+    /// Disconnect from the server and wait for the connection task to finish
+    pub async fn close(self) {
+        let _ = self.shutdown.send(true);
+        let _ = self.task.await;
+    }
+
+    /// Whether the connection task has stopped, e.g. after an authentication failure
+    pub fn is_closed(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+/// Connect to the server and start dispatching events to `handler`.
+///
+/// The first connection attempt happens before this returns, so invalid
+/// credentials are reported to the caller directly. If the server can't be reached
+/// the handle is returned anyway and the connection is retried in the background.
+pub(crate) async fn connect<H>(inner: Arc<ClientInner>, handler: H) -> Result<WebSocketHandle>
+where
+    H: EventHandler + 'static,
+{
+    let session = match Session::open(&inner).await {
+        Ok(session) => Some(session),
+        Err(e @ Error::AuthenticationError { .. }) => return Err(e),
+        Err(e) => {
+            tracing::warn!("Real-time connection failed: {}", e);
+            handler
+                .on_state_change(ConnectionState::Disconnected {
+                    reason: e.to_string(),
+                })
+                .await;
+            None
+        }
+    };
+    let (shutdown, shutdown_rx) = watch::channel(false);
+    let task = tokio::spawn(run(inner, Arc::new(handler), session, shutdown_rx));
+
+    Ok(WebSocketHandle { shutdown, task })
+}
+
+async fn run(
+    inner: Arc<ClientInner>,
+    handler: Arc<dyn EventHandler>,
+    mut session: Option<Session>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let mut backoff = Backoff::default();
+
+    loop {
+        if let Some(session) = session.take() {
+            backoff.reset();
+            handler.on_state_change(ConnectionState::Connected).await;
+
+            match session.run(handler.as_ref(), &mut shutdown).await {
+                SessionEnd::Shutdown => break,
+                SessionEnd::Disconnected(reason) => {
+                    tracing::info!("Real-time connection lost: {}", reason);
+                    handler
+                        .on_state_change(ConnectionState::Disconnected { reason })
+                        .await;
+                }
+            }
+        }
+
+        let delay = backoff.next_delay();
+        handler
+            .on_state_change(ConnectionState::Reconnecting {
+                attempt: backoff.attempt,
+                delay,
+            })
+            .await;
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = shutdown.changed() => break,
+        }
+
+        match Session::open(&inner).await {
+            Ok(new_session) => session = Some(new_session),
+            Err(Error::AuthenticationError { message }) => {
+                tracing::error!("Real-time connection rejected: {}", message);
+                handler
+                    .on_state_change(ConnectionState::AuthenticationFailed { message })
+                    .await;
+                return;
+            }
+            Err(e) => {
+                tracing::warn!("Reconnect attempt {} failed: {}", backoff.attempt, e);
+                handler
+                    .on_state_change(ConnectionState::Disconnected {
+                        reason: e.to_string(),
+                    })
+                    .await;
+            }
+        }
+    }
+
+    handler.on_state_change(ConnectionState::Closed).await;
+}
+
+enum SessionEnd {
+    Shutdown,
+    Disconnected(String),
+}
+
+/// A single authenticated Socket.IO connection
+struct Session {
+    socket: WebSocket,
+    handshake: Handshake,
+}
+
+impl Session {
+    async fn open(inner: &ClientInner) -> Result<Self> {
+        tokio::time::timeout(HANDSHAKE_TIMEOUT, Self::handshake(inner))
+            .await
+            .map_err(|_| Error::SocketError {
+                message: "Timed out waiting for the Socket.IO handshake".to_string(),
+            })?
+    }
+
+    async fn handshake(inner: &ClientInner) -> Result<Self> {
+        let mut socket = inner
+            .http
+            .get(format!("{}/socket.io/", inner.server_url))
+            .query(&[
+                ("EIO", "4"),
+                ("transport", "websocket"),
+                ("password", inner.password.as_str()),
+            ])
+            .upgrade()
+            .send()
+            .await?
+            .into_websocket()
+            .await?;
+
+        let handshake = match next_packet(&mut socket).await? {
+            Packet::Open(data) => serde_json::from_value::<Handshake>(data)
+                .map_err(|e| Error::UnexpectedResponse(format!("Invalid handshake: {e}")))?,
+            packet => {
+                return Err(Error::UnexpectedResponse(format!(
+                    "Expected open packet, got {packet:?}"
+                )));
+            }
+        };
+
+        // Join the default namespace, the server authenticates us with the query password
+        send_packet(&mut socket, Packet::Message(SocketPacket::Connect(None))).await?;
+        loop {
+            match next_packet(&mut socket).await? {
+                Packet::Message(SocketPacket::Connect(_)) => break,
+                Packet::Message(SocketPacket::ConnectError(data)) => {
+                    return Err(Error::AuthenticationError {
+                        message: data["message"]
+                            .as_str()
+                            .unwrap_or("Connection refused")
+                            .to_string(),
+                    });
+                }
+                Packet::Ping => send_packet(&mut socket, Packet::Pong).await?,
+                Packet::Close => {
+                    return Err(Error::SocketError {
+                        message: "Server closed the connection during handshake".to_string(),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        tracing::debug!("Socket.IO session {} established", handshake.sid);
+        Ok(Self { socket, handshake })
+    }
+
+    async fn run(
+        mut self,
+        handler: &dyn EventHandler,
+        shutdown: &mut watch::Receiver<bool>,
+    ) -> SessionEnd {
+        let deadline = self.handshake.heartbeat_deadline();
+
+        loop {
+            let message = tokio::select! {
+                _ = shutdown.changed() => {
+                    let _ = send_packet(
+                        &mut self.socket,
+                        Packet::Message(SocketPacket::Disconnect),
+                    )
+                    .await;
+                    let _ = SinkExt::close(&mut self.socket).await;
+                    return SessionEnd::Shutdown;
+                }
+                message = tokio::time::timeout(deadline, self.socket.next()) => message,
+            };
+
+            let text = match message {
+                Err(_) => return SessionEnd::Disconnected("Heartbeat timed out".to_string()),
+                Ok(None) => return SessionEnd::Disconnected("Connection closed".to_string()),
+                Ok(Some(Err(e))) => return SessionEnd::Disconnected(e.to_string()),
+                Ok(Some(Ok(WsMessage::Text(text)))) => text,
+                Ok(Some(Ok(WsMessage::Close { reason, .. }))) => {
+                    return SessionEnd::Disconnected(format!("Server closed connection: {reason}"));
+                }
+                Ok(Some(Ok(_))) => continue,
+            };
+
+            let packet = match Packet::decode(text.as_str()) {
+                Ok(packet) => packet,
+                Err(e) => {
+                    tracing::warn!("Ignoring invalid Socket.IO packet: {}", e);
+                    continue;
+                }
+            };
+
+            match packet {
+                Packet::Ping => {
+                    if let Err(e) = send_packet(&mut self.socket, Packet::Pong).await {
+                        return SessionEnd::Disconnected(e.to_string());
+                    }
+                }
+                Packet::Message(SocketPacket::Event { name, data, .. }) => {
+                    handler.on_event(Event::decode(name, data)).await;
+                }
+                Packet::Message(SocketPacket::Disconnect) | Packet::Close => {
+                    return SessionEnd::Disconnected("Server closed the session".to_string());
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+async fn next_packet(socket: &mut WebSocket) -> Result<Packet> {
+    loop {
+        match socket.next().await {
+            Some(Ok(WsMessage::Text(text))) => {
+                return Packet::decode(text.as_str())
+                    .map_err(|e| Error::UnexpectedResponse(e.to_string()));
+            }
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => return Err(e.into()),
+            None => {
+                return Err(Error::SocketError {
+                    message: "Connection closed".to_string(),
+                });
+            }
+        }
+    }
+}
+
+async fn send_packet(socket: &mut WebSocket, packet: Packet) -> Result<()> {
+    socket.send(WsMessage::Text(packet.encode())).await?;
+    Ok(())
+}
+
+/// Exponential reconnect delay, doubling from [`INITIAL_BACKOFF`] up to [`MAX_BACKOFF`]
+#[derive(Debug, Default)]
+struct Backoff {
+    attempt: u32,
+}
+
+impl Backoff {
+    fn next_delay(&mut self) -> Duration {
+        let delay = INITIAL_BACKOFF
+            .saturating_mul(2u32.saturating_pow(self.attempt))
+            .min(MAX_BACKOFF);
+        self.attempt += 1;
+        delay
+    }
+
+    fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let mut backoff = Backoff::default();
+        let delays: Vec<u64> = (0..8).map(|_| backoff.next_delay().as_secs()).collect();
+
+        assert_eq!(delays, vec![1, 2, 4, 8, 16, 32, 60, 60]);
+        assert_eq!(backoff.attempt, 8);
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), INITIAL_BACKOFF);
+    }
+}
@@ -0,0 +1,270 @@
+//! Minimal Engine.IO v4 / Socket.IO v5 text packet codec.
+//!
+//! Only the subset used by BlueBubbles is implemented: the default namespace,
+//! text frames, and events without binary attachments.
+
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Handshake data sent by the server in the Engine.IO `open` packet
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Handshake {
+    pub sid: String,
+    pub ping_interval: u64,
+    pub ping_timeout: u64,
+}
+
+impl Handshake {
+    /// How long to wait for the next server ping before considering the connection dead
+    pub fn heartbeat_deadline(&self) -> Duration {
+        Duration::from_millis(self.ping_interval + self.ping_timeout)
+    }
+}
+
+/// An Engine.IO packet
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Packet {
+    Open(Value),
+    Close,
+    Ping,
+    Pong,
+    Message(SocketPacket),
+    Upgrade,
+    Noop,
+}
+
+/// A Socket.IO packet carried inside an Engine.IO message
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SocketPacket {
+    Connect(Option<Value>),
+    Disconnect,
+    Event {
+        name: String,
+        data: Value,
+        ack_id: Option<u64>,
+    },
+    Ack {
+        ack_id: u64,
+        data: Value,
+    },
+    ConnectError(Value),
+    /// Binary packets are not used by BlueBubbles and are not decoded
+    Binary,
+}
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub(crate) enum PacketError {
+    #[error("empty packet")]
+    Empty,
+    #[error("unknown packet type {0:?}")]
+    UnknownType(char),
+    #[error("invalid packet payload: {0}")]
+    InvalidPayload(String),
+}
+
+impl Packet {
+    pub fn decode(text: &str) -> Result<Self, PacketError> {
+        let mut chars = text.chars();
+        let kind = chars.next().ok_or(PacketError::Empty)?;
+        let payload = chars.as_str();
+
+        Ok(match kind {
+            '0' => Packet::Open(parse_json(payload)?),
+            '1' => Packet::Close,
+            '2' => Packet::Ping,
+            '3' => Packet::Pong,
+            '4' => Packet::Message(SocketPacket::decode(payload)?),
+            '5' => Packet::Upgrade,
+            '6' => Packet::Noop,
+            other => return Err(PacketError::UnknownType(other)),
+        })
+    }
+
+    pub fn encode(&self) -> String {
+        match self {
+            Packet::Open(value) => format!("0{value}"),
+            Packet::Close => "1".to_string(),
+            Packet::Ping => "2".to_string(),
+            Packet::Pong => "3".to_string(),
+            Packet::Message(packet) => format!("4{}", packet.encode()),
+            Packet::Upgrade => "5".to_string(),
+            Packet::Noop => "6".to_string(),
+        }
+    }
+}
+
+impl SocketPacket {
+    fn decode(text: &str) -> Result<Self, PacketError> {
+        let mut chars = text.chars();
+        let kind = chars.next().ok_or(PacketError::Empty)?;
+        let mut rest = chars.as_str();
+
+        // Skip an explicit namespace, BlueBubbles only uses the default one
+        if rest.starts_with('/') {
+            rest = rest.split_once(',').map(|(_, rest)| rest).unwrap_or("");
+        }
+
+        // Optional acknowledgement id directly before the payload
+        let id_len = rest.chars().take_while(char::is_ascii_digit).count();
+        let (id, payload) = rest.split_at(id_len);
+        let ack_id = if id.is_empty() {
+            None
+        } else {
+            Some(
+                id.parse::<u64>()
+                    .map_err(|e| PacketError::InvalidPayload(e.to_string()))?,
+            )
+        };
+
+        Ok(match kind {
+            '0' => SocketPacket::Connect(if payload.is_empty() {
+                None
+            } else {
+                Some(parse_json(payload)?)
+            }),
+            '1' => SocketPacket::Disconnect,
+            '2' => {
+                let (name, data) = parse_event(payload)?;
+                SocketPacket::Event { name, data, ack_id }
+            }
+            '3' => SocketPacket::Ack {
+                ack_id: ack_id
+                    .ok_or_else(|| PacketError::InvalidPayload("ack without id".to_string()))?,
+                data: parse_json(payload)?,
+            },
+            '4' => SocketPacket::ConnectError(parse_json(payload)?),
+            '5' | '6' => SocketPacket::Binary,
+            other => return Err(PacketError::UnknownType(other)),
+        })
+    }
+
+    fn encode(&self) -> String {
+        match self {
+            SocketPacket::Connect(None) => "0".to_string(),
+            SocketPacket::Connect(Some(auth)) => format!("0{auth}"),
+            SocketPacket::Disconnect => "1".to_string(),
+            SocketPacket::Event { name, data, ack_id } => {
+                let args = match data {
+                    Value::Null => Value::Array(vec![Value::String(name.clone())]),
+                    data => Value::Array(vec![Value::String(name.clone()), data.clone()]),
+                };
+                match ack_id {
+                    Some(id) => format!("2{id}{args}"),
+                    None => format!("2{args}"),
+                }
+            }
+            SocketPacket::Ack { ack_id, data } => format!("3{ack_id}{data}"),
+            SocketPacket::ConnectError(data) => format!("4{data}"),
+            SocketPacket::Binary => "5".to_string(),
+        }
+    }
+}
+
+fn parse_json(payload: &str) -> Result<Value, PacketError> {
+    serde_json::from_str(payload).map_err(|e| PacketError::InvalidPayload(e.to_string()))
+}
+
+/// Split an event payload `["name", data]` into its name and first argument
+fn parse_event(payload: &str) -> Result<(String, Value), PacketError> {
+    let Value::Array(mut args) = parse_json(payload)? else {
+        return Err(PacketError::InvalidPayload(
+            "event payload is not an array".to_string(),
+        ));
+    };
+    if args.is_empty() {
+        return Err(PacketError::InvalidPayload(
+            "event payload has no name".to_string(),
+        ));
+    }
+    let Value::String(name) = args.remove(0) else {
+        return Err(PacketError::InvalidPayload(
+            "event name is not a string".to_string(),
+        ));
+    };
+    let data = if args.is_empty() {
+        Value::Null
+    } else {
+        args.remove(0)
+    };
+    Ok((name, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn decode_open() {
+        let packet = Packet::decode(
+            r#"0{"sid":"lv_VI97HAXpY6yYWAAAC","upgrades":[],"pingInterval":25000,"pingTimeout":20000,"maxPayload":1000000}"#,
+        )
+        .unwrap();
+        let Packet::Open(value) = packet else {
+            panic!("Expected open packet, got {packet:?}");
+        };
+        let handshake: Handshake = serde_json::from_value(value).unwrap();
+        assert_eq!(handshake.sid, "lv_VI97HAXpY6yYWAAAC");
+        assert_eq!(handshake.heartbeat_deadline(), Duration::from_secs(45));
+    }
+
+    #[test]
+    fn decode_heartbeat() {
+        assert_eq!(Packet::decode("2").unwrap(), Packet::Ping);
+        assert_eq!(Packet::Pong.encode(), "3");
+    }
+
+    #[test]
+    fn decode_event() {
+        let packet =
+            Packet::decode(r#"42["typing-indicator",{"display":true,"guid":"x"}]"#).unwrap();
+        assert_eq!(
+            packet,
+            Packet::Message(SocketPacket::Event {
+                name: "typing-indicator".to_string(),
+                data: json!({"display": true, "guid": "x"}),
+                ack_id: None,
+            })
+        );
+    }
+
+    #[test]
+    fn decode_event_with_namespace_and_ack() {
+        let packet = Packet::decode(r#"42/admin,13["hello-world"]"#).unwrap();
+        assert_eq!(
+            packet,
+            Packet::Message(SocketPacket::Event {
+                name: "hello-world".to_string(),
+                data: Value::Null,
+                ack_id: Some(13),
+            })
+        );
+    }
+
+    #[test]
+    fn decode_connect_error() {
+        let packet = Packet::decode(r#"44{"message":"Authentication failed"}"#).unwrap();
+        assert_eq!(
+            packet,
+            Packet::Message(SocketPacket::ConnectError(
+                json!({"message": "Authentication failed"})
+            ))
+        );
+    }
+
+    #[test]
+    fn encode_connect() {
+        assert_eq!(Packet::Message(SocketPacket::Connect(None)).encode(), "40");
+    }
+
+    #[test]
+    fn decode_invalid() {
+        assert_eq!(Packet::decode(""), Err(PacketError::Empty));
+        assert_eq!(Packet::decode("9"), Err(PacketError::UnknownType('9')));
+        assert!(Packet::decode("42{}").is_err());
+    }
+}
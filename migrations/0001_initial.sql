@@ -0,0 +1,76 @@
+-- Local mirror of the BlueBubbles server data.
+-- Timestamps are milliseconds since the unix epoch, as returned by the server.
+-- `payload` columns hold the full serialized API model so rows can be read back losslessly.
+
+CREATE TABLE handles (
+    id INTEGER PRIMARY KEY NOT NULL, -- server ROWID
+    address TEXT NOT NULL,
+    service TEXT NOT NULL,
+    country TEXT,
+    uncanonicalized_id TEXT
+);
+
+CREATE INDEX handles_address ON handles (address);
+
+CREATE TABLE chats (
+    id INTEGER PRIMARY KEY NOT NULL, -- stable key for the search index
+    guid TEXT NOT NULL UNIQUE,
+    original_row_id INTEGER NOT NULL,
+    style INTEGER NOT NULL,
+    chat_identifier TEXT NOT NULL,
+    display_name TEXT NOT NULL,
+    is_archived INTEGER NOT NULL DEFAULT 0,
+    group_id TEXT,
+    last_message_date INTEGER,
+    payload TEXT NOT NULL
+);
+
+CREATE INDEX chats_last_message_date ON chats (last_message_date DESC);
+
+CREATE TABLE chat_handles (
+    chat_guid TEXT NOT NULL REFERENCES chats (guid) ON DELETE CASCADE,
+    handle_id INTEGER NOT NULL REFERENCES handles (id) ON DELETE CASCADE,
+    PRIMARY KEY (chat_guid, handle_id)
+);
+
+CREATE TABLE messages (
+    id INTEGER PRIMARY KEY NOT NULL, -- stable key for the search index
+    guid TEXT NOT NULL UNIQUE,
+    original_row_id INTEGER NOT NULL,
+    chat_guid TEXT NOT NULL REFERENCES chats (guid) ON DELETE CASCADE,
+    handle_id INTEGER NOT NULL,
+    text TEXT,
+    subject TEXT,
+    error INTEGER NOT NULL DEFAULT 0,
+    is_from_me INTEGER NOT NULL,
+    date_created INTEGER NOT NULL,
+    date_delivered INTEGER,
+    date_read INTEGER,
+    date_edited INTEGER,
+    date_retracted INTEGER,
+    associated_message_guid TEXT,
+    associated_message_type TEXT,
+    thread_originator_guid TEXT,
+    payload TEXT NOT NULL
+);
+
+CREATE INDEX messages_chat_date ON messages (chat_guid, date_created DESC);
+CREATE INDEX messages_associated ON messages (associated_message_guid);
+CREATE INDEX messages_original_row_id ON messages (original_row_id);
+
+CREATE TABLE attachments (
+    guid TEXT PRIMARY KEY NOT NULL,
+    message_guid TEXT NOT NULL REFERENCES messages (guid) ON DELETE CASCADE,
+    original_row_id INTEGER NOT NULL,
+    uti TEXT,
+    mime_type TEXT,
+    transfer_name TEXT,
+    total_bytes INTEGER NOT NULL DEFAULT 0,
+    width INTEGER,
+    height INTEGER,
+    hide_attachment INTEGER NOT NULL DEFAULT 0,
+    is_sticker INTEGER NOT NULL DEFAULT 0,
+    local_path TEXT -- set once the file has been downloaded
+);
+
+CREATE INDEX attachments_message ON attachments (message_guid);
@@ -0,0 +1,16 @@
+-- Progress of the initial history backfill, kept so an interrupted sync can resume,
+-- and the cursor for catching up on changes afterwards.
+
+CREATE TABLE sync_state (
+    id INTEGER PRIMARY KEY NOT NULL CHECK (id = 0), -- single row
+    history_months INTEGER, -- NULL syncs all history, 0 only the chat list
+    started_at INTEGER NOT NULL,
+    backfill_complete INTEGER NOT NULL DEFAULT 0,
+    last_synced_at INTEGER -- when changes were last caught up on, NULL until the first time
+);
+
+CREATE TABLE chat_backfill (
+    chat_guid TEXT PRIMARY KEY NOT NULL REFERENCES chats (guid) ON DELETE CASCADE,
+    fetched INTEGER NOT NULL DEFAULT 0, -- messages fetched so far, the resume offset
+    complete INTEGER NOT NULL DEFAULT 0
+);
@@ -0,0 +1,10 @@
+-- Contacts used to show names instead of addresses.
+
+CREATE TABLE contacts (
+    id INTEGER PRIMARY KEY NOT NULL,
+    source TEXT NOT NULL, -- 'server' for the server's address book, 'vcf' for imported files
+    file TEXT, -- path of the imported file, importing it again replaces its contacts
+    payload TEXT NOT NULL
+);
+
+CREATE INDEX contacts_source ON contacts (source);
@@ -0,0 +1,45 @@
+-- Full-text search. The rowid of each row is the `id` of the message or chat it indexes,
+-- which unlike an implicit rowid is kept by VACUUM.
+-- The message body is written by the app since it may only be in `attributedBody`.
+
+CREATE VIRTUAL TABLE message_search USING fts5 (
+    body,
+    attachments, -- attachment file names
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+CREATE VIRTUAL TABLE chat_search USING fts5 (
+    name, -- display name, identifier and participant addresses
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+CREATE TRIGGER messages_search_delete AFTER DELETE ON messages BEGIN
+    DELETE FROM message_search WHERE rowid = old.id;
+END;
+
+CREATE TRIGGER chats_search_delete AFTER DELETE ON chats BEGIN
+    DELETE FROM chat_search WHERE rowid = old.id;
+END;
+
+-- Index what is already stored, tapbacks have nothing to search
+INSERT INTO message_search (rowid, body, attachments)
+SELECT
+    m.id,
+    COALESCE(m.text, ''),
+    COALESCE(
+        (SELECT group_concat(a.transfer_name, ' ') FROM attachments a WHERE a.message_guid = m.guid),
+        ''
+    )
+FROM messages m
+WHERE m.associated_message_type IS NULL;
+
+INSERT INTO chat_search (rowid, name)
+SELECT
+    c.id,
+    c.display_name || ' ' || c.chat_identifier || ' ' || COALESCE(
+        (SELECT group_concat(h.address, ' ')
+        FROM chat_handles ch JOIN handles h ON h.id = ch.handle_id
+        WHERE ch.chat_guid = c.guid),
+        ''
+    )
+FROM chats c;
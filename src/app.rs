@@ -6,60 +6,134 @@ use relm4::{
     adw, gtk, main_application,
 };
 
-use gtk::prelude::{ApplicationExt, GtkWindowExt, SettingsExt, WidgetExt};
+use bluebubbles_api::{client::Client, models, websocket::Event};
+use chrono::Utc;
+use gettextrs::{gettext, ngettext};
+use gtk::prelude::{
+    ApplicationExt, FileExt, GtkApplicationExt, GtkWindowExt, SettingsExt, WidgetExt,
+};
 use gtk::{gio, glib};
 
 use crate::ui::dialogs::about::AboutDialog;
+use crate::ui::dialogs::preferences::PreferencesDialog;
+use crate::ui::dialogs::quick_reply::{QuickReplyDialog, QuickReplyMsg};
 use crate::ui::dialogs::shortcuts::ShortcutsDialog;
 use crate::{
     config::{APP_ID, PROFILE},
-    ui::pages::onboarding::OnboardingPage,
+    services::{
+        self, background, contacts,
+        credentials::Credentials,
+        events::{self, Update},
+        store::Store,
+        sync::SyncEngine,
+    },
+    ui::{
+        notifications::{self, MessageNotification},
+        pages::{
+            main::{MainPage, MainPageMsg},
+            onboarding::{OnboardingOutput, OnboardingPage, OnboardingPageMsg},
+        },
+    },
 };
 
 pub static APP_BROKER: MessageBroker<AppMsg> = MessageBroker::new();
 
 pub(super) struct App {
     about_dialog: Controller<AboutDialog>,
+    preferences_dialog: Controller<PreferencesDialog>,
     shortcuts_dialog: Controller<ShortcutsDialog>,
+    quick_reply_dialog: Controller<QuickReplyDialog>,
     onboarding_page: Controller<OnboardingPage>,
+    main_page: Controller<MainPage>,
     toaster: Toaster,
+    page: AppPage,
+    /// Kept alive to receive change notifications
+    settings: gio::Settings,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AppPage {
+    /// Waiting for saved credentials to load
+    Loading,
+    Onboarding,
+    Main,
+}
+
+impl AppPage {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Loading => "loading",
+            Self::Onboarding => "onboarding",
+            Self::Main => "main",
+        }
+    }
 }
 
 #[derive(Debug)]
 pub(super) enum AppMsg {
     Quit,
     ShowToast(String),
+    CredentialsLoaded(Option<Credentials>),
+    OnboardingFinished,
+    ImportContacts,
+    RemoveImportedContacts,
+    SignOut,
+    Notify(MessageNotification),
+    /// Show the window with a chat open, e.g. when a notification is clicked
+    OpenChat(models::ChatGuid),
+    /// Reply to the notification for a chat in the quick reply dialog
+    Reply(models::ChatGuid),
+    /// Send a reply typed into a notification
+    SendReply {
+        chat_guid: models::ChatGuid,
+        text: String,
+    },
+    MarkRead(models::ChatGuid),
+    /// The run in background setting was changed
+    BackgroundChanged(bool),
+    /// The portal refused to let the app run in the background
+    BackgroundDenied,
 }
 
 relm4::new_action_group!(pub(super) WindowActionGroup, "win");
 relm4::new_stateless_action!(pub(super) PreferencesAction, WindowActionGroup, "preferences");
 relm4::new_stateless_action!(pub(super) ShortcutsAction, WindowActionGroup, "show-help-overlay");
 relm4::new_stateless_action!(pub(super) AboutAction, WindowActionGroup, "about");
+relm4::new_stateless_action!(pub(super) SearchAction, WindowActionGroup, "search");
+relm4::new_stateless_action!(pub(super) NewChatAction, WindowActionGroup, "new-chat");
+relm4::new_stateless_action!(
+    pub(super) ImportContactsAction,
+    WindowActionGroup,
+    "import-contacts"
+);
+relm4::new_stateless_action!(
+    pub(super) RemoveImportedContactsAction,
+    WindowActionGroup,
+    "remove-imported-contacts"
+);
+relm4::new_stateless_action!(pub(super) SignOutAction, WindowActionGroup, "sign-out");
 
 #[relm4::component(pub)]
 impl SimpleComponent for App {
-    type Init = ();
+    /// Start with the window hidden, e.g. when started at login
+    type Init = bool;
     type Input = AppMsg;
     type Output = ();
     type Widgets = AppWidgets;
 
-    menu! {
-        primary_menu: {
-            section! {
-                "_Preferences" => PreferencesAction,
-                "_Keyboard" => ShortcutsAction,
-                "_About TuxBubbles" => AboutAction,
-            }
-        }
-    }
-
     view! {
         #[root]
         main_window = adw::ApplicationWindow::new(&main_application()) {
-            set_visible: true,
+            set_visible: !hidden,
 
-            connect_close_request[sender] => move |_| {
-                sender.input(AppMsg::Quit);
+            connect_close_request[sender] => move |window| {
+                // Keep the connection open for notifications, the app is shown again
+                // when it's launched
+                if gio::Settings::new(APP_ID).boolean("run-in-background") {
+                    window.set_visible(false);
+                } else {
+                    sender.input(AppMsg::Quit);
+                }
                 glib::Propagation::Stop
             },
 
@@ -76,28 +150,60 @@ impl SimpleComponent for App {
             }
         },
         main_stack = &gtk::Stack {
-            add_titled: (model.onboarding_page.widget(), Some("onboarding"), "Onboarding"),
-        }
+            set_transition_type: gtk::StackTransitionType::Crossfade,
+            add_named: (&loading_page, Some(AppPage::Loading.name())),
+            add_titled: (model.onboarding_page.widget(), Some(AppPage::Onboarding.name()), "Onboarding"),
+            add_named: (model.main_page.widget(), Some(AppPage::Main.name())),
+            #[watch]
+            set_visible_child_name: model.page.name(),
+        },
+        loading_page = &adw::Spinner {},
     }
 
     fn init(
-        _init: Self::Init,
+        hidden: Self::Init,
         root: Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
         let about_dialog = AboutDialog::builder().launch(Some(root.clone())).detach();
 
+        let preferences_dialog = PreferencesDialog::builder()
+            .launch(Some(root.clone()))
+            .detach();
+
         let shortcuts_dialog = ShortcutsDialog::builder()
             .launch(Some(root.clone()))
             .detach();
 
-        let onboarding_page = OnboardingPage::builder().launch(()).detach();
+        let quick_reply_dialog = QuickReplyDialog::builder()
+            .launch(Some(root.clone()))
+            .detach();
+
+        let onboarding_page =
+            OnboardingPage::builder()
+                .launch(())
+                .forward(sender.input_sender(), |output| match output {
+                    OnboardingOutput::Finished => AppMsg::OnboardingFinished,
+                });
+
+        let main_page = MainPage::builder().launch(()).detach();
+
+        let settings = gio::Settings::new(APP_ID);
+        let background_sender = sender.clone();
+        settings.connect_changed(Some("run-in-background"), move |settings, key| {
+            background_sender.input(AppMsg::BackgroundChanged(settings.boolean(key)));
+        });
 
         let model = Self {
             onboarding_page,
+            main_page,
             about_dialog,
+            preferences_dialog,
             shortcuts_dialog,
+            quick_reply_dialog,
             toaster: Toaster::default(),
+            page: AppPage::Loading,
+            settings,
         };
 
         let toast_overlay = model.toaster.overlay_widget();
@@ -114,6 +220,14 @@ impl SimpleComponent for App {
         };
         main_application().set_accelerators_for_action::<ShortcutsAction>(&["<Control>question"]);
 
+        let preferences_action = {
+            let sender = model.preferences_dialog.sender().clone();
+            RelmAction::<PreferencesAction>::new_stateless(move |_| {
+                sender.send(()).unwrap();
+            })
+        };
+        main_application().set_accelerators_for_action::<PreferencesAction>(&["<Control>comma"]);
+
         let about_action = {
             let sender = model.about_dialog.sender().clone();
             RelmAction::<AboutAction>::new_stateless(move |_| {
@@ -121,22 +235,294 @@ impl SimpleComponent for App {
             })
         };
 
+        let search_action = {
+            let sender = model.main_page.sender().clone();
+            RelmAction::<SearchAction>::new_stateless(move |_| {
+                sender.send(MainPageMsg::Search).unwrap();
+            })
+        };
+        main_application()
+            .set_accelerators_for_action::<SearchAction>(&["<Control>f", "<Control>k"]);
+
+        let new_chat_action = {
+            let sender = model.main_page.sender().clone();
+            RelmAction::<NewChatAction>::new_stateless(move |_| {
+                sender.send(MainPageMsg::NewChat).unwrap();
+            })
+        };
+        main_application().set_accelerators_for_action::<NewChatAction>(&["<Control>n"]);
+
+        let import_contacts_action = {
+            let sender = sender.clone();
+            RelmAction::<ImportContactsAction>::new_stateless(move |_| {
+                sender.input(AppMsg::ImportContacts);
+            })
+        };
+
+        let remove_imported_contacts_action = {
+            let sender = sender.clone();
+            RelmAction::<RemoveImportedContactsAction>::new_stateless(move |_| {
+                sender.input(AppMsg::RemoveImportedContacts);
+            })
+        };
+
+        let sign_out_action = {
+            let sender = sender.clone();
+            RelmAction::<SignOutAction>::new_stateless(move |_| {
+                sender.input(AppMsg::SignOut);
+            })
+        };
+
+        actions.add_action(preferences_action);
         actions.add_action(shortcuts_action);
         actions.add_action(about_action);
+        actions.add_action(search_action);
+        actions.add_action(new_chat_action);
+        actions.add_action(import_contacts_action);
+        actions.add_action(remove_imported_contacts_action);
+        actions.add_action(sign_out_action);
         actions.register_for_widget(&widgets.main_window);
 
         widgets.load_window_size();
 
+        notifications::init_portal();
+
+        // Notify about new messages, the store is read off the main thread first
+        let notify_sender = sender.clone();
+        relm4::spawn(events::for_each(move |update| {
+            let Update::Event(Event::NewMessage(message)) = update else {
+                return;
+            };
+            let sender = notify_sender.clone();
+            relm4::spawn(async move {
+                match notifications::prepare(&message).await {
+                    Ok(Some(notification)) => sender.input(AppMsg::Notify(notification)),
+                    Ok(None) => {}
+                    Err(e) => tracing::error!("Failed to prepare a notification: {}", e),
+                }
+            });
+        }));
+
+        let credentials_sender = sender.clone();
+        relm4::spawn(async move {
+            let credentials = Credentials::load().await.unwrap_or_else(|e| {
+                tracing::error!("Failed to load credentials: {}", e);
+                None
+            });
+            credentials_sender.input(AppMsg::CredentialsLoaded(credentials));
+        });
+
         ComponentParts { model, widgets }
     }
 
-    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
         match message {
             AppMsg::Quit => main_application().quit(),
             AppMsg::ShowToast(message) => {
                 let toast = adw::Toast::builder().title(message).build();
                 self.toaster.add_toast(toast);
             }
+            AppMsg::CredentialsLoaded(Some(credentials)) => {
+                let client = credentials.client();
+                services::api::set_client(Some(client.clone()));
+                self.page = AppPage::Main;
+
+                // Go live first, connecting catches up on whatever arrived while the app
+                // was closed. Then finish a history sync that was interrupted by quitting.
+                let main_page = self.main_page.sender().clone();
+                relm4::spawn(async move {
+                    if let Err(e) = contacts::load().await {
+                        tracing::error!("Failed to load contacts: {}", e);
+                    }
+                    connect_events(client.clone()).await;
+
+                    let result = async {
+                        let store = Store::get().await?;
+                        SyncEngine::new(client.clone(), store)
+                            .resume_backfill(|progress| {
+                                tracing::debug!("Resumed sync progress: {:?}", progress)
+                            })
+                            .await
+                    }
+                    .await;
+                    if let Err(e) = result {
+                        tracing::error!("Failed to resume sync: {}", e);
+                    }
+                    main_page.emit(MainPageMsg::Refresh);
+
+                    sync_contacts(client).await;
+                });
+            }
+            AppMsg::CredentialsLoaded(None) => self.page = AppPage::Onboarding,
+            AppMsg::OnboardingFinished => {
+                self.main_page.emit(MainPageMsg::Refresh);
+                self.page = AppPage::Main;
+
+                if let Some(client) = services::api::client() {
+                    relm4::spawn(async move {
+                        connect_events(client.clone()).await;
+                        sync_contacts(client).await;
+                    });
+                }
+            }
+            AppMsg::ImportContacts => {
+                let filter = gtk::FileFilter::new();
+                filter.set_name(Some(&gettext("Contacts")));
+                filter.add_mime_type("text/vcard");
+                filter.add_mime_type("text/x-vcard");
+                filter.add_suffix("vcf");
+                let filters = gio::ListStore::new::<gtk::FileFilter>();
+                filters.append(&filter);
+                let dialog = gtk::FileDialog::builder()
+                    .title(gettext("Import Contacts"))
+                    .filters(&filters)
+                    .modal(true)
+                    .build();
+
+                let window = main_application().active_window();
+                relm4::spawn_local(async move {
+                    // Cancelling the dialog is reported as an error too
+                    let Ok(file) = dialog.open_future(window.as_ref()).await else {
+                        return;
+                    };
+                    let Some(path) = file.path() else {
+                        return;
+                    };
+                    relm4::spawn(async move {
+                        let message = match contacts::import_vcf(&path).await {
+                            Ok(count) => ngettext(
+                                "Imported {count} contact",
+                                "Imported {count} contacts",
+                                count as u32,
+                            )
+                            .replace("{count}", &count.to_string()),
+                            Err(e) => {
+                                tracing::error!("Failed to import contacts: {}", e);
+                                gettext("Could not import contacts")
+                            }
+                        };
+                        sender.input(AppMsg::ShowToast(message));
+                    });
+                });
+            }
+            AppMsg::RemoveImportedContacts => {
+                relm4::spawn(async move {
+                    let message = match contacts::remove_imported().await {
+                        Ok(()) => gettext("Removed imported contacts"),
+                        Err(e) => {
+                            tracing::error!("Failed to remove imported contacts: {}", e);
+                            gettext("Could not remove imported contacts")
+                        }
+                    };
+                    sender.input(AppMsg::ShowToast(message));
+                });
+            }
+            AppMsg::SignOut => {
+                services::api::set_client(None);
+                self.onboarding_page.emit(OnboardingPageMsg::Reset);
+                self.page = AppPage::Onboarding;
+
+                notifications::set_open_chat(None);
+                notifications::clear_muted();
+                // Nothing of this server may show up after signing in to another one
+                let main_page = self.main_page.sender().clone();
+                relm4::spawn(async move {
+                    events::disconnect().await;
+                    if let Err(e) = Credentials::clear().await {
+                        tracing::error!("Failed to clear credentials: {}", e);
+                    }
+                    let result = async {
+                        Store::get().await?.clear().await?;
+                        contacts::load().await
+                    }
+                    .await;
+                    if let Err(e) = result {
+                        tracing::error!("Failed to delete stored messages: {}", e);
+                    }
+                    main_page.emit(MainPageMsg::Refresh);
+                });
+            }
+            AppMsg::BackgroundChanged(enabled) => {
+                relm4::spawn(async move {
+                    match background::request(enabled).await {
+                        Ok(allowed) => {
+                            if enabled && !allowed {
+                                sender.input(AppMsg::BackgroundDenied);
+                            }
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to request running in background: {}", e);
+                            if enabled {
+                                sender.input(AppMsg::ShowToast(gettext(
+                                    "Could not set up starting at login",
+                                )));
+                            }
+                        }
+                    }
+                });
+            }
+            AppMsg::BackgroundDenied => {
+                // Turning the setting off also removes the autostart entry
+                if let Err(e) = self.settings.set_boolean("run-in-background", false) {
+                    tracing::error!("Failed to reset run in background: {}", e);
+                }
+                sender.input(AppMsg::ShowToast(gettext(
+                    "Running in the background was not allowed",
+                )));
+            }
+            AppMsg::Notify(notification) => notifications::show(notification),
+            AppMsg::OpenChat(chat_guid) => {
+                present_window();
+                let main_page = self.main_page.sender().clone();
+                relm4::spawn(async move {
+                    let chat = async { Store::get().await?.chat(&chat_guid).await }.await;
+                    match chat {
+                        Ok(Some(chat)) => main_page.emit(MainPageMsg::OpenChat {
+                            chat,
+                            message_guid: None,
+                        }),
+                        Ok(None) => tracing::warn!("Chat {} is not stored", chat_guid),
+                        Err(e) => tracing::error!("Failed to load chat: {}", e),
+                    }
+                });
+            }
+            AppMsg::Reply(chat_guid) => match notifications::posted(&chat_guid) {
+                Some(notification) => {
+                    present_window();
+                    self.quick_reply_dialog
+                        .emit(QuickReplyMsg::Show(notification));
+                }
+                // Posted before the app was restarted, the message is only in the chat
+                None => sender.input(AppMsg::OpenChat(chat_guid)),
+            },
+            AppMsg::SendReply { chat_guid, text } => notifications::send_reply(chat_guid, text),
+            AppMsg::MarkRead(chat_guid) => {
+                notifications::withdraw(&chat_guid);
+                let Some(client) = services::api::client() else {
+                    return;
+                };
+                let main_page = self.main_page.sender().clone();
+                relm4::spawn(async move {
+                    if let Err(e) = client.chats().mark_read().guid(&chat_guid).send().await {
+                        tracing::error!("Failed to mark chat as read: {}", e);
+                        APP_BROKER.send(AppMsg::ShowToast(gettext(
+                            "Could not mark the conversation as read",
+                        )));
+                        return;
+                    }
+                    let result = async {
+                        Store::get()
+                            .await?
+                            .mark_chat_read(&chat_guid, Utc::now().timestamp_millis())
+                            .await
+                    }
+                    .await;
+                    match result {
+                        Ok(()) => main_page.emit(MainPageMsg::RefreshChat(chat_guid)),
+                        Err(e) => tracing::error!("Failed to store read state: {}", e),
+                    }
+                });
+            }
         }
     }
 
@@ -145,6 +531,31 @@ impl SimpleComponent for App {
     }
 }
 
+/// Bring the window to the front, it may have been hidden or unfocused
+fn present_window() {
+    if let Some(window) = main_application().windows().first() {
+        window.present();
+    }
+}
+
+/// Start receiving real-time events. An unreachable server is retried in the
+/// background, only a rejected password fails here.
+async fn connect_events(client: Client) {
+    if let Err(e) = events::connect(client).await {
+        tracing::error!("Failed to open the real-time connection: {}", e);
+        APP_BROKER.send(AppMsg::ShowToast(gettext(
+            "Could not connect to the server, new messages will not appear",
+        )));
+    }
+}
+
+/// Refresh contact names from the server, the stored ones stay in use if that fails
+async fn sync_contacts(client: Client) {
+    if let Err(e) = contacts::sync(&client).await {
+        tracing::error!("Failed to sync contacts: {}", e);
+    }
+}
+
 impl AppWidgets {
     fn save_window_size(&self) -> Result<(), glib::BoolError> {
         let settings = gio::Settings::new(APP_ID);
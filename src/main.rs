@@ -3,9 +3,10 @@ mod config;
 mod services;
 mod ui;
 
+use bluebubbles_api::models;
 use config::{APP_ID, GETTEXT_PACKAGE, LOCALEDIR, RESOURCES_FILE};
 use gettextrs::{LocaleCategory, gettext};
-use gtk::prelude::ApplicationExt;
+use gtk::prelude::{ApplicationExt, GtkApplicationExt, GtkWindowExt};
 use gtk::{gio, glib};
 use relm4::{
     RelmApp,
@@ -13,10 +14,15 @@ use relm4::{
     gtk, main_application, set_global_css,
 };
 
-use app::{APP_BROKER, App};
+use app::{APP_BROKER, App, AppMsg};
+use services::background::BACKGROUND_ARG;
 
 relm4::new_action_group!(AppActionGroup, "app");
 relm4::new_stateless_action!(QuitAction, AppActionGroup, "quit");
+// Notification actions, the target is the chat GUID
+relm4::new_stateful_action!(OpenChatAction, AppActionGroup, "open-chat", String, ());
+relm4::new_stateful_action!(ReplyAction, AppActionGroup, "reply", String, ());
+relm4::new_stateful_action!(MarkReadAction, AppActionGroup, "mark-read", String, ());
 
 fn main() {
     gtk::init().unwrap();
@@ -51,11 +57,34 @@ fn main() {
         })
     };
     actions.add_action(quit_action);
+    actions.add_action(RelmAction::<OpenChatAction>::new_with_target_value(
+        |_, chat_guid| send_chat_action(chat_guid, AppMsg::OpenChat),
+    ));
+    actions.add_action(RelmAction::<ReplyAction>::new_with_target_value(
+        |_, chat_guid| send_chat_action(chat_guid, AppMsg::Reply),
+    ));
+    actions.add_action(RelmAction::<MarkReadAction>::new_with_target_value(
+        |_, chat_guid| send_chat_action(chat_guid, AppMsg::MarkRead),
+    ));
     actions.register_for_main_application();
 
     app.set_accelerators_for_action::<QuitAction>(&["<Control>q"]);
 
-    let app = RelmApp::from_app(app).with_broker(&APP_BROKER);
+    // Launching the app again while it runs in the background shows the window. The
+    // first activation creates it.
+    app.connect_activate(|app| {
+        if let Some(window) = app.windows().first() {
+            window.present();
+        }
+    });
+
+    // Started at login, GApplication doesn't know the flag so it is taken out here
+    let (background, args): (Vec<String>, Vec<String>) =
+        std::env::args().partition(|arg| arg == BACKGROUND_ARG);
+
+    let app = RelmApp::from_app(app)
+        .with_broker(&APP_BROKER)
+        .with_args(args);
 
     let data = res
         .lookup_data(
@@ -64,5 +93,15 @@ fn main() {
         )
         .unwrap();
     set_global_css(&glib::GString::from_utf8_checked(data.to_vec()).unwrap());
-    app.visible_on_activate(false).run::<App>(());
+    app.visible_on_activate(false)
+        .run::<App>(!background.is_empty());
+}
+
+/// Send the message for a notification action to the app, the action targets are
+/// checked since any process can activate them
+fn send_chat_action(chat_guid: String, message: fn(models::ChatGuid) -> AppMsg) {
+    match chat_guid.parse() {
+        Ok(guid) => APP_BROKER.send(message(guid)),
+        Err(e) => tracing::warn!("Invalid chat GUID {}: {}", chat_guid, e),
+    }
 }
@@ -1 +1,14 @@
+use std::sync::RwLock;
 
+use bluebubbles_api::client::Client;
+
+static CLIENT: RwLock<Option<Client>> = RwLock::new(None);
+
+/// The client for the signed in server, `None` while onboarding
+pub fn client() -> Option<Client> {
+    CLIENT.read().unwrap().clone()
+}
+
+pub fn set_client(client: Option<Client>) {
+    *CLIENT.write().unwrap() = client;
+}
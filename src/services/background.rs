@@ -0,0 +1,22 @@
+use anyhow::Result;
+use ashpd::desktop::background::Background;
+use gettextrs::gettext;
+
+/// Passed by the autostart entry, the app starts without showing its window
+pub const BACKGROUND_ARG: &str = "--background";
+
+/// Ask the Background portal to keep the app running with its window closed and to
+/// start it at login. Returns whether running in the background was allowed.
+///
+/// Disabling removes the autostart entry again.
+pub async fn request(enable: bool) -> Result<bool> {
+    let response = Background::request()
+        .reason(gettext("Receive messages while the window is closed").as_str())
+        .auto_start(enable)
+        .command(["tuxbubbles", BACKGROUND_ARG])
+        .dbus_activatable(false)
+        .send()
+        .await?
+        .response()?;
+    Ok(response.run_in_background())
+}
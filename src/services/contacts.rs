@@ -0,0 +1,159 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use anyhow::Result;
+use bluebubbles_api::{client::Client, models, vcard};
+use phonenumber::{Mode, country};
+use relm4::gtk::glib;
+
+use crate::services::{
+    events::{self, Update},
+    store::Store,
+};
+
+/// Contacts from the server's address book, replaced on every sync
+const SERVER_SOURCE: &str = "server";
+/// Contacts imported from vCard files
+const VCF_SOURCE: &str = "vcf";
+
+static CONTACTS: LazyLock<RwLock<Arc<ContactBook>>> = LazyLock::new(Default::default);
+
+/// Region of the system locale, used for phone numbers without a country
+static DEFAULT_REGION: LazyLock<Option<country::Id>> = LazyLock::new(|| {
+    glib::language_names().iter().find_map(|name| {
+        // e.g. `en_US.UTF-8` or `de_DE@euro`
+        let locale = name.split(['.', '@']).next()?;
+        locale.split_once('_')?.1.parse().ok()
+    })
+});
+
+/// Name of the contact owning `address`, `country` is the handle's country code used to
+/// read local phone numbers
+pub fn name(address: &str, country: Option<&str>) -> Option<String> {
+    book()
+        .entry(address, country)
+        .map(|entry| entry.name.clone())
+}
+
+/// The contact photo for `address`, as encoded image data
+pub fn avatar(address: &str, country: Option<&str>) -> Option<glib::Bytes> {
+    book().entry(address, country)?.avatar.clone()
+}
+
+/// Every address in the contact book with the name of its contact, addresses are
+/// normalized like [`normalize`] does
+pub fn addresses() -> Vec<(String, String)> {
+    book()
+        .entries
+        .iter()
+        .map(|(address, entry)| (address.clone(), entry.name.clone()))
+        .collect()
+}
+
+fn book() -> Arc<ContactBook> {
+    CONTACTS.read().unwrap().clone()
+}
+
+/// Read the stored contacts into memory
+pub async fn load() -> Result<()> {
+    let contacts = Store::get().await?.contacts().await?;
+    *CONTACTS.write().unwrap() = Arc::new(ContactBook::new(&contacts));
+    events::publish(Update::ContactsChanged);
+    Ok(())
+}
+
+/// Fetch the server's address book, including photos
+pub async fn sync(client: &Client) -> Result<()> {
+    let contacts = client.contacts().get().with_avatars().send().await?;
+    Store::get()
+        .await?
+        .replace_contacts(SERVER_SOURCE, &contacts)
+        .await?;
+    load().await
+}
+
+/// Add the contacts in a vCard file, returning how many were found.
+///
+/// Importing a file again replaces the contacts it added before.
+pub async fn import_vcf(path: &Path) -> Result<usize> {
+    let contacts = vcard::parse_file(path).await?;
+    Store::get()
+        .await?
+        .replace_file_contacts(VCF_SOURCE, &path.to_string_lossy(), &contacts)
+        .await?;
+    load().await?;
+    Ok(contacts.len())
+}
+
+/// Remove every contact imported from vCard files
+pub async fn remove_imported() -> Result<()> {
+    Store::get().await?.remove_contacts(VCF_SOURCE).await?;
+    load().await
+}
+
+/// Contacts indexed by normalized address
+#[derive(Debug, Default)]
+struct ContactBook {
+    entries: HashMap<String, Arc<Entry>>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    name: String,
+    avatar: Option<glib::Bytes>,
+}
+
+impl ContactBook {
+    fn new(contacts: &[models::Contact]) -> Self {
+        let mut entries = HashMap::new();
+        for contact in contacts {
+            let Some(name) = contact.name() else {
+                continue;
+            };
+            let avatar = contact
+                .avatar
+                .as_deref()
+                .filter(|avatar| !avatar.is_empty())
+                .map(|avatar| glib::Bytes::from_owned(glib::base64_decode(avatar)));
+            let entry = Arc::new(Entry { name, avatar });
+
+            for address in contact.addresses() {
+                // The first contact listing an address wins
+                entries
+                    .entry(normalize(address, None))
+                    .or_insert_with(|| entry.clone());
+            }
+        }
+        Self { entries }
+    }
+
+    fn entry(&self, address: &str, country: Option<&str>) -> Option<&Entry> {
+        self.entries
+            .get(&normalize(address, country))
+            .map(Arc::as_ref)
+    }
+}
+
+/// Key an address so different spellings of it match: phone numbers in E.164 form,
+/// emails in lower case
+pub fn normalize(address: &str, country: Option<&str>) -> String {
+    let address = address.trim();
+    if address.contains('@') {
+        return address.to_lowercase();
+    }
+
+    let region = country
+        .and_then(|country| country.to_ascii_uppercase().parse().ok())
+        .or(*DEFAULT_REGION);
+    match phonenumber::parse(region, address) {
+        Ok(number) => number.format().mode(Mode::E164).to_string(),
+        // Short codes and the like, compare the digits only
+        Err(_) => address
+            .chars()
+            .filter(|c| c.is_ascii_digit() || *c == '+')
+            .collect(),
+    }
+}
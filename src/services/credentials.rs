@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use bluebubbles_api::client::Client;
+use gtk::prelude::SettingsExt;
+use relm4::gtk::{self, gio};
+
+use crate::config::APP_ID;
+
+/// Connection details for the BlueBubbles server.
+///
+/// The URL lives in GSettings, the password only ever in the keyring. `oo7` talks to
+/// the Secret Service on the host and falls back to an encrypted file inside the sandbox.
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub server_url: String,
+    pub password: String,
+}
+
+impl Credentials {
+    /// Load saved credentials, `None` if the user hasn't signed in yet
+    pub async fn load() -> Result<Option<Self>> {
+        let server_url = gio::Settings::new(APP_ID).string("server-url").to_string();
+        if server_url.is_empty() {
+            return Ok(None);
+        }
+
+        let keyring = oo7::Keyring::new().await?;
+        keyring.unlock().await?;
+        let items = keyring.search_items(&attributes()).await?;
+        let Some(item) = items.first() else {
+            return Ok(None);
+        };
+        let password = String::from_utf8(item.secret().await?.to_vec())?;
+
+        Ok(Some(Self {
+            server_url,
+            password,
+        }))
+    }
+
+    pub async fn save(&self) -> Result<()> {
+        let keyring = oo7::Keyring::new().await?;
+        keyring.unlock().await?;
+        keyring
+            .create_item(
+                "BlueBubbles server password",
+                &attributes(),
+                oo7::Secret::text(&self.password),
+                true,
+            )
+            .await?;
+
+        gio::Settings::new(APP_ID).set_string("server-url", &self.server_url)?;
+        Ok(())
+    }
+
+    /// Forget the saved credentials, used when signing out
+    pub async fn clear() -> Result<()> {
+        let keyring = oo7::Keyring::new().await?;
+        keyring.delete(&attributes()).await?;
+
+        gio::Settings::new(APP_ID).reset("server-url");
+        Ok(())
+    }
+
+    pub fn client(&self) -> Client {
+        Client::builder()
+            .server_url(self.server_url.clone())
+            .password(self.password.clone())
+            .build()
+    }
+}
+
+fn attributes() -> HashMap<&'static str, &'static str> {
+    HashMap::from([("application", APP_ID), ("type", "server-password")])
+}
@@ -0,0 +1,135 @@
+use std::sync::{LazyLock, Mutex};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bluebubbles_api::{
+    client::Client,
+    websocket::{ConnectionState, Event, EventHandler, WebSocketHandle},
+};
+use tokio::sync::broadcast;
+
+use crate::services::{store::Store, sync::SyncEngine};
+
+/// Updates buffered per subscriber before the slowest one starts missing them
+const CHANNEL_CAPACITY: usize = 256;
+
+static UPDATES: LazyLock<broadcast::Sender<Update>> =
+    LazyLock::new(|| broadcast::channel(CHANNEL_CAPACITY).0);
+static CONNECTION: Mutex<Option<WebSocketHandle>> = Mutex::new(None);
+
+/// Changes pushed to the UI, sent after they have been written to the [`Store`]
+#[derive(Debug, Clone)]
+pub enum Update {
+    Event(Event),
+    State(ConnectionState),
+    /// Messages missed while disconnected have been fetched
+    CaughtUp,
+    /// Contact names or photos changed
+    ContactsChanged,
+}
+
+/// Receive updates from the real-time connection
+pub fn subscribe() -> broadcast::Receiver<Update> {
+    UPDATES.subscribe()
+}
+
+/// Send an update that did not come from the real-time connection
+pub(super) fn publish(update: Update) {
+    // Nobody listening is fine, e.g. while the window is being built
+    let _ = UPDATES.send(update);
+}
+
+/// Call `f` for every update.
+///
+/// Updates missed because the receiver fell behind are reported as [`Update::CaughtUp`],
+/// so it reloads from the store.
+pub async fn for_each(mut f: impl FnMut(Update)) {
+    let mut updates = subscribe();
+    loop {
+        match updates.recv().await {
+            Ok(update) => f(update),
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                tracing::warn!("Missed {} real-time updates", missed);
+                f(Update::CaughtUp);
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+/// Open the real-time connection for a signed in client, replacing any previous one
+pub async fn connect(client: Client) -> Result<()> {
+    let handler = StoreHandler {
+        client: client.clone(),
+    };
+    let handle = client.connect_websocket(handler).await?;
+    // Dropping the previous handle closes its connection
+    *CONNECTION.lock().unwrap() = Some(handle);
+    Ok(())
+}
+
+pub async fn disconnect() {
+    let handle = CONNECTION.lock().unwrap().take();
+    if let Some(handle) = handle {
+        handle.close().await;
+    }
+}
+
+/// Writes events to the store before passing them on
+struct StoreHandler {
+    client: Client,
+}
+
+impl StoreHandler {
+    async fn store(&self, event: &Event) -> Result<()> {
+        let message = match event {
+            Event::NewMessage(message)
+            | Event::UpdatedMessage(message)
+            | Event::MessageSendError(message)
+            | Event::GroupNameChange(message)
+            | Event::ParticipantAdded(message)
+            | Event::ParticipantRemoved(message)
+            | Event::ParticipantLeft(message)
+            | Event::GroupIconChanged(message)
+            | Event::GroupIconRemoved(message) => message,
+            _ => return Ok(()),
+        };
+        let store = Store::get().await?;
+        SyncEngine::new(self.client.clone(), store)
+            .store_message(message)
+            .await
+    }
+}
+
+#[async_trait]
+impl EventHandler for StoreHandler {
+    async fn on_event(&self, event: Event) {
+        if let Err(e) = self.store(&event).await {
+            tracing::error!("Failed to store event: {}", e);
+        }
+        publish(Update::Event(event));
+    }
+
+    async fn on_state_change(&self, state: ConnectionState) {
+        let connected = state == ConnectionState::Connected;
+        publish(Update::State(state));
+
+        // Events are not replayed after a reconnect, fetch what was missed. This runs
+        // separately so the connection keeps answering heartbeats meanwhile.
+        if connected {
+            let client = self.client.clone();
+            tokio::spawn(async move {
+                match catch_up(client).await {
+                    Ok(()) => publish(Update::CaughtUp),
+                    Err(e) => tracing::error!("Failed to catch up after connecting: {}", e),
+                }
+            });
+        }
+    }
+}
+
+async fn catch_up(client: Client) -> Result<()> {
+    let store = Store::get().await?;
+    SyncEngine::new(client, store).catch_up().await?;
+    Ok(())
+}
@@ -1 +1,8 @@
 pub mod api;
+pub mod background;
+pub mod contacts;
+pub mod credentials;
+pub mod events;
+pub mod notification_portal;
+pub mod store;
+pub mod sync;
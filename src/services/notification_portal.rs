@@ -0,0 +1,135 @@
+use std::sync::OnceLock;
+
+use anyhow::{Result, bail};
+use ashpd::{
+    desktop::{
+        Icon,
+        notification::{Action, Button, ButtonPurpose, Category, Notification, NotificationProxy},
+    },
+    zvariant::{Dict, OwnedValue},
+};
+use bluebubbles_api::models;
+use futures_util::StreamExt;
+use gettextrs::gettext;
+
+const OPEN_CHAT_ACTION: &str = "open-chat";
+const REPLY_ACTION: &str = "reply";
+const MARK_READ_ACTION: &str = "mark-read";
+
+/// Set once the portal is known to take typed replies
+static PROXY: OnceLock<NotificationProxy<'static>> = OnceLock::new();
+
+/// What was done with a notification posted through the portal, by chat GUID
+#[derive(Debug)]
+pub enum PortalAction {
+    OpenChat(models::ChatGuid),
+    /// `text` is `None` if the reply button was used without typing anything
+    Reply {
+        chat_guid: models::ChatGuid,
+        text: Option<String>,
+    },
+    MarkRead(models::ChatGuid),
+}
+
+/// A notification for a chat, posted through the portal
+#[derive(Debug)]
+pub struct ChatNotification<'a> {
+    pub chat_guid: &'a models::ChatGuid,
+    pub title: &'a str,
+    pub body: &'a str,
+    /// Encoded image data
+    pub avatar: Option<&'a [u8]>,
+}
+
+/// Use version 2 of the Notification portal if it supports replying from the
+/// notification, calling `on_action` for every action taken on one.
+///
+/// GNotification only uses version 1, which has buttons but no text field. Returns
+/// whether the portal is used.
+pub async fn init(on_action: impl Fn(PortalAction) + Send + 'static) -> Result<bool> {
+    let proxy = NotificationProxy::new().await?;
+    // Fails on version 1
+    let (_, purposes) = proxy.supported_options().await?;
+    if !purposes.contains(&ButtonPurpose::ImReplyWithText) {
+        return Ok(false);
+    }
+
+    let mut actions = proxy.receive_action_invoked().await?;
+    if PROXY.set(proxy).is_err() {
+        bail!("The notification portal was set up twice");
+    }
+    relm4::spawn(async move {
+        while let Some(action) = actions.next().await {
+            match parse_action(&action) {
+                Some(action) => on_action(action),
+                None => tracing::warn!("Unknown notification action {}", action.name()),
+            }
+        }
+    });
+    Ok(true)
+}
+
+/// Whether notifications are posted through the portal
+pub fn is_available() -> bool {
+    PROXY.get().is_some()
+}
+
+/// Post a notification with a reply field, replacing the one with the same `id`
+pub async fn add(id: &str, notification: ChatNotification<'_>) -> Result<()> {
+    let Some(proxy) = PROXY.get() else {
+        bail!("The notification portal is not available");
+    };
+    let guid = notification.chat_guid.as_str();
+    let posted = Notification::new(notification.title)
+        .body(notification.body)
+        .icon(
+            notification
+                .avatar
+                .map(|avatar| Icon::Bytes(avatar.to_vec())),
+        )
+        .category(Category::ImMessage)
+        .default_action(OPEN_CHAT_ACTION)
+        .default_action_target(guid)
+        .button(
+            Button::new(&gettext("Reply"), REPLY_ACTION)
+                .target(guid)
+                .purpose(ButtonPurpose::ImReplyWithText),
+        )
+        .button(Button::new(&gettext("Mark as Read"), MARK_READ_ACTION).target(guid));
+    proxy.add_notification(id, posted).await?;
+    Ok(())
+}
+
+pub async fn remove(id: &str) -> Result<()> {
+    if let Some(proxy) = PROXY.get() {
+        proxy.remove_notification(id).await?;
+    }
+    Ok(())
+}
+
+/// The parameters are the target, the chat GUID, and platform data holding the typed
+/// reply as `response`
+fn parse_action(action: &Action) -> Option<PortalAction> {
+    let mut parameters = action.parameter().iter();
+    let chat_guid = parameters
+        .next()?
+        .downcast_ref::<&str>()
+        .ok()?
+        .parse()
+        .ok()?;
+    match action.name() {
+        OPEN_CHAT_ACTION => Some(PortalAction::OpenChat(chat_guid)),
+        REPLY_ACTION => Some(PortalAction::Reply {
+            chat_guid,
+            text: parameters.next().and_then(reply_text),
+        }),
+        MARK_READ_ACTION => Some(PortalAction::MarkRead(chat_guid)),
+        _ => None,
+    }
+}
+
+fn reply_text(platform_data: &OwnedValue) -> Option<String> {
+    let platform_data = platform_data.downcast_ref::<Dict>().ok()?;
+    let text: String = platform_data.get(&"response").ok()??;
+    (!text.trim().is_empty()).then_some(text)
+}
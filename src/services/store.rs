@@ -0,0 +1,886 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use bluebubbles_api::models;
+use relm4::gtk::glib;
+use sqlx::{
+    SqliteConnection, SqlitePool,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+    types::Json,
+};
+use tokio::sync::OnceCell;
+
+use crate::services::contacts;
+
+static STORE: OnceCell<Store> = OnceCell::const_new();
+
+/// Marks the start of a matched term in [`SearchHit::snippet`]
+pub const MATCH_START: char = '\u{2}';
+/// Marks the end of a matched term in [`SearchHit::snippet`]
+pub const MATCH_END: char = '\u{3}';
+
+/// Persistent local copy of chats and messages
+#[derive(Debug, Clone)]
+pub struct Store {
+    pool: SqlitePool,
+}
+
+impl Store {
+    /// Get the shared store, opening the database on first use
+    pub async fn get() -> Result<&'static Store> {
+        STORE
+            .get_or_try_init(|| async { Store::open(&Self::default_path()).await })
+            .await
+    }
+
+    pub fn default_path() -> PathBuf {
+        glib::user_data_dir()
+            .join(env!("CARGO_PKG_NAME"))
+            .join("messages.db")
+    }
+
+    pub async fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .foreign_keys(true);
+        let pool = SqlitePoolOptions::new().connect_with(options).await?;
+
+        sqlx::migrate!().run(&pool).await?;
+
+        Ok(Self { pool })
+    }
+
+    /// Delete everything stored for the server, used when signing out
+    pub async fn clear(&self) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        // Attachments, backfill progress and search rows go with their messages and chats
+        for table in ["messages", "chats", "handles", "sync_state", "contacts"] {
+            sqlx::query(&format!("DELETE FROM {table}"))
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Insert or update a chat along with its participants and last message
+    pub async fn upsert_chat(&self, chat: &models::Chat) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        upsert_chat(&mut tx, chat).await?;
+        if let Some(message) = &chat.last_message {
+            upsert_message(&mut tx, &chat.guid, message).await?;
+        }
+        for message in &chat.messages {
+            upsert_message(&mut tx, &chat.guid, message).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Insert or update messages belonging to a chat that is already stored
+    pub async fn upsert_messages(
+        &self,
+        chat_guid: &str,
+        messages: &[models::Message],
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for message in messages {
+            upsert_message(&mut tx, chat_guid, message).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Chats ordered by most recent activity, each with its last message attached
+    pub async fn chats(&self, limit: u32, offset: u32) -> Result<Vec<models::Chat>> {
+        let rows: Vec<ChatWithLastMessage> = sqlx::query_as(
+            "SELECT c.payload,
+                (SELECT m.payload FROM messages m
+                    WHERE m.chat_guid = c.guid
+                    ORDER BY m.date_created DESC LIMIT 1)
+            FROM chats c
+            ORDER BY c.last_message_date DESC NULLS LAST
+            LIMIT ? OFFSET ?",
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(with_last_message).collect())
+    }
+
+    /// A chat with its last message attached, as listed by [`Store::chats`]
+    pub async fn listed_chat(&self, guid: &str) -> Result<Option<models::Chat>> {
+        let row: Option<ChatWithLastMessage> = sqlx::query_as(
+            "SELECT c.payload,
+                (SELECT m.payload FROM messages m
+                    WHERE m.chat_guid = c.guid
+                    ORDER BY m.date_created DESC LIMIT 1)
+            FROM chats c
+            WHERE c.guid = ?",
+        )
+        .bind(guid)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(with_last_message))
+    }
+
+    pub async fn chat(&self, guid: &str) -> Result<Option<models::Chat>> {
+        let chat: Option<Json<models::Chat>> =
+            sqlx::query_scalar("SELECT payload FROM chats WHERE guid = ?")
+                .bind(guid)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(chat.map(|Json(chat)| chat))
+    }
+
+    /// A page of a conversation, newest first.
+    ///
+    /// Pass the `date_created` of the oldest message already shown as `before`
+    /// to load the next page of older history. The bound is inclusive so messages
+    /// sharing that millisecond aren't skipped, the ones already shown come back too.
+    pub async fn messages(
+        &self,
+        chat_guid: &str,
+        before: Option<i64>,
+        limit: u32,
+    ) -> Result<Vec<models::Message>> {
+        let messages: Vec<Json<models::Message>> = sqlx::query_scalar(
+            "SELECT payload FROM messages
+            WHERE chat_guid = ? AND date_created <= ?
+            ORDER BY date_created DESC
+            LIMIT ?",
+        )
+        .bind(chat_guid)
+        .bind(before.unwrap_or(i64::MAX))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(messages.into_iter().map(|Json(message)| message).collect())
+    }
+
+    pub async fn message(&self, guid: &str) -> Result<Option<models::Message>> {
+        let message: Option<Json<models::Message>> =
+            sqlx::query_scalar("SELECT payload FROM messages WHERE guid = ?")
+                .bind(guid)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(message.map(|Json(message)| message))
+    }
+
+    /// A thread: the message with GUID `originator_guid` and every reply to it, oldest first
+    pub async fn thread(&self, originator_guid: &str) -> Result<Vec<models::Message>> {
+        let messages: Vec<Json<models::Message>> = sqlx::query_scalar(
+            "SELECT payload FROM messages
+            WHERE guid = ? OR thread_originator_guid = ?
+            ORDER BY date_created",
+        )
+        .bind(originator_guid)
+        .bind(originator_guid)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(messages.into_iter().map(|Json(message)| message).collect())
+    }
+
+    /// Messages matching a search, newest first
+    pub async fn search_messages(&self, query: &str, limit: u32) -> Result<Vec<SearchHit>> {
+        let Some(query) = match_query(query) else {
+            return Ok(Vec::new());
+        };
+        let hits: Vec<(String, Json<models::Message>, String)> = sqlx::query_as(
+            "SELECT m.chat_guid, m.payload, snippet(message_search, -1, ?, ?, '…', 16)
+            FROM message_search s JOIN messages m ON m.id = s.rowid
+            WHERE message_search MATCH ?
+            ORDER BY m.date_created DESC
+            LIMIT ?",
+        )
+        .bind(MATCH_START.to_string())
+        .bind(MATCH_END.to_string())
+        .bind(query)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(hits
+            .into_iter()
+            .map(|(chat_guid, Json(message), snippet)| SearchHit {
+                chat_guid,
+                message,
+                snippet,
+            })
+            .collect())
+    }
+
+    /// Chats whose name, identifier or participant addresses match a search, most
+    /// recently active first
+    pub async fn search_chats(&self, query: &str, limit: u32) -> Result<Vec<models::Chat>> {
+        let Some(query) = match_query(query) else {
+            return Ok(Vec::new());
+        };
+        let chats: Vec<Json<models::Chat>> = sqlx::query_scalar(
+            "SELECT c.payload
+            FROM chat_search s JOIN chats c ON c.id = s.rowid
+            WHERE chat_search MATCH ?
+            ORDER BY c.last_message_date DESC NULLS LAST
+            LIMIT ?",
+        )
+        .bind(query)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(chats.into_iter().map(|Json(chat)| chat).collect())
+    }
+
+    /// Every stored handle, the addresses chats have been had with
+    pub async fn handles(&self) -> Result<Vec<models::Handle>> {
+        let rows: Vec<HandleRow> = sqlx::query_as(
+            "SELECT id, address, service, country, uncanonicalized_id FROM handles ORDER BY id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(
+                |(id, address, service, country, uncanonicalized_id)| models::Handle {
+                    original_row_id: id,
+                    address,
+                    service: models::Service::from_name(&service),
+                    country,
+                    uncanonicalized_id,
+                    formatted_address: None,
+                },
+            )
+            .collect())
+    }
+
+    /// The most recently active one-to-one chat with `address`, which is normalized like
+    /// [`contacts::normalize`] does. Handles are stored as the server spells them, so
+    /// each is normalized with its own country before comparing.
+    pub async fn direct_chat(&self, address: &str) -> Result<Option<models::Chat>> {
+        let handles: Vec<(String, Option<String>, String)> = sqlx::query_as(
+            "SELECT h.address, h.country, c.guid FROM chats c
+            JOIN chat_handles ch ON ch.chat_guid = c.guid
+            JOIN handles h ON h.id = ch.handle_id
+            WHERE (SELECT COUNT(*) FROM chat_handles other WHERE other.chat_guid = c.guid) = 1
+            ORDER BY c.last_message_date DESC NULLS LAST",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let guid = handles.into_iter().find_map(|(handle, country, guid)| {
+            (contacts::normalize(&handle, country.as_deref()) == address).then_some(guid)
+        });
+        match guid {
+            Some(guid) => self.chat(&guid).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Mark the received messages of a chat as read at `date`
+    pub async fn mark_chat_read(&self, chat_guid: &str, date: i64) -> Result<()> {
+        sqlx::query(
+            "UPDATE messages SET date_read = ?, payload = json_set(payload, '$.dateRead', ?)
+            WHERE chat_guid = ? AND is_from_me = 0 AND date_read IS NULL",
+        )
+        .bind(date)
+        .bind(date)
+        .bind(chat_guid)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Remove a message, e.g. a failed send the user discarded
+    pub async fn delete_message(&self, guid: &str) -> Result<()> {
+        sqlx::query("DELETE FROM messages WHERE guid = ?")
+            .bind(guid)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Remember where a downloaded attachment was saved
+    pub async fn set_attachment_path(&self, guid: &str, path: &Path) -> Result<()> {
+        sqlx::query("UPDATE attachments SET local_path = ? WHERE guid = ?")
+            .bind(path.to_string_lossy().into_owned())
+            .bind(guid)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn attachment_path(&self, guid: &str) -> Result<Option<PathBuf>> {
+        let path: Option<Option<String>> =
+            sqlx::query_scalar("SELECT local_path FROM attachments WHERE guid = ?")
+                .bind(guid)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(path.flatten().map(PathBuf::from))
+    }
+
+    pub async fn has_chat(&self, guid: &str) -> Result<bool> {
+        let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM chats WHERE guid = ?)")
+            .bind(guid)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(exists)
+    }
+
+    /// Record the start of a new history backfill, discarding any unfinished one
+    pub async fn begin_backfill(&self, history_months: Option<u32>, started_at: i64) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM chat_backfill")
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO sync_state (id, history_months, started_at, backfill_complete)
+            VALUES (0, ?, ?, 0)",
+        )
+        .bind(history_months)
+        .bind(started_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn backfill_state(&self) -> Result<Option<BackfillState>> {
+        let state = sqlx::query_as(
+            "SELECT history_months, started_at, backfill_complete FROM sync_state WHERE id = 0",
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(state)
+    }
+
+    pub async fn complete_backfill(&self) -> Result<()> {
+        sqlx::query("UPDATE sync_state SET backfill_complete = 1 WHERE id = 0")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Messages fetched so far for a chat and whether its backfill is done
+    pub async fn chat_backfill(&self, chat_guid: &str) -> Result<(u32, bool)> {
+        let progress =
+            sqlx::query_as("SELECT fetched, complete FROM chat_backfill WHERE chat_guid = ?")
+                .bind(chat_guid)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(progress.unwrap_or((0, false)))
+    }
+
+    pub async fn set_chat_backfill(
+        &self,
+        chat_guid: &str,
+        fetched: u32,
+        complete: bool,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO chat_backfill (chat_guid, fetched, complete) VALUES (?, ?, ?)",
+        )
+        .bind(chat_guid)
+        .bind(fetched)
+        .bind(complete)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Replace every contact from `source` with `contacts`
+    pub async fn replace_contacts(&self, source: &str, contacts: &[models::Contact]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM contacts WHERE source = ?")
+            .bind(source)
+            .execute(&mut *tx)
+            .await?;
+        insert_contacts(&mut tx, source, None, contacts).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Replace the contacts previously imported from `file` with `contacts`
+    pub async fn replace_file_contacts(
+        &self,
+        source: &str,
+        file: &str,
+        contacts: &[models::Contact],
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM contacts WHERE source = ? AND file = ?")
+            .bind(source)
+            .bind(file)
+            .execute(&mut *tx)
+            .await?;
+        insert_contacts(&mut tx, source, Some(file), contacts).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn remove_contacts(&self, source: &str) -> Result<()> {
+        sqlx::query("DELETE FROM contacts WHERE source = ?")
+            .bind(source)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn contacts(&self) -> Result<Vec<models::Contact>> {
+        let contacts: Vec<Json<models::Contact>> =
+            sqlx::query_scalar("SELECT payload FROM contacts ORDER BY id")
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(contacts.into_iter().map(|Json(contact)| contact).collect())
+    }
+
+    /// When changes to stored messages were last caught up on, falling back to the start
+    /// of the backfill since it fetched everything up to then
+    pub async fn last_synced_at(&self) -> Result<Option<i64>> {
+        let date = sqlx::query_scalar(
+            "SELECT COALESCE(last_synced_at, started_at) FROM sync_state WHERE id = 0",
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(date)
+    }
+
+    pub async fn set_last_synced_at(&self, date: i64) -> Result<()> {
+        sqlx::query("UPDATE sync_state SET last_synced_at = ? WHERE id = 0")
+            .bind(date)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Server ROWID of the newest stored message, the cursor for incremental sync
+    pub async fn last_message_row_id(&self) -> Result<Option<i64>> {
+        let row_id = sqlx::query_scalar("SELECT MAX(original_row_id) FROM messages")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(row_id)
+    }
+}
+
+/// A message matching a search
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub chat_guid: String,
+    pub message: models::Message,
+    /// The matching part of the message, matched terms are wrapped in [`MATCH_START`]
+    /// and [`MATCH_END`]
+    pub snippet: String,
+}
+
+/// The history backfill chosen during onboarding
+#[derive(Debug, Clone, Copy, sqlx::FromRow)]
+pub struct BackfillState {
+    /// `None` syncs all history, `Some(0)` only the chat list
+    pub history_months: Option<u32>,
+    pub started_at: i64,
+    #[sqlx(rename = "backfill_complete")]
+    pub complete: bool,
+}
+
+type HandleRow = (i64, String, String, Option<String>, Option<String>);
+
+type ChatWithLastMessage = (Json<models::Chat>, Option<Json<models::Message>>);
+
+fn with_last_message((Json(mut chat), last_message): ChatWithLastMessage) -> models::Chat {
+    chat.last_message = last_message.map(|Json(message)| message);
+    chat
+}
+
+/// An FTS5 query matching every word of `input` as a prefix, `None` if it has no words
+fn match_query(input: &str) -> Option<String> {
+    let terms: Vec<String> = input
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+async fn insert_contacts(
+    conn: &mut SqliteConnection,
+    source: &str,
+    file: Option<&str>,
+    contacts: &[models::Contact],
+) -> Result<()> {
+    for contact in contacts {
+        sqlx::query("INSERT INTO contacts (source, file, payload) VALUES (?, ?, ?)")
+            .bind(source)
+            .bind(file)
+            .bind(Json(contact))
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+async fn upsert_chat(conn: &mut SqliteConnection, chat: &models::Chat) -> Result<()> {
+    // Messages are stored in their own table, keep the chat payload small
+    let mut payload = chat.clone();
+    payload.last_message = None;
+    payload.messages.clear();
+
+    // Participant lists are only present when requested, keep the stored ones otherwise
+    if payload.participants.is_empty() {
+        let existing: Option<Json<models::Chat>> =
+            sqlx::query_scalar("SELECT payload FROM chats WHERE guid = ?")
+                .bind(chat.guid.as_str())
+                .fetch_optional(&mut *conn)
+                .await?;
+        if let Some(Json(existing)) = existing {
+            payload.participants = existing.participants;
+        }
+    }
+
+    sqlx::query(
+        "INSERT INTO chats (guid, original_row_id, style, chat_identifier, display_name,
+            is_archived, group_id, payload)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT (guid) DO UPDATE SET
+            original_row_id = excluded.original_row_id,
+            style = excluded.style,
+            chat_identifier = excluded.chat_identifier,
+            display_name = excluded.display_name,
+            is_archived = excluded.is_archived,
+            group_id = excluded.group_id,
+            payload = excluded.payload",
+    )
+    .bind(chat.guid.as_str())
+    .bind(chat.original_row_id)
+    .bind(i64::from(chat.style))
+    .bind(&chat.chat_identifier)
+    .bind(&chat.display_name)
+    .bind(chat.is_archived)
+    .bind(&chat.group_id)
+    .bind(Json(&payload))
+    .execute(&mut *conn)
+    .await?;
+
+    if !chat.participants.is_empty() {
+        sqlx::query("DELETE FROM chat_handles WHERE chat_guid = ?")
+            .bind(chat.guid.as_str())
+            .execute(&mut *conn)
+            .await?;
+
+        for participant in &chat.participants {
+            sqlx::query(
+                "INSERT INTO handles (id, address, service, country, uncanonicalized_id)
+                VALUES (?, ?, ?, ?, ?)
+                ON CONFLICT (id) DO UPDATE SET
+                    address = excluded.address,
+                    service = excluded.service,
+                    country = excluded.country,
+                    uncanonicalized_id = excluded.uncanonicalized_id",
+            )
+            .bind(participant.original_row_id)
+            .bind(&participant.address)
+            .bind(participant.service.name())
+            .bind(&participant.country)
+            .bind(&participant.uncanonicalized_id)
+            .execute(&mut *conn)
+            .await?;
+
+            sqlx::query("INSERT OR IGNORE INTO chat_handles (chat_guid, handle_id) VALUES (?, ?)")
+                .bind(chat.guid.as_str())
+                .bind(participant.original_row_id)
+                .execute(&mut *conn)
+                .await?;
+        }
+    }
+
+    sqlx::query(
+        "INSERT OR REPLACE INTO chat_search (rowid, name)
+        SELECT
+            c.id,
+            c.display_name || ' ' || c.chat_identifier || ' ' || COALESCE(
+                (SELECT group_concat(h.address, ' ')
+                FROM chat_handles ch JOIN handles h ON h.id = ch.handle_id
+                WHERE ch.chat_guid = c.guid),
+                ''
+            )
+        FROM chats c
+        WHERE c.guid = ?",
+    )
+    .bind(chat.guid.as_str())
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn upsert_message(
+    conn: &mut SqliteConnection,
+    chat_guid: &str,
+    message: &models::Message,
+) -> Result<()> {
+    // Nested chats are already stored separately
+    let mut payload = message.clone();
+    payload.chats.clear();
+
+    sqlx::query(
+        "INSERT INTO messages (guid, original_row_id, chat_guid, handle_id, text, subject, error,
+            is_from_me, date_created, date_delivered, date_read, date_edited, date_retracted,
+            associated_message_guid, associated_message_type, thread_originator_guid, payload)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT (guid) DO UPDATE SET
+            original_row_id = excluded.original_row_id,
+            handle_id = excluded.handle_id,
+            text = excluded.text,
+            subject = excluded.subject,
+            error = excluded.error,
+            date_delivered = excluded.date_delivered,
+            date_read = excluded.date_read,
+            date_edited = excluded.date_edited,
+            date_retracted = excluded.date_retracted,
+            associated_message_guid = excluded.associated_message_guid,
+            associated_message_type = excluded.associated_message_type,
+            thread_originator_guid = excluded.thread_originator_guid,
+            payload = excluded.payload",
+    )
+    .bind(&message.guid)
+    .bind(message.original_row_id)
+    .bind(chat_guid)
+    .bind(message.handle_id)
+    .bind(&message.text)
+    .bind(&message.subject)
+    .bind(message.error)
+    .bind(message.is_from_me)
+    .bind(message.date_created)
+    .bind(message.date_delivered)
+    .bind(message.date_read)
+    .bind(message.date_edited)
+    .bind(message.date_retracted)
+    .bind(&message.associated_message_guid)
+    .bind(&message.associated_message_type)
+    .bind(&message.thread_originator_guid)
+    .bind(Json(&payload))
+    .execute(&mut *conn)
+    .await?;
+
+    for attachment in &message.attachments {
+        sqlx::query(
+            "INSERT INTO attachments (guid, message_guid, original_row_id, uti, mime_type,
+                transfer_name, total_bytes, width, height, hide_attachment, is_sticker)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (guid) DO UPDATE SET
+                uti = excluded.uti,
+                mime_type = excluded.mime_type,
+                transfer_name = excluded.transfer_name,
+                total_bytes = excluded.total_bytes,
+                width = excluded.width,
+                height = excluded.height,
+                hide_attachment = excluded.hide_attachment,
+                is_sticker = excluded.is_sticker",
+        )
+        .bind(&attachment.guid)
+        .bind(&message.guid)
+        .bind(attachment.original_row_id)
+        .bind(&attachment.uti)
+        .bind(&attachment.mime_type)
+        .bind(&attachment.transfer_name)
+        .bind(attachment.total_bytes)
+        .bind(attachment.width)
+        .bind(attachment.height)
+        .bind(attachment.hide_attachment)
+        .bind(attachment.is_sticker)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    // Tapbacks have nothing to search
+    if message.associated_message_type.is_none() {
+        let attachments = message
+            .attachments
+            .iter()
+            .filter_map(|attachment| attachment.transfer_name.as_deref())
+            .collect::<Vec<_>>()
+            .join(" ");
+        sqlx::query(
+            "INSERT OR REPLACE INTO message_search (rowid, body, attachments)
+            SELECT id, ?, ? FROM messages WHERE guid = ?",
+        )
+        .bind(message.body_text())
+        .bind(attachments)
+        .bind(&message.guid)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    sqlx::query(
+        "UPDATE chats SET last_message_date = MAX(COALESCE(last_message_date, 0), ?)
+        WHERE guid = ?",
+    )
+    .bind(message.date_created)
+    .bind(chat_guid)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    /// A store in a fresh database under the temporary directory
+    async fn open_temp() -> Store {
+        let path = std::env::temp_dir()
+            .join(Uuid::new_v4().to_string())
+            .join("messages.db");
+        Store::open(&path).await.unwrap()
+    }
+
+    fn chat(guid: &str, participants: &[(i64, &str, Option<&str>)]) -> models::Chat {
+        let guid: models::ChatGuid = guid.parse().unwrap();
+        models::Chat {
+            original_row_id: 1,
+            style: guid.style(),
+            chat_identifier: guid.identifier().to_string(),
+            guid,
+            is_archived: false,
+            display_name: String::new(),
+            participants: participants
+                .iter()
+                .map(|&(id, address, country)| models::Handle {
+                    original_row_id: id,
+                    address: address.to_string(),
+                    service: models::Service::IMessage,
+                    country: country.map(str::to_string),
+                    ..Default::default()
+                })
+                .collect(),
+            is_filtered: None,
+            group_id: None,
+            properties: Vec::new(),
+            last_addressed_handle: String::new(),
+            last_message: None,
+            messages: Vec::new(),
+        }
+    }
+
+    fn message(guid: &str, text: &str, date_created: i64) -> models::Message {
+        models::Message {
+            guid: guid.to_string(),
+            text: Some(text.to_string()),
+            date_created,
+            ..Default::default()
+        }
+    }
+
+    const CHAT: &str = "iMessage;-;+12025550123";
+
+    #[tokio::test]
+    async fn upsert_is_idempotent() {
+        let store = open_temp().await;
+        let chat = chat(CHAT, &[(1, "+12025550123", Some("us"))]);
+        let messages = [message("a", "hello", 1000), message("b", "there", 2000)];
+
+        for _ in 0..2 {
+            store.upsert_chat(&chat).await.unwrap();
+            store.upsert_messages(CHAT, &messages).await.unwrap();
+        }
+
+        assert_eq!(store.chats(10, 0).await.unwrap().len(), 1);
+        assert_eq!(store.handles().await.unwrap().len(), 1);
+        assert_eq!(store.messages(CHAT, None, 10).await.unwrap().len(), 2);
+        assert_eq!(store.search_messages("hello", 10).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn messages_page_across_equal_timestamps() {
+        let store = open_temp().await;
+        store.upsert_chat(&chat(CHAT, &[])).await.unwrap();
+        let messages = [
+            message("a", "1", 1000),
+            message("b", "2", 1000),
+            message("c", "3", 1000),
+            message("d", "4", 2000),
+        ];
+        store.upsert_messages(CHAT, &messages).await.unwrap();
+
+        let first = store.messages(CHAT, None, 2).await.unwrap();
+        assert_eq!(first[0].guid, "d");
+        assert_eq!(first[1].date_created, 1000);
+
+        // Continuing from the oldest shown message returns every message sharing its date
+        let second = store.messages(CHAT, Some(1000), 10).await.unwrap();
+        let mut guids: Vec<_> = second.iter().map(|message| message.guid.as_str()).collect();
+        guids.sort();
+        assert_eq!(guids, ["a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn search_messages_after_update() {
+        let store = open_temp().await;
+        store.upsert_chat(&chat(CHAT, &[])).await.unwrap();
+        store
+            .upsert_messages(CHAT, &[message("a", "lunch tomorrow?", 1000)])
+            .await
+            .unwrap();
+        assert_eq!(store.search_messages("lunch", 10).await.unwrap().len(), 1);
+
+        // e.g. an edit
+        store
+            .upsert_messages(CHAT, &[message("a", "dinner tomorrow?", 1000)])
+            .await
+            .unwrap();
+        assert!(store.search_messages("lunch", 10).await.unwrap().is_empty());
+        let hits = store.search_messages("dinner", 10).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].chat_guid, CHAT);
+        assert_eq!(hits[0].message.guid, "a");
+    }
+
+    #[tokio::test]
+    async fn direct_chat_matches_normalized_address() {
+        let store = open_temp().await;
+        // The server spells the handle as it was typed, without a country code
+        store
+            .upsert_chat(&chat(
+                "SMS;-;2025550123",
+                &[(1, "(202) 555-0123", Some("us"))],
+            ))
+            .await
+            .unwrap();
+        store
+            .upsert_chat(&chat(
+                "iMessage;+;chat1",
+                &[
+                    (1, "(202) 555-0123", Some("us")),
+                    (2, "ann@example.com", None),
+                ],
+            ))
+            .await
+            .unwrap();
+
+        let direct = store.direct_chat("+12025550123").await.unwrap().unwrap();
+        assert_eq!(direct.guid, "SMS;-;2025550123");
+        assert!(
+            store
+                .direct_chat("ann@example.com")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
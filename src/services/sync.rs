@@ -0,0 +1,306 @@
+use anyhow::Result;
+use bluebubbles_api::{
+    client::Client,
+    models::{self, QueryFilter, SortOrder},
+};
+use chrono::{DateTime, Months, Utc};
+use futures_util::TryStreamExt;
+
+use crate::services::store::Store;
+
+/// Chats requested per page while listing chats
+const CHAT_PAGE_SIZE: u32 = 100;
+/// Messages requested per page while backfilling or catching up
+const MESSAGE_PAGE_SIZE: u32 = 200;
+
+/// How far back of the last sync changes are fetched again, in case the server's clock
+/// is behind ours
+const CLOCK_SKEW_MS: i64 = 5 * 60 * 1000;
+/// Unix time in milliseconds of 2001-01-01, the epoch of the dates in the server's database
+const APPLE_EPOCH_MS: i64 = 978_307_200_000;
+
+/// How much message history the initial sync downloads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryRange {
+    /// Only the chat list and the last message of every chat
+    None,
+    Months(u32),
+    All,
+}
+
+impl HistoryRange {
+    fn from_months(history_months: Option<u32>) -> Self {
+        match history_months {
+            None => Self::All,
+            Some(0) => Self::None,
+            Some(months) => Self::Months(months),
+        }
+    }
+
+    fn months(&self) -> Option<u32> {
+        match self {
+            Self::None => Some(0),
+            Self::Months(months) => Some(*months),
+            Self::All => None,
+        }
+    }
+}
+
+/// Progress of a running sync
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SyncProgress {
+    pub chats_done: usize,
+    pub total_chats: usize,
+    pub messages_fetched: usize,
+}
+
+impl SyncProgress {
+    pub fn fraction(&self) -> f64 {
+        if self.total_chats == 0 {
+            0.0
+        } else {
+            self.chats_done as f64 / self.total_chats as f64
+        }
+    }
+}
+
+/// Mirrors server data into the local [`Store`]
+pub struct SyncEngine {
+    client: Client,
+    store: &'static Store,
+}
+
+impl SyncEngine {
+    pub fn new(client: Client, store: &'static Store) -> Self {
+        Self { client, store }
+    }
+
+    /// Start the initial history backfill, replacing any unfinished one
+    pub async fn start_backfill(
+        &self,
+        range: HistoryRange,
+        on_progress: impl FnMut(SyncProgress),
+    ) -> Result<()> {
+        self.store
+            .begin_backfill(range.months(), Utc::now().timestamp_millis())
+            .await?;
+        self.backfill(on_progress).await
+    }
+
+    /// Continue a backfill that was interrupted, e.g. by quitting the app.
+    ///
+    /// Returns `false` if there was nothing to resume.
+    pub async fn resume_backfill(&self, on_progress: impl FnMut(SyncProgress)) -> Result<bool> {
+        match self.store.backfill_state().await? {
+            Some(state) if !state.complete => {
+                self.backfill(on_progress).await?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn backfill(&self, mut on_progress: impl FnMut(SyncProgress)) -> Result<()> {
+        let Some(state) = self.store.backfill_state().await? else {
+            anyhow::bail!("No backfill has been started");
+        };
+        let range = HistoryRange::from_months(state.history_months);
+        // Bound the history by when the backfill started, so a resumed sync covers the same range
+        let after = match range {
+            HistoryRange::Months(months) => DateTime::from_timestamp_millis(state.started_at)
+                .and_then(|started_at| started_at.checked_sub_months(Months::new(months))),
+            _ => None,
+        };
+
+        let chats = self.sync_chats().await?;
+        let mut progress = SyncProgress {
+            total_chats: chats.len(),
+            ..Default::default()
+        };
+        on_progress(progress);
+
+        for chat in &chats {
+            let (mut fetched, complete) = self.store.chat_backfill(&chat.guid).await?;
+
+            if !complete && range != HistoryRange::None {
+                loop {
+                    let page = self
+                        .client
+                        .chats()
+                        .get_messages()
+                        .with_attachment()
+                        .with_handle()
+                        .with_attributed_body()
+                        .guid(&chat.guid)
+                        .limit(MESSAGE_PAGE_SIZE)
+                        .offset(fetched)
+                        .sort(SortOrder::Descending)
+                        .maybe_after(after)
+                        .send()
+                        .await?;
+
+                    self.store.upsert_messages(&chat.guid, &page).await?;
+                    fetched += page.len() as u32;
+                    self.store
+                        .set_chat_backfill(&chat.guid, fetched, false)
+                        .await?;
+
+                    progress.messages_fetched += page.len();
+                    on_progress(progress);
+
+                    if page.len() < MESSAGE_PAGE_SIZE as usize {
+                        break;
+                    }
+                }
+            }
+
+            self.store
+                .set_chat_backfill(&chat.guid, fetched, true)
+                .await?;
+            progress.chats_done += 1;
+            on_progress(progress);
+        }
+
+        self.store.complete_backfill().await?;
+        Ok(())
+    }
+
+    /// Fetch every chat with its participants and last message
+    async fn sync_chats(&self) -> Result<Vec<models::Chat>> {
+        let mut stream = self
+            .client
+            .chats()
+            .query_stream()
+            .with_participants()
+            .with_last_message()
+            .page_size(CHAT_PAGE_SIZE)
+            .send();
+
+        let mut chats = Vec::new();
+        while let Some(chat) = stream.try_next().await? {
+            self.store.upsert_chat(&chat).await?;
+            chats.push(chat);
+        }
+        Ok(chats)
+    }
+
+    /// A page of a conversation from `before` back, newest first. Messages at `before`
+    /// are included, see [`Store::messages`].
+    ///
+    /// Reads from the store and continues from the server where the local history ends,
+    /// storing what it fetches so the next read is local.
+    pub async fn history_page(
+        &self,
+        chat_guid: &models::ChatGuid,
+        before: Option<i64>,
+        limit: u32,
+    ) -> Result<Vec<models::Message>> {
+        let mut page = self.store.messages(chat_guid, before, limit).await?;
+        if page.len() < limit as usize {
+            let oldest = page.last().map(|message| message.date_created).or(before);
+            let remote = self
+                .client
+                .chats()
+                .get_messages()
+                .with_attachment()
+                .with_handle()
+                .with_attributed_body()
+                .guid(chat_guid)
+                .maybe_before(oldest.and_then(DateTime::from_timestamp_millis))
+                .limit(limit - page.len() as u32)
+                .sort(SortOrder::Descending)
+                .send()
+                .await?;
+
+            self.store.upsert_messages(chat_guid, &remote).await?;
+            page.extend(remote);
+        }
+        Ok(page)
+    }
+
+    /// Fetch what changed while disconnected, e.g. after reconnecting: messages newer than
+    /// the newest stored one, and edits, unsends and receipts of already stored messages.
+    ///
+    /// Returns the number of messages fetched.
+    pub async fn catch_up(&self) -> Result<usize> {
+        let Some(last_row_id) = self.store.last_message_row_id().await? else {
+            // Nothing synced yet, there is no cursor to catch up from
+            return Ok(0);
+        };
+        let synced_at = Utc::now().timestamp_millis();
+
+        let mut fetched = self
+            .fetch_matching(QueryFilter::new("message.ROWID > :rowId").arg("rowId", last_row_id))
+            .await?;
+
+        if let Some(last_synced_at) = self.store.last_synced_at().await? {
+            let since = apple_time(last_synced_at - CLOCK_SKEW_MS);
+            fetched += self
+                .fetch_matching(
+                    QueryFilter::new(
+                        "(message.dateEdited > :since OR message.dateRetracted > :since
+                        OR message.dateRead > :since OR message.dateDelivered > :since)",
+                    )
+                    .arg("since", since),
+                )
+                .await?;
+        }
+        self.store.set_last_synced_at(synced_at).await?;
+
+        tracing::info!("Caught up on {} messages", fetched);
+        Ok(fetched)
+    }
+
+    /// Fetch and store every message matching `filter`, oldest first
+    async fn fetch_matching(&self, filter: QueryFilter) -> Result<usize> {
+        let mut fetched = 0;
+        loop {
+            let page = self
+                .client
+                .messages()
+                .query()
+                .with_chat()
+                .with_chat_participants()
+                .with_attachment()
+                .with_handle()
+                .with_attributed_body()
+                .filters(vec![filter.clone()])
+                .sort(SortOrder::Ascending)
+                .limit(MESSAGE_PAGE_SIZE)
+                .offset(fetched as u32)
+                .send()
+                .await?;
+
+            for message in &page {
+                self.store_message(message).await?;
+            }
+
+            fetched += page.len();
+            if page.len() < MESSAGE_PAGE_SIZE as usize {
+                return Ok(fetched);
+            }
+        }
+    }
+
+    /// Store a message along with its chat, e.g. one received as a real-time event.
+    ///
+    /// The message must have been fetched with its chats.
+    pub async fn store_message(&self, message: &models::Message) -> Result<()> {
+        let Some(chat) = message.chats.first() else {
+            tracing::warn!("Message {} has no chat, skipping", message.guid);
+            return Ok(());
+        };
+        if !self.store.has_chat(&chat.guid).await? {
+            self.store.upsert_chat(chat).await?;
+        }
+        self.store
+            .upsert_messages(&chat.guid, std::slice::from_ref(message))
+            .await
+    }
+}
+
+/// Convert a unix time in milliseconds to the nanoseconds since 2001 the server's database
+/// stores, which raw query filters compare against
+fn apple_time(unix_ms: i64) -> i64 {
+    (unix_ms - APPLE_EPOCH_MS) * 1_000_000
+}
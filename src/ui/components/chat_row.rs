@@ -0,0 +1,143 @@
+use bluebubbles_api::models;
+use libadwaita::prelude::*;
+use relm4::{
+    RelmWidgetExt, adw,
+    gtk::{self, gdk, glib, pango},
+    typed_view::list::RelmListItem,
+};
+
+use crate::{services::contacts, ui::format};
+
+/// A chat in the sidebar list
+#[derive(Debug, Clone)]
+pub struct ChatRow {
+    pub guid: models::ChatGuid,
+    pub title: String,
+    pub preview: String,
+    /// Date of the last message in milliseconds
+    pub last_message_date: Option<i64>,
+    pub unread: bool,
+    /// Photo of the other person in one-to-one chats, as encoded image data
+    pub avatar: Option<glib::Bytes>,
+}
+
+impl ChatRow {
+    pub fn new(chat: &models::Chat) -> Self {
+        let last_message = chat.last_message.as_ref();
+        Self {
+            guid: chat.guid.clone(),
+            title: format::chat_title(chat),
+            preview: last_message
+                .map(format::message_preview)
+                .unwrap_or_default(),
+            last_message_date: last_message.map(|message| message.date_created),
+            unread: last_message
+                .is_some_and(|message| !message.is_from_me && message.date_read.is_none()),
+            avatar: match chat.participants.as_slice() {
+                [participant] => {
+                    contacts::avatar(&participant.address, participant.country.as_deref())
+                }
+                _ => None,
+            },
+        }
+    }
+}
+
+pub struct ChatRowWidgets {
+    avatar: adw::Avatar,
+    title: gtk::Label,
+    timestamp: gtk::Label,
+    preview: gtk::Label,
+    unread: gtk::Box,
+}
+
+impl RelmListItem for ChatRow {
+    type Root = gtk::Box;
+    type Widgets = ChatRowWidgets;
+
+    fn setup(_item: &gtk::ListItem) -> (gtk::Box, ChatRowWidgets) {
+        relm4::view! {
+            root = gtk::Box {
+                set_orientation: gtk::Orientation::Horizontal,
+                set_spacing: 12,
+                set_margin_vertical: 6,
+
+                #[name = "avatar"]
+                adw::Avatar {
+                    set_size: 40,
+                    set_show_initials: true,
+                },
+
+                gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_valign: gtk::Align::Center,
+                    set_hexpand: true,
+                    set_spacing: 2,
+
+                    gtk::Box {
+                        set_spacing: 6,
+
+                        #[name = "title"]
+                        gtk::Label {
+                            set_hexpand: true,
+                            set_xalign: 0.0,
+                            set_ellipsize: pango::EllipsizeMode::End,
+                            add_css_class: "heading",
+                        },
+
+                        #[name = "timestamp"]
+                        gtk::Label {
+                            add_css_class: "caption",
+                            add_css_class: "dimmed",
+                        },
+                    },
+
+                    gtk::Box {
+                        set_spacing: 6,
+
+                        #[name = "preview"]
+                        gtk::Label {
+                            set_hexpand: true,
+                            set_xalign: 0.0,
+                            set_ellipsize: pango::EllipsizeMode::End,
+                            add_css_class: "dimmed",
+                        },
+
+                        #[name = "unread"]
+                        gtk::Box {
+                            set_valign: gtk::Align::Center,
+                            add_css_class: "unread-indicator",
+                        },
+                    },
+                },
+            }
+        }
+
+        let widgets = ChatRowWidgets {
+            avatar,
+            title,
+            timestamp,
+            preview,
+            unread,
+        };
+        (root, widgets)
+    }
+
+    fn bind(&mut self, widgets: &mut Self::Widgets, _root: &mut Self::Root) {
+        widgets.avatar.set_text(Some(&self.title));
+        let image = self
+            .avatar
+            .as_ref()
+            .and_then(|avatar| gdk::Texture::from_bytes(avatar).ok());
+        widgets.avatar.set_custom_image(image.as_ref());
+        widgets.title.set_label(&self.title);
+        widgets.timestamp.set_label(
+            &self
+                .last_message_date
+                .map(format::short_timestamp)
+                .unwrap_or_default(),
+        );
+        widgets.preview.set_label(&self.preview);
+        widgets.unread.set_visible(self.unread);
+    }
+}
@@ -0,0 +1,199 @@
+use gettextrs::gettext;
+use libadwaita::prelude::*;
+use relm4::{
+    ComponentParts, ComponentSender, SimpleComponent,
+    gtk::{self, gdk, glib, pango},
+};
+
+/// The message a draft replies to
+#[derive(Debug, Clone)]
+pub struct ReplyTarget {
+    pub guid: String,
+    /// Part of the message being replied to
+    pub part: u32,
+    pub sender: String,
+    pub text: String,
+}
+
+#[derive(Debug)]
+pub enum ComposerMsg {
+    Changed,
+    Send,
+    /// Discard the draft, e.g. when switching chats
+    Clear,
+    /// Reply to a message with the next send, or stop replying
+    ReplyTo(Option<ReplyTarget>),
+}
+
+#[derive(Debug)]
+pub enum ComposerOutput {
+    Send {
+        text: String,
+        reply_to: Option<ReplyTarget>,
+    },
+}
+
+/// Multi-line message entry. Enter sends, Shift+Enter inserts a newline.
+pub struct Composer {
+    buffer: gtk::TextBuffer,
+    empty: bool,
+    reply_to: Option<ReplyTarget>,
+}
+
+impl Composer {
+    fn text(&self) -> String {
+        let (start, end) = self.buffer.bounds();
+        self.buffer.text(&start, &end, false).trim().to_string()
+    }
+
+    fn reply_heading(&self) -> String {
+        self.reply_to
+            .as_ref()
+            .map(|reply_to| gettext("Replying to {name}").replace("{name}", &reply_to.sender))
+            .unwrap_or_default()
+    }
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for Composer {
+    type Input = ComposerMsg;
+    type Output = ComposerOutput;
+    type Init = ();
+
+    view! {
+        #[root]
+        gtk::Box {
+            set_orientation: gtk::Orientation::Vertical,
+            set_spacing: 6,
+            add_css_class: "toolbar",
+
+            gtk::Box {
+                set_spacing: 6,
+                add_css_class: "reply-bar",
+                #[watch]
+                set_visible: model.reply_to.is_some(),
+
+                gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_hexpand: true,
+                    set_valign: gtk::Align::Center,
+
+                    gtk::Label {
+                        set_xalign: 0.0,
+                        set_ellipsize: pango::EllipsizeMode::End,
+                        add_css_class: "caption-heading",
+                        #[watch]
+                        set_label: &model.reply_heading(),
+                    },
+
+                    gtk::Label {
+                        set_xalign: 0.0,
+                        set_single_line_mode: true,
+                        set_ellipsize: pango::EllipsizeMode::End,
+                        add_css_class: "caption",
+                        add_css_class: "dimmed",
+                        #[watch]
+                        set_label: model.reply_to.as_ref().map_or("", |reply_to| &reply_to.text),
+                    },
+                },
+
+                gtk::Button {
+                    set_icon_name: "window-close-symbolic",
+                    set_valign: gtk::Align::Center,
+                    set_tooltip_text: Some(&gettext("Cancel Reply")),
+                    set_css_classes: &["flat", "circular"],
+                    connect_clicked => ComposerMsg::ReplyTo(None),
+                },
+            },
+
+            gtk::Box {
+                set_spacing: 6,
+
+                gtk::ScrolledWindow {
+                    set_hexpand: true,
+                    set_hscrollbar_policy: gtk::PolicyType::Never,
+                    set_propagate_natural_height: true,
+                    set_max_content_height: 160,
+                    add_css_class: "card",
+
+                    gtk::TextView {
+                        set_buffer: Some(&model.buffer),
+                        set_wrap_mode: gtk::WrapMode::WordChar,
+                        set_accepts_tab: false,
+                        set_top_margin: 8,
+                        set_bottom_margin: 8,
+                        set_left_margin: 10,
+                        set_right_margin: 10,
+                        add_css_class: "composer",
+                        add_controller: key_controller,
+                    }
+                },
+
+                gtk::Button {
+                    set_icon_name: "go-up-symbolic",
+                    set_valign: gtk::Align::End,
+                    set_tooltip_text: Some(&gettext("Send")),
+                    set_css_classes: &["suggested-action", "circular"],
+                    #[watch]
+                    set_sensitive: !model.empty,
+                    connect_clicked => ComposerMsg::Send,
+                },
+            },
+        }
+    }
+
+    fn init(
+        _: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let buffer = gtk::TextBuffer::new(None);
+        let changed_sender = sender.clone();
+        buffer.connect_changed(move |_| changed_sender.input(ComposerMsg::Changed));
+
+        // Capture Enter before the text view inserts a newline
+        let key_controller = gtk::EventControllerKey::new();
+        key_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+        let key_sender = sender.clone();
+        key_controller.connect_key_pressed(move |_, key, _, modifiers| {
+            if matches!(key, gdk::Key::Return | gdk::Key::KP_Enter)
+                && !modifiers.contains(gdk::ModifierType::SHIFT_MASK)
+            {
+                key_sender.input(ComposerMsg::Send);
+                glib::Propagation::Stop
+            } else {
+                glib::Propagation::Proceed
+            }
+        });
+
+        let model = Self {
+            buffer,
+            empty: true,
+            reply_to: None,
+        };
+        let widgets = view_output!();
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
+        match message {
+            ComposerMsg::Changed => self.empty = self.text().is_empty(),
+            ComposerMsg::Send => {
+                let text = self.text();
+                if !text.is_empty() {
+                    self.buffer.set_text("");
+                    let reply_to = self.reply_to.take();
+                    sender
+                        .output(ComposerOutput::Send { text, reply_to })
+                        .unwrap();
+                }
+            }
+            ComposerMsg::Clear => {
+                self.buffer.set_text("");
+                self.reply_to = None;
+            }
+            ComposerMsg::ReplyTo(reply_to) => self.reply_to = reply_to,
+        }
+    }
+}
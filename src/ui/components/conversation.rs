@@ -0,0 +1,1093 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Context;
+use bluebubbles_api::{models, websocket::Event};
+use chrono::Utc;
+use gettextrs::gettext;
+use libadwaita::prelude::*;
+use relm4::{
+    Component, ComponentController, ComponentParts, ComponentSender, Controller,
+    gtk::{self, gio, glib},
+    typed_view::list::TypedListView,
+};
+use uuid::Uuid;
+
+use crate::{
+    app::{APP_BROKER, AppMsg},
+    services::{
+        self,
+        events::{self, Update},
+        store::Store,
+        sync::SyncEngine,
+    },
+    ui::{
+        components::{
+            composer::{Composer, ComposerMsg, ComposerOutput, ReplyTarget},
+            message_row::{DeliveryStatus, MessageKind, MessageRow, ReactionBadge, ReplyPreview},
+        },
+        dialogs::thread::{ThreadDialog, ThreadDialogMsg, ThreadEntry},
+        format,
+    },
+};
+
+/// Messages loaded per page of history
+const PAGE_SIZE: u32 = 50;
+/// Messages closer together than this from the same sender are drawn as one group
+const GROUPING_INTERVAL_MS: i64 = 5 * 60 * 1000;
+/// A gap this long between messages gets a timestamp separator
+const SEPARATOR_INTERVAL_MS: i64 = 60 * 60 * 1000;
+/// `models::Message::error` for sends that never reached the server, the same code the
+/// BlueBubbles mobile apps use
+const SEND_REQUEST_FAILED: i64 = 1000;
+/// Distance from the bottom within which new messages keep the view scrolled down
+const STICKY_SCROLL_DISTANCE: f64 = 64.0;
+
+#[derive(Debug)]
+pub enum ConversationMsg {
+    /// Show the chat with this GUID
+    Open(models::ChatGuid),
+    /// Scroll to the message with this GUID, loading older history until it is found
+    JumpTo(String),
+    /// Load the next page of older history
+    LoadOlder,
+    Send {
+        text: String,
+        /// GUID and part of the message being replied to
+        reply_to: Option<(String, u32)>,
+    },
+    /// Send a failed message again, by GUID
+    Retry(String),
+    /// Discard a failed message, by GUID
+    Delete(String),
+    /// Reply to a message with the next send, by GUID
+    Reply(String),
+    /// Show the thread started by the message with this GUID
+    ShowThread(String),
+}
+
+#[derive(Debug)]
+pub enum ConversationCommandMsg {
+    Opened(Result<(models::Chat, Vec<models::Message>), String>),
+    OlderLoaded {
+        chat_guid: models::ChatGuid,
+        result: Result<Vec<models::Message>, String>,
+    },
+    /// The newest page was read again after missing real-time updates
+    Reloaded {
+        chat_guid: models::ChatGuid,
+        result: Result<Vec<models::Message>, String>,
+    },
+    Sent {
+        chat_guid: models::ChatGuid,
+        temp_guid: String,
+        result: Result<models::Message, String>,
+    },
+    /// Messages replied to that were not loaded with the history around their replies
+    OriginatorsLoaded {
+        chat_guid: models::ChatGuid,
+        messages: Vec<models::Message>,
+    },
+    ThreadLoaded {
+        chat_guid: models::ChatGuid,
+        result: Result<Vec<models::Message>, String>,
+    },
+    Update(Update),
+}
+
+pub struct Conversation {
+    chat_guid: Option<models::ChatGuid>,
+    chat: Option<models::Chat>,
+    /// Loaded messages, oldest first, without tapbacks
+    messages: Vec<models::Message>,
+    /// Index of each loaded message by GUID
+    positions: HashMap<String, usize>,
+    /// Index of the last sent message, the only one that shows a receipt
+    last_sent: Option<usize>,
+    /// GUIDs of the loaded replies to each message, by the GUID of the message
+    replies: HashMap<String, Vec<String>>,
+    tapbacks: models::Tapbacks,
+    /// Thread originators outside the loaded history, for reply previews
+    originators: HashMap<String, models::Message>,
+    /// Originators looked up already, found or not
+    requested_originators: HashSet<String>,
+    /// Date of the oldest loaded message or tapback, where the next page of history starts
+    oldest: Option<i64>,
+    rows: TypedListView<MessageRow, gtk::NoSelection>,
+    composer: Controller<Composer>,
+    thread_dialog: Controller<ThreadDialog>,
+    /// Temporary GUIDs of messages waiting for the server
+    sending: HashSet<String>,
+    loading: bool,
+    /// The start of the conversation has been loaded
+    complete: bool,
+    /// GUID of a message to scroll to once it is loaded
+    jump_to: Option<String>,
+}
+
+impl Conversation {
+    fn is_group(&self) -> bool {
+        self.chat
+            .as_ref()
+            .is_some_and(|chat| chat.style == models::ChatStyle::Group)
+    }
+
+    fn sender_name(&self, message: &models::Message) -> String {
+        if message.is_from_me {
+            return gettext("You");
+        }
+        self.chat
+            .as_ref()
+            .and_then(|chat| {
+                chat.participants
+                    .iter()
+                    .find(|participant| participant.original_row_id == message.handle_id)
+            })
+            .or(message.handle.as_ref())
+            .map(format::handle_name)
+            .unwrap_or_else(|| gettext("Unknown"))
+    }
+
+    /// Rebuild the lookups rows are built from after `messages` was replaced or a page
+    /// was added, so building each row doesn't scan every message
+    fn reindex(&mut self) {
+        self.positions = self
+            .messages
+            .iter()
+            .enumerate()
+            .map(|(index, message)| (message.guid.clone(), index))
+            .collect();
+        self.last_sent = self.messages.iter().rposition(shows_receipt);
+        self.replies.clear();
+        for message in &self.messages {
+            if let Some(originator) = &message.thread_originator_guid {
+                self.replies
+                    .entry(originator.clone())
+                    .or_default()
+                    .push(message.guid.clone());
+            }
+        }
+    }
+
+    /// Insert into `messages` at `index`, updating the lookups for the messages after it
+    /// only. The rows are left as they are.
+    fn insert_at(&mut self, index: usize, message: models::Message) {
+        for later in &self.messages[index..] {
+            if let Some(position) = self.positions.get_mut(&later.guid) {
+                *position += 1;
+            }
+        }
+        self.positions.insert(message.guid.clone(), index);
+        if let Some(originator) = &message.thread_originator_guid {
+            self.replies
+                .entry(originator.clone())
+                .or_default()
+                .push(message.guid.clone());
+        }
+        self.last_sent = match self.last_sent {
+            Some(last) if last >= index => Some(last + 1),
+            _ if shows_receipt(&message) => Some(index),
+            last => last,
+        };
+        self.messages.insert(index, message);
+    }
+
+    /// Remove from `messages` at `index`, the counterpart of [`Self::insert_at`]
+    fn remove_at(&mut self, index: usize) -> models::Message {
+        let message = self.messages.remove(index);
+        self.positions.remove(&message.guid);
+        for later in &self.messages[index..] {
+            if let Some(position) = self.positions.get_mut(&later.guid) {
+                *position -= 1;
+            }
+        }
+        if let Some(originator) = &message.thread_originator_guid
+            && let Some(replies) = self.replies.get_mut(originator)
+        {
+            replies.retain(|guid| *guid != message.guid);
+            if replies.is_empty() {
+                self.replies.remove(originator);
+            }
+        }
+        self.last_sent = match self.last_sent {
+            Some(last) if last == index => self.messages[..index].iter().rposition(shows_receipt),
+            Some(last) if last > index => Some(last - 1),
+            last => last,
+        };
+        message
+    }
+
+    /// Build the row for the message at `index` from it and its neighbours
+    fn row(&self, index: usize) -> MessageRow {
+        let message = &self.messages[index];
+        let previous = index.checked_sub(1).map(|index| &self.messages[index]);
+
+        let heading = match previous {
+            Some(previous) if !needs_separator(previous, message) => None,
+            _ => Some(format!(
+                "{} {}",
+                format::day_heading(message.date_created),
+                format::time(message.date_created)
+            )),
+        };
+        let grouped_with_previous =
+            heading.is_none() && previous.is_some_and(|previous| same_group(previous, message));
+
+        let kind = if message.item_type == 0 {
+            MessageKind::Bubble
+        } else {
+            MessageKind::Event
+        };
+        let text = match kind {
+            MessageKind::Bubble => bubble_markup(message),
+            MessageKind::Event => format::event_text(message, &self.sender_name(message)),
+        };
+        let show_sender = kind == MessageKind::Bubble
+            && self.is_group()
+            && !message.is_from_me
+            && !grouped_with_previous;
+
+        MessageRow {
+            guid: message.guid.clone(),
+            kind,
+            text,
+            is_from_me: message.is_from_me,
+            sender: show_sender.then(|| self.sender_name(message)),
+            heading,
+            grouped_with_previous,
+            status: if self.sending.contains(&message.guid) {
+                DeliveryStatus::Sending
+            } else if message.is_from_me && message.error != 0 {
+                DeliveryStatus::Failed
+            } else {
+                DeliveryStatus::Sent
+            },
+            edited: message.date_edited.is_some(),
+            unsent: message.date_retracted.is_some(),
+            receipt: if self.last_sent == Some(index) {
+                receipt(message)
+            } else {
+                None
+            },
+            reactions: self.reaction_badges(&message.guid),
+            reply_preview: message
+                .thread_originator_guid
+                .as_deref()
+                .and_then(|guid| self.reply_preview(guid)),
+            thread: self.thread_of(message),
+        }
+    }
+
+    /// A loaded message or thread originator
+    fn find_message(&self, guid: &str) -> Option<&models::Message> {
+        self.index_of(guid)
+            .map(|index| &self.messages[index])
+            .or_else(|| self.originators.get(guid))
+    }
+
+    fn reply_preview(&self, originator_guid: &str) -> Option<ReplyPreview> {
+        let originator = self.find_message(originator_guid)?;
+        Some(ReplyPreview {
+            originator_guid: originator_guid.to_string(),
+            sender: self.sender_name(originator),
+            text: if originator.date_retracted.is_some() {
+                gettext("This message was unsent")
+            } else {
+                bubble_text(originator)
+            },
+        })
+    }
+
+    /// GUID of the thread a message belongs to, its own if it has loaded replies
+    fn thread_of(&self, message: &models::Message) -> Option<String> {
+        message.thread_originator_guid.clone().or_else(|| {
+            self.replies
+                .contains_key(&message.guid)
+                .then(|| message.guid.clone())
+        })
+    }
+
+    fn reply_target(&self, message: &models::Message) -> ReplyTarget {
+        ReplyTarget {
+            guid: message.guid.clone(),
+            // Bubbles show the whole message, replies go to its first part
+            part: 0,
+            sender: self.sender_name(message),
+            text: bubble_text(message),
+        }
+    }
+
+    /// Look up the originators of loaded replies that are neither loaded nor cached
+    fn load_originators(&mut self, sender: &ComponentSender<Self>) {
+        let Some(chat_guid) = self.chat_guid.clone() else {
+            return;
+        };
+        let missing: Vec<String> = self
+            .messages
+            .iter()
+            .filter_map(|message| message.thread_originator_guid.clone())
+            .filter(|guid| self.find_message(guid).is_none())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .filter(|guid| self.requested_originators.insert(guid.clone()))
+            .collect();
+        if missing.is_empty() {
+            return;
+        }
+
+        sender.oneshot_command(async move {
+            let mut messages = Vec::new();
+            for guid in missing {
+                match originator(&guid).await {
+                    Ok(message) => messages.push(message),
+                    Err(e) => tracing::warn!("Failed to load replied to message {}: {}", guid, e),
+                }
+            }
+            ConversationCommandMsg::OriginatorsLoaded {
+                chat_guid,
+                messages,
+            }
+        });
+    }
+
+    /// Rebuild the rows of the replies to `originator_guid` and of the originator itself
+    fn refresh_thread(&mut self, originator_guid: &str) {
+        let indices: Vec<usize> = std::iter::once(originator_guid)
+            .chain(
+                self.replies
+                    .get(originator_guid)
+                    .into_iter()
+                    .flatten()
+                    .map(String::as_str),
+            )
+            .filter_map(|guid| self.index_of(guid))
+            .collect();
+        for index in indices {
+            self.refresh_row(index);
+        }
+    }
+
+    /// Badges for the reactions on a message. Its parts share one bubble, so reactions
+    /// on different parts are shown together.
+    fn reaction_badges(&self, guid: &str) -> Vec<ReactionBadge> {
+        let mut badges: Vec<(models::Reaction, ReactionBadge)> = Vec::new();
+        for folded in self.tapbacks.fold(guid) {
+            let index = match badges
+                .iter()
+                .position(|(reaction, _)| *reaction == folded.reaction)
+            {
+                Some(index) => index,
+                None => {
+                    badges.push((
+                        folded.reaction,
+                        ReactionBadge {
+                            emoji: format::reaction_emoji(folded.reaction),
+                            senders: Vec::new(),
+                            mine: false,
+                        },
+                    ));
+                    badges.len() - 1
+                }
+            };
+            let badge = &mut badges[index].1;
+            for message in folded.from {
+                badge.mine |= message.is_from_me;
+                // Someone reacting the same way to several parts counts once
+                let name = self.sender_name(message);
+                if !badge.senders.contains(&name) {
+                    badge.senders.push(name);
+                }
+            }
+        }
+        badges.into_iter().map(|(_, badge)| badge).collect()
+    }
+
+    /// Rebuild a row after one of its neighbours changed
+    fn refresh_row(&mut self, index: usize) {
+        if index < self.messages.len() {
+            let row = self.row(index);
+            self.rows.remove(index as u32);
+            self.rows.insert(index as u32, row);
+        }
+    }
+
+    fn last_sent_guid(&self) -> Option<String> {
+        self.last_sent
+            .map(|index| self.messages[index].guid.clone())
+    }
+
+    /// Move the receipt if the last sent message changed from `previous`
+    fn refresh_receipts(&mut self, previous: Option<String>) {
+        if self.last_sent_guid() == previous {
+            return;
+        }
+        if let Some(index) = previous.and_then(|guid| self.index_of(&guid)) {
+            self.refresh_row(index);
+        }
+        if let Some(index) = self.last_sent {
+            self.refresh_row(index);
+        }
+    }
+
+    fn index_of(&self, guid: &str) -> Option<usize> {
+        self.positions.get(guid).copied()
+    }
+
+    /// Whether a message from an event belongs to the open chat
+    fn is_open(&self, message: &models::Message) -> bool {
+        let chat_guid = message.chats.first().map(|chat| &chat.guid);
+        (chat_guid.is_some() && chat_guid == self.chat_guid.as_ref())
+            || self.index_of(&message.guid).is_some()
+    }
+
+    /// Insert a message in date order, replacing an older copy of it and the optimistic
+    /// message with the GUID `replaces`
+    fn upsert_message(&mut self, message: models::Message, replaces: Option<&str>) {
+        let last_sent = self.last_sent_guid();
+
+        for guid in [Some(message.guid.as_str()), replaces]
+            .into_iter()
+            .flatten()
+        {
+            if let Some(index) = self.index_of(guid) {
+                self.remove_at(index);
+                self.rows.remove(index as u32);
+                self.refresh_row(index);
+            }
+        }
+
+        let index = self
+            .messages
+            .partition_point(|existing| existing.date_created <= message.date_created);
+        // Its replies quote it and its originator offers to show the thread
+        let thread = message
+            .thread_originator_guid
+            .clone()
+            .unwrap_or_else(|| message.guid.clone());
+        self.insert_at(index, message);
+        let row = self.row(index);
+        self.rows.insert(index as u32, row);
+        self.refresh_row(index + 1);
+        self.refresh_thread(&thread);
+
+        self.refresh_receipts(last_sent);
+    }
+
+    fn remove_message(&mut self, guid: &str) -> Option<models::Message> {
+        let last_sent = self.last_sent_guid();
+
+        let index = self.index_of(guid)?;
+        let message = self.remove_at(index);
+        self.rows.remove(index as u32);
+        self.refresh_row(index);
+
+        self.refresh_receipts(last_sent);
+        Some(message)
+    }
+
+    /// Keep the tapbacks of a page of history apart, returning the other messages
+    fn take_tapbacks(&mut self, page: Vec<models::Message>) -> Vec<models::Message> {
+        if let Some(oldest) = page.iter().map(|message| message.date_created).min() {
+            self.oldest = Some(self.oldest.map_or(oldest, |current| current.min(oldest)));
+        }
+        let (tapbacks, messages): (Vec<_>, Vec<_>) =
+            page.into_iter().partition(models::Message::is_tapback);
+        for tapback in tapbacks {
+            self.add_tapback(tapback);
+        }
+        messages
+    }
+
+    fn add_tapback(&mut self, tapback: models::Message) {
+        // The target may not be loaded yet, its row picks the tapback up once it is
+        if let Some(index) = self
+            .tapbacks
+            .insert(tapback)
+            .and_then(|target| self.index_of(&target))
+        {
+            self.refresh_row(index);
+        }
+    }
+
+    fn is_scrolled_to_end(&self) -> bool {
+        self.rows.view.vadjustment().is_none_or(|adjustment| {
+            adjustment.value() + adjustment.page_size()
+                >= adjustment.upper() - STICKY_SCROLL_DISTANCE
+        })
+    }
+
+    /// Scroll to the message waiting in `jump_to`, or load more history to find it
+    fn continue_jump(&mut self, sender: &ComponentSender<Self>) {
+        let Some(guid) = &self.jump_to else {
+            return;
+        };
+        if let Some(index) = self.index_of(guid) {
+            self.rows
+                .view
+                .scroll_to(index as u32, gtk::ListScrollFlags::FOCUS, None);
+            self.jump_to = None;
+        } else if self.complete {
+            self.jump_to = None;
+        } else if !self.loading {
+            sender.input(ConversationMsg::LoadOlder);
+        }
+    }
+
+    fn scroll_to_end(&self) {
+        if let Some(last) = self.rows.len().checked_sub(1) {
+            self.rows
+                .view
+                .scroll_to(last, gtk::ListScrollFlags::NONE, None);
+        }
+    }
+}
+
+#[relm4::component(pub)]
+impl Component for Conversation {
+    type Input = ConversationMsg;
+    type Output = ();
+    type Init = ();
+    type CommandOutput = ConversationCommandMsg;
+
+    view! {
+        #[root]
+        gtk::Box {
+            set_orientation: gtk::Orientation::Vertical,
+
+            gtk::ScrolledWindow {
+                set_vexpand: true,
+                set_hscrollbar_policy: gtk::PolicyType::Never,
+                connect_edge_reached[sender] => move |_, position| {
+                    if position == gtk::PositionType::Top {
+                        sender.input(ConversationMsg::LoadOlder);
+                    }
+                },
+
+                #[local_ref]
+                message_list -> gtk::ListView {
+                    add_css_class: "conversation",
+                }
+            },
+
+            append: model.composer.widget(),
+        }
+    }
+
+    fn init(
+        _: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let composer = Composer::builder()
+            .launch(())
+            .forward(sender.input_sender(), |output| match output {
+                ComposerOutput::Send { text, reply_to } => ConversationMsg::Send {
+                    text,
+                    reply_to: reply_to.map(|reply_to| (reply_to.guid, reply_to.part)),
+                },
+            });
+        let thread_dialog = ThreadDialog::builder()
+            .launch(root.clone().upcast())
+            .forward(composer.sender(), |reply_to| {
+                ComposerMsg::ReplyTo(Some(reply_to))
+            });
+
+        let model = Self {
+            chat_guid: None,
+            chat: None,
+            messages: Vec::new(),
+            tapbacks: models::Tapbacks::default(),
+            positions: HashMap::new(),
+            last_sent: None,
+            replies: HashMap::new(),
+            originators: HashMap::new(),
+            requested_originators: HashSet::new(),
+            oldest: None,
+            rows: TypedListView::new(),
+            composer,
+            thread_dialog,
+            sending: HashSet::new(),
+            loading: false,
+            complete: false,
+            jump_to: None,
+        };
+
+        let message_list = &model.rows.view;
+        let widgets = view_output!();
+
+        // Buttons and menu items on messages pass the GUID as the action target
+        let actions = gio::SimpleActionGroup::new();
+        let message_actions: [(&str, fn(String) -> ConversationMsg); 4] = [
+            ("retry", ConversationMsg::Retry),
+            ("delete", ConversationMsg::Delete),
+            ("reply", ConversationMsg::Reply),
+            ("thread", ConversationMsg::ShowThread),
+        ];
+        for (name, message) in message_actions {
+            let action = gio::SimpleAction::new(name, Some(glib::VariantTy::STRING));
+            let action_sender = sender.clone();
+            action.connect_activate(move |_, parameter| {
+                if let Some(guid) = parameter.and_then(|parameter| parameter.get::<String>()) {
+                    action_sender.input(message(guid));
+                }
+            });
+            actions.add_action(&action);
+        }
+        root.insert_action_group("conversation", Some(&actions));
+
+        sender.command(|out, shutdown| {
+            shutdown
+                .register(events::for_each(move |update| {
+                    out.emit(ConversationCommandMsg::Update(update))
+                }))
+                .drop_on_shutdown()
+        });
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, _root: &Self::Root) {
+        match message {
+            ConversationMsg::Open(guid) => {
+                if self.chat_guid.as_ref() == Some(&guid) {
+                    return;
+                }
+                self.chat_guid = Some(guid.clone());
+                self.chat = None;
+                self.messages.clear();
+                self.reindex();
+                self.tapbacks.clear();
+                self.originators.clear();
+                self.requested_originators.clear();
+                self.oldest = None;
+                self.rows.clear();
+                self.sending.clear();
+                self.composer.emit(ComposerMsg::Clear);
+                self.loading = true;
+                self.complete = false;
+                self.jump_to = None;
+
+                sender.oneshot_command(async move {
+                    let result = async {
+                        let store = Store::get().await?;
+                        let chat = store.chat(&guid).await?.context("Chat is not stored")?;
+                        let page = history_page(&guid, None).await?;
+                        anyhow::Ok((chat, page))
+                    }
+                    .await;
+                    ConversationCommandMsg::Opened(result.map_err(|e| e.to_string()))
+                });
+            }
+            ConversationMsg::JumpTo(guid) => {
+                self.jump_to = Some(guid);
+                self.continue_jump(&sender);
+            }
+            ConversationMsg::LoadOlder => {
+                let Some(chat_guid) = self.chat_guid.clone() else {
+                    return;
+                };
+                if self.loading || self.complete {
+                    return;
+                }
+                self.loading = true;
+
+                let before = self.oldest;
+                sender.oneshot_command(async move {
+                    let result = history_page(&chat_guid, before)
+                        .await
+                        .map_err(|e| e.to_string());
+                    ConversationCommandMsg::OlderLoaded { chat_guid, result }
+                });
+            }
+            ConversationMsg::Send { text, reply_to } => {
+                let Some(chat_guid) = self.chat_guid.clone() else {
+                    return;
+                };
+                let temp_guid = format!("temp-{}", Uuid::new_v4());
+
+                // Show the message right away, it is replaced once the server echoes it.
+                // A reply joins the thread of the message it replies to.
+                let thread_originator_guid = reply_to.as_ref().map(|(guid, _)| {
+                    self.find_message(guid)
+                        .and_then(|message| message.thread_originator_guid.clone())
+                        .unwrap_or_else(|| guid.clone())
+                });
+                let message = models::Message {
+                    guid: temp_guid.clone(),
+                    text: Some(text.clone()),
+                    is_from_me: true,
+                    date_created: Utc::now().timestamp_millis(),
+                    thread_originator_guid,
+                    ..Default::default()
+                };
+                self.sending.insert(temp_guid.clone());
+                self.upsert_message(message, None);
+                self.scroll_to_end();
+
+                sender.oneshot_command(async move {
+                    let result = async {
+                        let client = services::api::client().context("Not signed in")?;
+                        let message = client
+                            .messages()
+                            .send_text()
+                            .chat_guid(&chat_guid)
+                            .temp_guid(&temp_guid)
+                            .message(&text)
+                            // Replies need the Private API
+                            .maybe_method(
+                                reply_to.is_some().then_some(models::SendMethod::PrivateApi),
+                            )
+                            .maybe_selected_message_guid(
+                                reply_to.as_ref().map(|(guid, _)| guid.as_str()),
+                            )
+                            .maybe_part_index(reply_to.as_ref().map(|(_, part)| *part))
+                            .send()
+                            .await?;
+                        Store::get()
+                            .await?
+                            .upsert_messages(&chat_guid, std::slice::from_ref(&message))
+                            .await?;
+                        anyhow::Ok(message)
+                    }
+                    .await;
+                    ConversationCommandMsg::Sent {
+                        chat_guid,
+                        temp_guid,
+                        result: result.map_err(|e| e.to_string()),
+                    }
+                });
+            }
+            ConversationMsg::Retry(guid) => {
+                let Some(message) = self.remove_message(&guid) else {
+                    return;
+                };
+                delete_stored_message(guid);
+                let reply_to = message
+                    .thread_originator_guid
+                    .clone()
+                    .map(|guid| (guid, message.thread_originator_part_index().unwrap_or(0)));
+                if let Some(text) = message.text {
+                    sender.input(ConversationMsg::Send { text, reply_to });
+                }
+            }
+            ConversationMsg::Delete(guid) => {
+                self.remove_message(&guid);
+                delete_stored_message(guid);
+            }
+            ConversationMsg::Reply(guid) => {
+                if let Some(message) = self.find_message(&guid) {
+                    let target = self.reply_target(message);
+                    self.composer.emit(ComposerMsg::ReplyTo(Some(target)));
+                }
+            }
+            ConversationMsg::ShowThread(originator_guid) => {
+                let Some(chat_guid) = self.chat_guid.clone() else {
+                    return;
+                };
+                sender.oneshot_command(async move {
+                    let result = async {
+                        let mut thread = Store::get().await?.thread(&originator_guid).await?;
+                        // The originator can be older than the stored history
+                        if !thread.iter().any(|message| message.guid == originator_guid) {
+                            thread.insert(0, originator(&originator_guid).await?);
+                        }
+                        anyhow::Ok(thread)
+                    }
+                    .await
+                    .map_err(|e| e.to_string());
+                    ConversationCommandMsg::ThreadLoaded { chat_guid, result }
+                });
+            }
+        }
+    }
+
+    fn update_cmd(
+        &mut self,
+        message: Self::CommandOutput,
+        sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match message {
+            ConversationCommandMsg::Opened(Ok((chat, page))) => {
+                if self.chat_guid.as_ref() != Some(&chat.guid) {
+                    return;
+                }
+                self.loading = false;
+                self.complete = page.len() < PAGE_SIZE as usize;
+                self.chat = Some(chat);
+
+                let mut messages = self.take_tapbacks(page);
+                messages.reverse();
+                self.messages = messages;
+                self.reindex();
+                let rows: Vec<_> = (0..self.messages.len()).map(|i| self.row(i)).collect();
+                self.rows.extend_from_iter(rows);
+                self.scroll_to_end();
+                self.load_originators(&sender);
+                self.continue_jump(&sender);
+            }
+            ConversationCommandMsg::Opened(Err(e)) => {
+                self.loading = false;
+                tracing::error!("Failed to open chat: {}", e);
+            }
+            ConversationCommandMsg::OlderLoaded { chat_guid, result } => {
+                if self.chat_guid.as_ref() != Some(&chat_guid) {
+                    return;
+                }
+                self.loading = false;
+
+                let page = match result {
+                    Ok(page) => page,
+                    Err(e) => {
+                        tracing::error!("Failed to load older messages: {}", e);
+                        return;
+                    }
+                };
+                let before = self.oldest;
+                self.complete = page.len() < PAGE_SIZE as usize;
+                let page = self.take_tapbacks(page);
+                // Only if a whole page shares one timestamp, asking again would return
+                // the same page forever
+                self.complete |= self.oldest == before;
+
+                // Messages sharing the boundary timestamp are returned again
+                let mut older: Vec<_> = page
+                    .into_iter()
+                    .filter(|message| !self.positions.contains_key(&message.guid))
+                    .collect();
+                older.reverse();
+
+                let count = older.len();
+                self.messages.splice(0..0, older);
+                self.reindex();
+                for index in 0..count {
+                    let row = self.row(index);
+                    self.rows.insert(index as u32, row);
+                }
+                // The previously first message now has a neighbour above it
+                self.refresh_row(count);
+                self.load_originators(&sender);
+                self.continue_jump(&sender);
+            }
+            ConversationCommandMsg::Reloaded { chat_guid, result } => {
+                if self.chat_guid.as_ref() != Some(&chat_guid) {
+                    return;
+                }
+                match result {
+                    Ok(page) => {
+                        let at_end = self.is_scrolled_to_end();
+                        let page = self.take_tapbacks(page);
+                        for message in page.into_iter().rev() {
+                            self.upsert_message(message, None);
+                        }
+                        if at_end {
+                            self.scroll_to_end();
+                        }
+                        self.load_originators(&sender);
+                    }
+                    Err(e) => tracing::error!("Failed to reload chat: {}", e),
+                }
+            }
+            ConversationCommandMsg::Sent {
+                chat_guid,
+                temp_guid,
+                result,
+            } => {
+                self.sending.remove(&temp_guid);
+                if self.chat_guid.as_ref() != Some(&chat_guid) {
+                    return;
+                }
+                match result {
+                    Ok(message) => self.upsert_message(message, Some(&temp_guid)),
+                    Err(e) => {
+                        tracing::error!("Failed to send message: {}", e);
+                        if let Some(index) = self.index_of(&temp_guid) {
+                            self.messages[index].error = SEND_REQUEST_FAILED;
+                            self.refresh_row(index);
+                        }
+                        APP_BROKER.send(AppMsg::ShowToast(gettext("Message could not be sent")));
+                    }
+                }
+            }
+            ConversationCommandMsg::OriginatorsLoaded {
+                chat_guid,
+                messages,
+            } => {
+                if self.chat_guid.as_ref() != Some(&chat_guid) {
+                    return;
+                }
+                for message in messages {
+                    let guid = message.guid.clone();
+                    self.originators.insert(guid.clone(), message);
+                    self.refresh_thread(&guid);
+                }
+            }
+            ConversationCommandMsg::ThreadLoaded { chat_guid, result } => {
+                if self.chat_guid.as_ref() != Some(&chat_guid) {
+                    return;
+                }
+                match result {
+                    Ok(thread) => {
+                        let entries = thread
+                            .iter()
+                            .filter(|message| !message.is_tapback())
+                            .map(|message| ThreadEntry {
+                                reply: self.reply_target(message),
+                                time: format!(
+                                    "{} {}",
+                                    format::day_heading(message.date_created),
+                                    format::time(message.date_created)
+                                ),
+                            })
+                            .collect();
+                        self.thread_dialog.emit(ThreadDialogMsg::Show(entries));
+                    }
+                    Err(e) => tracing::error!("Failed to load thread: {}", e),
+                }
+            }
+            ConversationCommandMsg::Update(Update::Event(event)) => match event {
+                Event::NewMessage(message)
+                | Event::UpdatedMessage(message)
+                | Event::MessageSendError(message)
+                | Event::GroupNameChange(message)
+                | Event::ParticipantAdded(message)
+                | Event::ParticipantRemoved(message)
+                | Event::ParticipantLeft(message)
+                | Event::GroupIconChanged(message)
+                | Event::GroupIconRemoved(message) => {
+                    if !self.is_open(&message) {
+                        return;
+                    }
+                    if message.is_tapback() {
+                        self.add_tapback(*message);
+                        return;
+                    }
+                    let at_end = self.is_scrolled_to_end();
+                    let temp_guid = message.temp_guid.clone();
+                    if let Some(temp_guid) = &temp_guid {
+                        self.sending.remove(temp_guid);
+                    }
+                    self.upsert_message(*message, temp_guid.as_deref());
+                    if at_end {
+                        self.scroll_to_end();
+                    }
+                    self.load_originators(&sender);
+                }
+                _ => {}
+            },
+            ConversationCommandMsg::Update(Update::CaughtUp) => {
+                let Some(chat_guid) = self.chat_guid.clone() else {
+                    return;
+                };
+                sender.oneshot_command(async move {
+                    let result = history_page(&chat_guid, None)
+                        .await
+                        .map_err(|e| e.to_string());
+                    ConversationCommandMsg::Reloaded { chat_guid, result }
+                });
+            }
+            ConversationCommandMsg::Update(Update::ContactsChanged) => {
+                // Sender names are looked up when rows are built
+                for index in 0..self.messages.len() {
+                    self.refresh_row(index);
+                }
+            }
+            ConversationCommandMsg::Update(Update::State(_)) => {}
+        }
+    }
+}
+
+/// A message replied to, from the store or else the server
+async fn originator(guid: &str) -> anyhow::Result<models::Message> {
+    let store = Store::get().await?;
+    if let Some(message) = store.message(guid).await? {
+        return Ok(message);
+    }
+    let client = services::api::client().context("Not signed in")?;
+    Ok(client
+        .messages()
+        .get()
+        .with_handle()
+        .guid(guid)
+        .send()
+        .await?)
+}
+
+fn delete_stored_message(guid: String) {
+    relm4::spawn(async move {
+        let result = async { Store::get().await?.delete_message(&guid).await }.await;
+        if let Err(e) = result {
+            tracing::error!("Failed to delete message: {}", e);
+        }
+    });
+}
+
+async fn history_page(
+    chat_guid: &models::ChatGuid,
+    before: Option<i64>,
+) -> anyhow::Result<Vec<models::Message>> {
+    let client = services::api::client().context("Not signed in")?;
+    let store = Store::get().await?;
+    SyncEngine::new(client, store)
+        .history_page(chat_guid, before, PAGE_SIZE)
+        .await
+}
+
+/// Whether a message can show a delivery receipt, if it is the last one sent
+fn shows_receipt(message: &models::Message) -> bool {
+    message.is_from_me && message.item_type == 0
+}
+
+/// Whether a timestamp separator goes between two consecutive messages
+fn needs_separator(previous: &models::Message, message: &models::Message) -> bool {
+    let day = |timestamp| format::local_time(timestamp).map(|time| time.date_naive());
+    day(previous.date_created) != day(message.date_created)
+        || message.date_created - previous.date_created > SEPARATOR_INTERVAL_MS
+}
+
+/// Whether a message continues the bubble group of the one before it
+fn same_group(previous: &models::Message, message: &models::Message) -> bool {
+    previous.item_type == 0
+        && message.item_type == 0
+        && previous.is_from_me == message.is_from_me
+        && previous.handle_id == message.handle_id
+        && message.date_created - previous.date_created < GROUPING_INTERVAL_MS
+}
+
+/// Pango markup for a bubble, see [`bubble_text`]
+fn bubble_markup(message: &models::Message) -> String {
+    let markup = format::message_markup(message);
+    if !markup.is_empty() || message.attachments.is_empty() {
+        return markup;
+    }
+    glib::markup_escape_text(&bubble_text(message)).to_string()
+}
+
+/// The text of a message, or the names of its attachments if it has none
+fn bubble_text(message: &models::Message) -> String {
+    let text = format::message_text(message);
+    if !text.is_empty() || message.attachments.is_empty() {
+        return text;
+    }
+    message
+        .attachments
+        .iter()
+        .map(|attachment| {
+            attachment
+                .transfer_name
+                .clone()
+                .unwrap_or_else(|| gettext("Attachment"))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Receipt shown under the last sent message
+fn receipt(message: &models::Message) -> Option<String> {
+    if let Some(date_read) = message.date_read {
+        Some(gettext("Read {time}").replace("{time}", &format::time(date_read)))
+    } else if message.date_delivered.is_some() {
+        Some(gettext("Delivered"))
+    } else {
+        None
+    }
+}
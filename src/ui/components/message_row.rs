@@ -0,0 +1,395 @@
+use gettextrs::gettext;
+use libadwaita::prelude::*;
+use relm4::{
+    gtk::{self, gio, pango},
+    typed_view::list::RelmListItem,
+};
+
+/// How a message is drawn
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MessageKind {
+    Bubble,
+    /// Group changes such as renames, drawn as a centered caption
+    Event,
+}
+
+/// Whether an outgoing message made it to the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    Sent,
+    /// Shown optimistically until the server confirms it
+    Sending,
+    /// The send failed, the row offers to retry or delete it
+    Failed,
+}
+
+/// A reaction shown under a bubble
+#[derive(Debug, Clone)]
+pub struct ReactionBadge {
+    pub emoji: &'static str,
+    /// Names of everyone who reacted this way
+    pub senders: Vec<String>,
+    /// One of the reactions is ours
+    pub mine: bool,
+}
+
+/// The message a reply quotes, shown above its bubble
+#[derive(Debug, Clone)]
+pub struct ReplyPreview {
+    /// GUID of the thread's first message, clicking the preview opens the thread
+    pub originator_guid: String,
+    pub sender: String,
+    pub text: String,
+}
+
+/// A message in the conversation list, with everything that depends on its neighbours
+/// already worked out
+#[derive(Debug, Clone)]
+pub struct MessageRow {
+    pub guid: String,
+    pub kind: MessageKind,
+    /// Pango markup for bubbles, plain text for events
+    pub text: String,
+    pub is_from_me: bool,
+    /// Sender name, only set for the first received message of a group in group chats
+    pub sender: Option<String>,
+    /// Date separator shown above the message
+    pub heading: Option<String>,
+    pub grouped_with_previous: bool,
+    pub edited: bool,
+    pub unsent: bool,
+    pub status: DeliveryStatus,
+    /// Delivery receipt, only set for the last sent message
+    pub receipt: Option<String>,
+    pub reactions: Vec<ReactionBadge>,
+    pub reply_preview: Option<ReplyPreview>,
+    /// GUID of the thread's first message, set for every message of a thread
+    pub thread: Option<String>,
+}
+
+pub struct MessageRowWidgets {
+    heading: gtk::Label,
+    event: gtk::Label,
+    sender: gtk::Label,
+    reply_preview: gtk::Button,
+    reply_sender: gtk::Label,
+    reply_text: gtk::Label,
+    bubble: gtk::Box,
+    menu: gtk::PopoverMenu,
+    text: gtk::Label,
+    reactions: gtk::Box,
+    footer: gtk::Label,
+    failed_actions: gtk::Box,
+    retry: gtk::Button,
+    delete: gtk::Button,
+}
+
+impl RelmListItem for MessageRow {
+    type Root = gtk::Box;
+    type Widgets = MessageRowWidgets;
+
+    fn setup(_item: &gtk::ListItem) -> (gtk::Box, MessageRowWidgets) {
+        relm4::view! {
+            root = gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                set_margin_start: 12,
+                set_margin_end: 12,
+                set_spacing: 2,
+
+                #[name = "heading"]
+                gtk::Label {
+                    set_margin_top: 12,
+                    set_margin_bottom: 6,
+                    set_justify: gtk::Justification::Center,
+                    add_css_class: "caption-heading",
+                    add_css_class: "dimmed",
+                },
+
+                #[name = "event"]
+                gtk::Label {
+                    set_wrap: true,
+                    set_justify: gtk::Justification::Center,
+                    add_css_class: "caption",
+                    add_css_class: "dimmed",
+                },
+
+                #[name = "sender"]
+                gtk::Label {
+                    set_halign: gtk::Align::Start,
+                    set_margin_start: 12,
+                    set_ellipsize: pango::EllipsizeMode::End,
+                    add_css_class: "caption",
+                    add_css_class: "dimmed",
+                },
+
+                #[name = "reply_preview"]
+                gtk::Button {
+                    set_action_name: Some("conversation.thread"),
+                    set_tooltip_text: Some(&gettext("View Thread")),
+                    add_css_class: "flat",
+                    add_css_class: "reply-preview",
+
+                    gtk::Box {
+                        set_orientation: gtk::Orientation::Vertical,
+
+                        #[name = "reply_sender"]
+                        gtk::Label {
+                            set_xalign: 0.0,
+                            set_ellipsize: pango::EllipsizeMode::End,
+                            add_css_class: "caption-heading",
+                        },
+
+                        #[name = "reply_text"]
+                        gtk::Label {
+                            set_xalign: 0.0,
+                            set_single_line_mode: true,
+                            set_max_width_chars: 40,
+                            set_ellipsize: pango::EllipsizeMode::End,
+                            add_css_class: "caption",
+                            add_css_class: "dimmed",
+                        },
+                    },
+                },
+
+                #[name = "bubble"]
+                gtk::Box {
+                    #[name = "text"]
+                    gtk::Label {
+                        set_wrap: true,
+                        set_wrap_mode: pango::WrapMode::WordChar,
+                        set_max_width_chars: 50,
+                        set_xalign: 0.0,
+                        set_selectable: true,
+                    },
+                },
+
+                #[name = "reactions"]
+                gtk::Box {
+                    set_spacing: 4,
+                    set_margin_top: 2,
+                },
+
+                #[name = "footer"]
+                gtk::Label {
+                    add_css_class: "caption",
+                    add_css_class: "dimmed",
+                },
+
+                #[name = "failed_actions"]
+                gtk::Box {
+                    set_halign: gtk::Align::End,
+                    set_spacing: 6,
+
+                    #[name = "retry"]
+                    gtk::Button {
+                        set_label: &gettext("Retry"),
+                        set_action_name: Some("conversation.retry"),
+                        set_css_classes: &["flat", "caption"],
+                    },
+
+                    #[name = "delete"]
+                    gtk::Button {
+                        set_label: &gettext("Delete"),
+                        set_action_name: Some("conversation.delete"),
+                        set_css_classes: &["flat", "caption", "error"],
+                    },
+                },
+            }
+        }
+
+        // Right click or a long press on a bubble opens its menu, filled in on bind. The
+        // gestures run before the selectable text's own menu.
+        let menu = gtk::PopoverMenu::from_model(None::<&gio::MenuModel>);
+        menu.set_parent(&bubble);
+        menu.set_has_arrow(false);
+        bubble.connect_destroy({
+            let menu = menu.clone();
+            move |_| menu.unparent()
+        });
+
+        let click = gtk::GestureClick::new();
+        click.set_button(gtk::gdk::BUTTON_SECONDARY);
+        click.set_propagation_phase(gtk::PropagationPhase::Capture);
+        click.connect_pressed({
+            let menu = menu.clone();
+            move |gesture, _, x, y| {
+                gesture.set_state(gtk::EventSequenceState::Claimed);
+                popup_at(&menu, x, y);
+            }
+        });
+        bubble.add_controller(click);
+
+        let long_press = gtk::GestureLongPress::new();
+        long_press.set_propagation_phase(gtk::PropagationPhase::Capture);
+        long_press.connect_pressed({
+            let menu = menu.clone();
+            move |gesture, x, y| {
+                gesture.set_state(gtk::EventSequenceState::Claimed);
+                popup_at(&menu, x, y);
+            }
+        });
+        bubble.add_controller(long_press);
+
+        let widgets = MessageRowWidgets {
+            heading,
+            event,
+            sender,
+            reply_preview,
+            reply_sender,
+            reply_text,
+            bubble,
+            menu,
+            text,
+            reactions,
+            footer,
+            failed_actions,
+            retry,
+            delete,
+        };
+        (root, widgets)
+    }
+
+    fn bind(&mut self, widgets: &mut Self::Widgets, root: &mut Self::Root) {
+        // Rows are recycled, every property set for one message has to be reset for the next
+        root.set_margin_top(if self.grouped_with_previous { 0 } else { 8 });
+
+        widgets.heading.set_visible(self.heading.is_some());
+        widgets
+            .heading
+            .set_label(self.heading.as_deref().unwrap_or_default());
+
+        let is_event = self.kind == MessageKind::Event;
+        widgets.event.set_visible(is_event);
+        widgets.bubble.set_visible(!is_event);
+        if is_event {
+            widgets.event.set_label(&self.text);
+            widgets.sender.set_visible(false);
+            widgets.reply_preview.set_visible(false);
+            widgets.reactions.set_visible(false);
+            widgets.footer.set_visible(false);
+            widgets.failed_actions.set_visible(false);
+            return;
+        }
+
+        widgets.sender.set_visible(self.sender.is_some());
+        widgets
+            .sender
+            .set_label(self.sender.as_deref().unwrap_or_default());
+
+        let align = if self.is_from_me {
+            gtk::Align::End
+        } else {
+            gtk::Align::Start
+        };
+        widgets.reply_preview.set_halign(align);
+        widgets.bubble.set_halign(align);
+        widgets.reactions.set_halign(align);
+        widgets.footer.set_halign(align);
+
+        let mut classes = vec![
+            "message-bubble",
+            if self.is_from_me {
+                "outgoing"
+            } else {
+                "incoming"
+            },
+        ];
+        if self.unsent {
+            classes.push("unsent");
+        }
+        match self.status {
+            DeliveryStatus::Sent => {}
+            DeliveryStatus::Sending => classes.push("sending"),
+            DeliveryStatus::Failed => classes.push("failed"),
+        }
+        widgets.bubble.set_css_classes(&classes);
+
+        widgets
+            .reply_preview
+            .set_visible(self.reply_preview.is_some());
+        if let Some(preview) = &self.reply_preview {
+            widgets
+                .reply_preview
+                .set_action_target_value(Some(&preview.originator_guid.to_variant()));
+            widgets.reply_sender.set_label(&preview.sender);
+            widgets.reply_text.set_label(&preview.text);
+        }
+
+        let menu = gio::Menu::new();
+        if self.status == DeliveryStatus::Sent && !self.unsent {
+            menu.append_item(&menu_item(
+                &gettext("Reply"),
+                "conversation.reply",
+                &self.guid,
+            ));
+        }
+        if let Some(thread) = &self.thread {
+            menu.append_item(&menu_item(
+                &gettext("View Thread"),
+                "conversation.thread",
+                thread,
+            ));
+        }
+        widgets.menu.set_menu_model(Some(&menu));
+
+        if self.unsent {
+            widgets.text.set_text(&gettext("This message was unsent"));
+        } else {
+            widgets.text.set_markup(&self.text);
+        }
+
+        while let Some(child) = widgets.reactions.first_child() {
+            widgets.reactions.remove(&child);
+        }
+        widgets.reactions.set_visible(!self.reactions.is_empty());
+        for badge in &self.reactions {
+            let label = if badge.senders.len() > 1 {
+                format!("{} {}", badge.emoji, badge.senders.len())
+            } else {
+                badge.emoji.to_string()
+            };
+            let badge_label = gtk::Label::new(Some(&label));
+            badge_label.add_css_class("reaction-badge");
+            if badge.mine {
+                badge_label.add_css_class("mine");
+            }
+            badge_label.set_tooltip_text(Some(&badge.senders.join(", ")));
+            widgets.reactions.append(&badge_label);
+        }
+
+        let status = match self.status {
+            DeliveryStatus::Sent => self.receipt.clone(),
+            DeliveryStatus::Sending => Some(gettext("Sending…")),
+            DeliveryStatus::Failed => Some(gettext("Not Delivered")),
+        };
+        let footer = [self.edited.then(|| gettext("Edited")), status]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" · ");
+        widgets.footer.set_visible(!footer.is_empty());
+        widgets.footer.set_label(&footer);
+
+        // The buttons target the message through their action parameter
+        let failed = self.status == DeliveryStatus::Failed;
+        widgets.failed_actions.set_visible(failed);
+        if failed {
+            let guid = self.guid.to_variant();
+            widgets.retry.set_action_target_value(Some(&guid));
+            widgets.delete.set_action_target_value(Some(&guid));
+        }
+    }
+}
+
+fn popup_at(menu: &gtk::PopoverMenu, x: f64, y: f64) {
+    if menu.menu_model().is_some_and(|model| model.n_items() > 0) {
+        menu.set_pointing_to(Some(&gtk::gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+        menu.popup();
+    }
+}
+
+fn menu_item(label: &str, action: &str, guid: &str) -> gio::MenuItem {
+    let item = gio::MenuItem::new(Some(label), None);
+    item.set_action_and_target_value(Some(action), Some(&guid.to_variant()));
+    item
+}
@@ -0,0 +1,4 @@
+pub mod chat_row;
+pub mod composer;
+pub mod conversation;
+pub mod message_row;
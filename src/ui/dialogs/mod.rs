@@ -1,2 +1,6 @@
 pub mod about;
+pub mod new_chat;
+pub mod preferences;
+pub mod quick_reply;
 pub mod shortcuts;
+pub mod thread;
@@ -0,0 +1,540 @@
+use std::collections::HashSet;
+
+use anyhow::Context;
+use bluebubbles_api::models;
+use gettextrs::gettext;
+use libadwaita::prelude::*;
+use relm4::{
+    Component, ComponentParts, ComponentSender, adw,
+    gtk::{self, glib},
+};
+use uuid::Uuid;
+
+use crate::{
+    app::{APP_BROKER, AppMsg},
+    services::{self, contacts, store::Store},
+};
+
+/// Suggestions shown while typing a recipient
+const SUGGESTION_LIMIT: usize = 8;
+
+#[derive(Debug)]
+pub enum NewChatMsg {
+    Show,
+    QueryChanged(String),
+    /// Add the suggestion at this index
+    AddSuggestion(usize),
+    /// Add the first suggestion, otherwise what was typed as an address
+    AddTyped,
+    Remove(String),
+    MessageChanged(String),
+    Send,
+}
+
+#[derive(Debug)]
+pub enum NewChatCommandMsg {
+    CandidatesLoaded(Vec<Candidate>),
+    /// Whether an address is registered with iMessage, `None` if that couldn't be checked
+    Availability {
+        address: String,
+        imessage: Option<bool>,
+    },
+    Sent(Result<models::Chat, String>),
+}
+
+/// An address messages can be sent to
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    address: String,
+    /// The address normalized with [`contacts::normalize`], to tell if two match
+    key: String,
+    /// Name of the contact owning the address
+    name: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Availability {
+    Checking,
+    IMessage,
+    Sms,
+    /// The check failed, e.g. without the Private API
+    Unknown,
+}
+
+#[derive(Debug)]
+struct Recipient {
+    candidate: Candidate,
+    availability: Availability,
+}
+
+/// Starts a conversation with one or more recipients. Messaging a single person
+/// continues the existing chat with them instead of creating another one.
+pub struct NewChatDialog {
+    parent: gtk::Widget,
+    recipient_entry: adw::EntryRow,
+    suggestion_list: gtk::ListBox,
+    recipient_list: gtk::ListBox,
+    message_entry: adw::EntryRow,
+    send_button: gtk::Button,
+    /// Contacts and handles, loaded when the dialog is shown
+    candidates: Vec<Candidate>,
+    suggestions: Vec<Candidate>,
+    recipients: Vec<Recipient>,
+    query: String,
+    message: String,
+    sending: bool,
+}
+
+impl NewChatDialog {
+    fn update_suggestions(&mut self) {
+        let query = self.query.trim().to_lowercase();
+        self.suggestions = if query.is_empty() {
+            Vec::new()
+        } else {
+            self.candidates
+                .iter()
+                .filter(|candidate| !self.has_recipient(&candidate.key))
+                .filter(|candidate| {
+                    candidate.address.to_lowercase().contains(&query)
+                        || candidate
+                            .name
+                            .as_ref()
+                            .is_some_and(|name| name.to_lowercase().contains(&query))
+                })
+                .take(SUGGESTION_LIMIT)
+                .cloned()
+                .collect()
+        };
+    }
+
+    fn has_recipient(&self, key: &str) -> bool {
+        self.recipients
+            .iter()
+            .any(|recipient| recipient.candidate.key == key)
+    }
+
+    fn add_recipient(&mut self, candidate: Candidate, sender: &ComponentSender<Self>) {
+        if !self.has_recipient(&candidate.key) {
+            let address = candidate.address.clone();
+            self.recipients.push(Recipient {
+                candidate,
+                availability: Availability::Checking,
+            });
+            sender.oneshot_command(async move {
+                let imessage = match services::api::client() {
+                    Some(client) => client
+                        .handles()
+                        .get_imessage_availability()
+                        .address(&address)
+                        .send()
+                        .await
+                        .inspect_err(|e| {
+                            tracing::warn!("Failed to check iMessage availability: {}", e)
+                        })
+                        .ok(),
+                    None => None,
+                };
+                NewChatCommandMsg::Availability { address, imessage }
+            });
+        }
+        // Clearing the entry resets the query and suggestions
+        self.recipient_entry.set_text("");
+    }
+
+    /// Rebuild the lists and button states from the model
+    fn refresh(&self, sender: &ComponentSender<Self>) {
+        self.suggestion_list.remove_all();
+        for candidate in &self.suggestions {
+            let row = adw::ActionRow::builder()
+                .title(candidate.name.as_ref().unwrap_or(&candidate.address))
+                .use_markup(false)
+                .activatable(true)
+                .build();
+            if candidate.name.is_some() {
+                row.set_subtitle(&glib::markup_escape_text(&candidate.address));
+            }
+            self.suggestion_list.append(&row);
+        }
+        self.suggestion_list
+            .set_visible(!self.suggestions.is_empty());
+
+        self.recipient_list.remove_all();
+        for recipient in &self.recipients {
+            let candidate = &recipient.candidate;
+            let status = match recipient.availability {
+                Availability::Checking => Some(gettext("Checking…")),
+                Availability::IMessage => Some(gettext("iMessage")),
+                Availability::Sms => Some(gettext("SMS")),
+                Availability::Unknown => None,
+            };
+            let subtitle = match (&candidate.name, status) {
+                (Some(_), Some(status)) => format!("{} · {}", candidate.address, status),
+                (Some(_), None) => candidate.address.clone(),
+                (None, Some(status)) => status,
+                (None, None) => String::new(),
+            };
+            let row = adw::ActionRow::builder()
+                .title(candidate.name.as_ref().unwrap_or(&candidate.address))
+                .use_markup(false)
+                .subtitle(glib::markup_escape_text(&subtitle))
+                .build();
+
+            let remove_button = gtk::Button::builder()
+                .icon_name("window-close-symbolic")
+                .tooltip_text(gettext("Remove"))
+                .valign(gtk::Align::Center)
+                .css_classes(["flat", "circular"])
+                .build();
+            let remove_sender = sender.clone();
+            let address = candidate.address.clone();
+            remove_button.connect_clicked(move |_| {
+                remove_sender.input(NewChatMsg::Remove(address.clone()));
+            });
+            row.add_suffix(&remove_button);
+            self.recipient_list.append(&row);
+        }
+        self.recipient_list.set_visible(!self.recipients.is_empty());
+
+        self.recipient_entry.set_sensitive(!self.sending);
+        self.message_entry.set_sensitive(!self.sending);
+        self.send_button.set_sensitive(
+            !self.sending && !self.recipients.is_empty() && !self.message.trim().is_empty(),
+        );
+    }
+}
+
+impl Component for NewChatDialog {
+    type Init = gtk::Widget;
+    type Widgets = ();
+    type Input = NewChatMsg;
+    /// The chat the message was sent to
+    type Output = models::Chat;
+    type CommandOutput = NewChatCommandMsg;
+    type Root = adw::Dialog;
+
+    fn init_root() -> Self::Root {
+        adw::Dialog::builder()
+            .title(gettext("New Conversation"))
+            .content_width(420)
+            .content_height(520)
+            .build()
+    }
+
+    fn init(
+        parent: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        relm4::view! {
+            toolbar_view = adw::ToolbarView {
+                add_top_bar = &adw::HeaderBar {
+                    #[name = "send_button"]
+                    pack_end = &gtk::Button {
+                        set_label: &gettext("_Send"),
+                        set_use_underline: true,
+                        add_css_class: "suggested-action",
+                        connect_clicked[sender] => move |_| {
+                            sender.input(NewChatMsg::Send);
+                        },
+                    },
+                },
+
+                #[wrap(Some)]
+                set_content = &gtk::ScrolledWindow {
+                    set_hscrollbar_policy: gtk::PolicyType::Never,
+
+                    adw::Clamp {
+                        set_margin_top: 12,
+                        set_margin_bottom: 12,
+                        set_margin_start: 12,
+                        set_margin_end: 12,
+
+                        gtk::Box {
+                            set_orientation: gtk::Orientation::Vertical,
+                            set_spacing: 12,
+
+                            gtk::ListBox {
+                                set_selection_mode: gtk::SelectionMode::None,
+                                add_css_class: "boxed-list",
+
+                                #[name = "recipient_entry"]
+                                adw::EntryRow {
+                                    set_title: &gettext("To"),
+                                    connect_changed[sender] => move |entry| {
+                                        let text = entry.text().to_string();
+                                        sender.input(NewChatMsg::QueryChanged(text));
+                                    },
+                                    connect_entry_activated[sender] => move |_| {
+                                        sender.input(NewChatMsg::AddTyped);
+                                    },
+                                },
+                            },
+
+                            #[name = "suggestion_list"]
+                            gtk::ListBox {
+                                set_selection_mode: gtk::SelectionMode::None,
+                                add_css_class: "boxed-list",
+                                set_visible: false,
+                                connect_row_activated[sender] => move |_, row| {
+                                    sender.input(NewChatMsg::AddSuggestion(row.index() as usize));
+                                },
+                            },
+
+                            #[name = "recipient_list"]
+                            gtk::ListBox {
+                                set_selection_mode: gtk::SelectionMode::None,
+                                add_css_class: "boxed-list",
+                                set_visible: false,
+                            },
+
+                            gtk::ListBox {
+                                set_selection_mode: gtk::SelectionMode::None,
+                                add_css_class: "boxed-list",
+
+                                #[name = "message_entry"]
+                                adw::EntryRow {
+                                    set_title: &gettext("Message"),
+                                    connect_changed[sender] => move |entry| {
+                                        let text = entry.text().to_string();
+                                        sender.input(NewChatMsg::MessageChanged(text));
+                                    },
+                                    connect_entry_activated[sender] => move |_| {
+                                        sender.input(NewChatMsg::Send);
+                                    },
+                                },
+                            },
+                        },
+                    },
+                },
+            }
+        }
+        root.set_child(Some(&toolbar_view));
+
+        let model = Self {
+            parent,
+            recipient_entry,
+            suggestion_list,
+            recipient_list,
+            message_entry,
+            send_button,
+            candidates: Vec::new(),
+            suggestions: Vec::new(),
+            recipients: Vec::new(),
+            query: String::new(),
+            message: String::new(),
+            sending: false,
+        };
+        model.refresh(&sender);
+
+        ComponentParts { model, widgets: () }
+    }
+
+    fn update_with_view(
+        &mut self,
+        _widgets: &mut Self::Widgets,
+        message: Self::Input,
+        sender: ComponentSender<Self>,
+        dialog: &Self::Root,
+    ) {
+        match message {
+            NewChatMsg::Show => {
+                self.recipients.clear();
+                self.sending = false;
+                self.recipient_entry.set_text("");
+                self.message_entry.set_text("");
+
+                sender.oneshot_command(async {
+                    let mut seen = HashSet::new();
+                    // Contacts first, they have names to match against
+                    let mut candidates: Vec<Candidate> = contacts::addresses()
+                        .into_iter()
+                        .filter(|(address, _)| seen.insert(address.clone()))
+                        .map(|(address, name)| Candidate {
+                            key: address.clone(),
+                            address,
+                            name: Some(name),
+                        })
+                        .collect();
+
+                    let handles = async { Store::get().await?.handles().await }.await;
+                    match handles {
+                        Ok(handles) => {
+                            for handle in handles {
+                                let key =
+                                    contacts::normalize(&handle.address, handle.country.as_deref());
+                                if seen.insert(key.clone()) {
+                                    candidates.push(Candidate {
+                                        address: handle.address,
+                                        key,
+                                        name: None,
+                                    });
+                                }
+                            }
+                        }
+                        Err(e) => tracing::error!("Failed to load handles: {}", e),
+                    }
+                    NewChatCommandMsg::CandidatesLoaded(candidates)
+                });
+
+                dialog.present(Some(&self.parent));
+                self.recipient_entry.grab_focus();
+            }
+            NewChatMsg::QueryChanged(query) => {
+                self.query = query;
+                self.update_suggestions();
+            }
+            NewChatMsg::AddSuggestion(index) => {
+                if let Some(candidate) = self.suggestions.get(index).cloned() {
+                    self.add_recipient(candidate, &sender);
+                }
+            }
+            NewChatMsg::AddTyped => {
+                let query = self.query.trim().to_string();
+                if let Some(candidate) = self.suggestions.first().cloned() {
+                    self.add_recipient(candidate, &sender);
+                } else if is_address(&query) {
+                    let address = contacts::normalize(&query, None);
+                    let candidate = Candidate {
+                        key: address.clone(),
+                        address,
+                        name: None,
+                    };
+                    self.add_recipient(candidate, &sender);
+                } else if !self.recipients.is_empty() {
+                    self.message_entry.grab_focus();
+                }
+            }
+            NewChatMsg::Remove(address) => {
+                self.recipients
+                    .retain(|recipient| recipient.candidate.address != address);
+                self.update_suggestions();
+            }
+            NewChatMsg::MessageChanged(message) => self.message = message,
+            NewChatMsg::Send => {
+                let text = self.message.trim().to_string();
+                if self.sending || self.recipients.is_empty() || text.is_empty() {
+                    return;
+                }
+                self.sending = true;
+
+                let addresses: Vec<String> = self
+                    .recipients
+                    .iter()
+                    .map(|recipient| recipient.candidate.address.clone())
+                    .collect();
+                let keys: Vec<String> = self
+                    .recipients
+                    .iter()
+                    .map(|recipient| recipient.candidate.key.clone())
+                    .collect();
+                // Anyone without iMessage makes it an SMS conversation
+                let service = if self
+                    .recipients
+                    .iter()
+                    .any(|recipient| recipient.availability == Availability::Sms)
+                {
+                    models::Service::Sms
+                } else {
+                    models::Service::IMessage
+                };
+
+                sender.oneshot_command(async move {
+                    let result = async {
+                        let client = services::api::client().context("Not signed in")?;
+                        let store = Store::get().await?;
+                        let temp_guid = format!("temp-{}", Uuid::new_v4());
+
+                        if let [key] = keys.as_slice()
+                            && let Some(chat) = store.direct_chat(key).await?
+                        {
+                            let message = client
+                                .messages()
+                                .send_text()
+                                .chat_guid(&chat.guid)
+                                .temp_guid(&temp_guid)
+                                .message(&text)
+                                .send()
+                                .await?;
+                            store
+                                .upsert_messages(&chat.guid, std::slice::from_ref(&message))
+                                .await?;
+                            return anyhow::Ok(chat);
+                        }
+
+                        let addresses: Vec<&str> = addresses.iter().map(String::as_str).collect();
+                        let chat = client
+                            .chats()
+                            .create()
+                            .addresses(&addresses)
+                            .message(&text)
+                            .service(service)
+                            .temp_guid(&temp_guid)
+                            .send()
+                            .await?;
+                        store.upsert_chat(&chat).await?;
+                        store.upsert_messages(&chat.guid, &chat.messages).await?;
+                        anyhow::Ok(chat)
+                    }
+                    .await;
+                    NewChatCommandMsg::Sent(result.map_err(|e| e.to_string()))
+                });
+            }
+        }
+
+        self.refresh(&sender);
+    }
+
+    fn update_cmd(
+        &mut self,
+        message: Self::CommandOutput,
+        sender: ComponentSender<Self>,
+        dialog: &Self::Root,
+    ) {
+        match message {
+            NewChatCommandMsg::CandidatesLoaded(candidates) => {
+                self.candidates = candidates;
+                self.update_suggestions();
+            }
+            NewChatCommandMsg::Availability { address, imessage } => {
+                if let Some(recipient) = self
+                    .recipients
+                    .iter_mut()
+                    .find(|recipient| recipient.candidate.address == address)
+                {
+                    recipient.availability = match imessage {
+                        Some(true) => Availability::IMessage,
+                        Some(false) => Availability::Sms,
+                        None => Availability::Unknown,
+                    };
+                }
+            }
+            NewChatCommandMsg::Sent(Ok(chat)) => {
+                self.sending = false;
+                dialog.close();
+                sender.output(chat).unwrap();
+            }
+            NewChatCommandMsg::Sent(Err(e)) => {
+                tracing::error!("Failed to start conversation: {}", e);
+                self.sending = false;
+                APP_BROKER.send(AppMsg::ShowToast(gettext(
+                    "Could not start the conversation",
+                )));
+            }
+        }
+
+        self.refresh(&sender);
+    }
+}
+
+/// Whether typed text looks like an email address or phone number
+fn is_address(text: &str) -> bool {
+    if text.contains('@') {
+        return text
+            .split_once('@')
+            .is_some_and(|(user, domain)| !user.is_empty() && domain.contains('.'));
+    }
+    text.chars().filter(char::is_ascii_digit).count() >= 3
+        && text
+            .chars()
+            .all(|c| c.is_ascii_digit() || "+-() .".contains(c))
+}
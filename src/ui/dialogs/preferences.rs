@@ -0,0 +1,75 @@
+use gettextrs::gettext;
+use relm4::{
+    ComponentParts, ComponentSender, SimpleComponent,
+    adw::{self, prelude::*},
+    gtk::gio,
+};
+
+use crate::config::APP_ID;
+
+pub struct PreferencesDialog {
+    parent: Option<adw::ApplicationWindow>,
+    /// Kept alive for the bindings
+    _settings: gio::Settings,
+}
+
+impl SimpleComponent for PreferencesDialog {
+    type Init = Option<adw::ApplicationWindow>;
+    type Widgets = adw::PreferencesDialog;
+    type Input = ();
+    type Output = ();
+    type Root = adw::PreferencesDialog;
+
+    fn init_root() -> Self::Root {
+        adw::PreferencesDialog::new()
+    }
+
+    fn init(
+        parent: Self::Init,
+        root: Self::Root,
+        _sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let settings = gio::Settings::new(APP_ID);
+
+        let background_row = adw::SwitchRow::builder()
+            .title(gettext("Run in Background"))
+            .subtitle(gettext(
+                "Keep receiving messages and notifications with the window closed, and start at login",
+            ))
+            .build();
+        settings
+            .bind("run-in-background", &background_row, "active")
+            .build();
+
+        let compact_row = adw::SwitchRow::builder()
+            .title(gettext("Compact Mode"))
+            .subtitle(gettext("Show the chat list and conversation one at a time"))
+            .build();
+        settings
+            .bind("compact-mode", &compact_row, "active")
+            .build();
+
+        let general_group = adw::PreferencesGroup::new();
+        general_group.add(&background_row);
+        general_group.add(&compact_row);
+
+        let general_page = adw::PreferencesPage::builder()
+            .title(gettext("General"))
+            .icon_name("preferences-system-symbolic")
+            .build();
+        general_page.add(&general_group);
+        root.add(&general_page);
+
+        let model = Self {
+            parent,
+            _settings: settings,
+        };
+        let widgets = root.clone();
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update_view(&self, dialog: &mut Self::Widgets, _sender: ComponentSender<Self>) {
+        dialog.present(self.parent.as_ref());
+    }
+}
@@ -0,0 +1,101 @@
+use bluebubbles_api::models;
+use gettextrs::gettext;
+use libadwaita::prelude::*;
+use relm4::{Component, ComponentParts, ComponentSender, adw, gtk};
+
+use crate::ui::notifications::{self, MessageNotification};
+
+#[derive(Debug)]
+pub enum QuickReplyMsg {
+    /// Ask for a reply to the message a notification was posted for
+    Show(MessageNotification),
+    Send,
+}
+
+/// Replies to a notification without opening the chat.
+///
+/// The fallback for when notifications can't take a typed reply, see
+/// [`notifications::init_portal`].
+pub struct QuickReplyDialog {
+    parent: Option<adw::ApplicationWindow>,
+    entry: gtk::Entry,
+    chat_guid: Option<models::ChatGuid>,
+}
+
+impl Component for QuickReplyDialog {
+    type Init = Option<adw::ApplicationWindow>;
+    type Widgets = ();
+    type Input = QuickReplyMsg;
+    type Output = ();
+    type CommandOutput = ();
+    type Root = adw::AlertDialog;
+
+    fn init_root() -> Self::Root {
+        let dialog = adw::AlertDialog::builder()
+            .close_response("cancel")
+            .default_response("send")
+            .build();
+        dialog.add_response("cancel", &gettext("_Cancel"));
+        dialog.add_response("send", &gettext("_Send"));
+        dialog.set_response_appearance("send", adw::ResponseAppearance::Suggested);
+        dialog
+    }
+
+    fn init(
+        parent: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let entry = gtk::Entry::builder()
+            .placeholder_text(gettext("Message"))
+            .activates_default(true)
+            .build();
+        let dialog = root.clone();
+        entry.connect_changed(move |entry| {
+            dialog.set_response_enabled("send", !entry.text().trim().is_empty());
+        });
+        root.set_extra_child(Some(&entry));
+
+        root.connect_response(Some("send"), move |_, _| sender.input(QuickReplyMsg::Send));
+
+        let model = Self {
+            parent,
+            entry,
+            chat_guid: None,
+        };
+
+        ComponentParts { model, widgets: () }
+    }
+
+    fn update_with_view(
+        &mut self,
+        _widgets: &mut Self::Widgets,
+        message: Self::Input,
+        _sender: ComponentSender<Self>,
+        dialog: &Self::Root,
+    ) {
+        match message {
+            QuickReplyMsg::Show(notification) => {
+                dialog.set_heading(Some(
+                    &gettext("Reply to {name}").replace("{name}", &notification.title),
+                ));
+                dialog.set_body(&notification.body);
+                self.entry.set_text("");
+                dialog.set_response_enabled("send", false);
+                self.chat_guid = Some(notification.chat_guid);
+                dialog.present(self.parent.as_ref());
+                self.entry.grab_focus();
+            }
+            QuickReplyMsg::Send => {
+                let Some(chat_guid) = self.chat_guid.take() else {
+                    return;
+                };
+                let text = self.entry.text().trim().to_string();
+                if text.is_empty() {
+                    return;
+                }
+                notifications::send_reply(chat_guid, text);
+            }
+        }
+    }
+}
@@ -33,6 +33,18 @@ impl SimpleComponent for ShortcutsDialog {
         let show_shortcuts_item = adw::ShortcutsItem::new("Show Shortcuts", "<Primary>question");
         general_section.add(show_shortcuts_item);
 
+        // Add "Preferences" shortcut
+        let preferences_item = adw::ShortcutsItem::new("Preferences", "<Primary>comma");
+        general_section.add(preferences_item);
+
+        // Add "New Conversation" shortcut
+        let new_chat_item = adw::ShortcutsItem::new("New Conversation", "<Primary>n");
+        general_section.add(new_chat_item);
+
+        // Add "Search" shortcut
+        let search_item = adw::ShortcutsItem::new("Search", "<Primary>f");
+        general_section.add(search_item);
+
         // Add "Quit" shortcut
         let quit_item = adw::ShortcutsItem::new("Quit", "<Primary>q");
         general_section.add(quit_item);
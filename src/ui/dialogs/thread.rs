@@ -0,0 +1,120 @@
+use gettextrs::gettext;
+use libadwaita::prelude::*;
+use relm4::{Component, ComponentParts, ComponentSender, adw, gtk};
+
+use crate::ui::components::composer::ReplyTarget;
+
+/// A message of a thread, as shown in the dialog
+#[derive(Debug, Clone)]
+pub struct ThreadEntry {
+    pub reply: ReplyTarget,
+    pub time: String,
+}
+
+#[derive(Debug)]
+pub enum ThreadDialogMsg {
+    /// Show a thread, originator first
+    Show(Vec<ThreadEntry>),
+    /// Reply to the entry at this index
+    Reply(usize),
+}
+
+/// The full reply chain of a thread. Activating a message replies to it.
+pub struct ThreadDialog {
+    parent: gtk::Widget,
+    list: gtk::ListBox,
+    entries: Vec<ThreadEntry>,
+}
+
+impl Component for ThreadDialog {
+    type Init = gtk::Widget;
+    type Widgets = ();
+    type Input = ThreadDialogMsg;
+    type Output = ReplyTarget;
+    type CommandOutput = ();
+    type Root = adw::Dialog;
+
+    fn init_root() -> Self::Root {
+        adw::Dialog::builder()
+            .title(gettext("Thread"))
+            .content_width(420)
+            .content_height(560)
+            .build()
+    }
+
+    fn init(
+        parent: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        relm4::view! {
+            toolbar_view = adw::ToolbarView {
+                add_top_bar = &adw::HeaderBar {},
+
+                #[wrap(Some)]
+                set_content = &gtk::ScrolledWindow {
+                    set_hscrollbar_policy: gtk::PolicyType::Never,
+
+                    adw::Clamp {
+                        set_margin_top: 12,
+                        set_margin_bottom: 12,
+                        set_margin_start: 12,
+                        set_margin_end: 12,
+
+                        #[name = "list"]
+                        gtk::ListBox {
+                            set_selection_mode: gtk::SelectionMode::None,
+                            add_css_class: "boxed-list",
+                            connect_row_activated[sender] => move |_, row| {
+                                sender.input(ThreadDialogMsg::Reply(row.index() as usize));
+                            },
+                        },
+                    },
+                },
+            }
+        }
+        root.set_child(Some(&toolbar_view));
+
+        let model = Self {
+            parent,
+            list,
+            entries: Vec::new(),
+        };
+
+        ComponentParts { model, widgets: () }
+    }
+
+    fn update_with_view(
+        &mut self,
+        _widgets: &mut Self::Widgets,
+        message: Self::Input,
+        sender: ComponentSender<Self>,
+        dialog: &Self::Root,
+    ) {
+        match message {
+            ThreadDialogMsg::Show(entries) => {
+                self.list.remove_all();
+                for entry in &entries {
+                    let row = adw::ActionRow::builder()
+                        .title(&entry.reply.text)
+                        .title_lines(0)
+                        .use_markup(false)
+                        .subtitle(format!("{} · {}", entry.reply.sender, entry.time))
+                        .activatable(true)
+                        .tooltip_text(gettext("Reply"))
+                        .build();
+                    row.add_suffix(&gtk::Image::from_icon_name("mail-reply-sender-symbolic"));
+                    self.list.append(&row);
+                }
+                self.entries = entries;
+                dialog.present(Some(&self.parent));
+            }
+            ThreadDialogMsg::Reply(index) => {
+                if let Some(entry) = self.entries.get(index) {
+                    sender.output(entry.reply.clone()).unwrap();
+                    dialog.close();
+                }
+            }
+        }
+    }
+}
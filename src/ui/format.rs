@@ -0,0 +1,216 @@
+use bluebubbles_api::models;
+use chrono::{DateTime, Datelike, Local, TimeDelta};
+use gettextrs::{gettext, ngettext};
+use relm4::gtk::glib;
+
+use crate::services::contacts;
+
+/// Placeholder iMessage puts in the text where an attachment is shown inline
+const OBJECT_REPLACEMENT: char = '\u{FFFC}';
+
+/// The name shown for a chat: its display name, otherwise the participants
+pub fn chat_title(chat: &models::Chat) -> String {
+    if !chat.display_name.is_empty() {
+        return chat.display_name.clone();
+    }
+    if chat.participants.is_empty() {
+        return chat.chat_identifier.clone();
+    }
+    chat.participants
+        .iter()
+        .map(handle_name)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The contact name for a handle, otherwise its address
+pub fn handle_name(handle: &models::Handle) -> String {
+    contacts::name(&handle.address, handle.country.as_deref())
+        .or_else(|| handle.formatted_address.clone())
+        .unwrap_or_else(|| handle.address.clone())
+}
+
+/// Emoji shown on a reaction badge
+pub fn reaction_emoji(reaction: models::Reaction) -> &'static str {
+    match reaction {
+        models::Reaction::Love => "❤️",
+        models::Reaction::Like => "👍",
+        models::Reaction::Dislike => "👎",
+        models::Reaction::Laugh => "😂",
+        models::Reaction::Emphasize => "‼️",
+        models::Reaction::Question => "❓",
+    }
+}
+
+/// The text of a message without attachment placeholders
+pub fn message_text(message: &models::Message) -> String {
+    message
+        .body_text()
+        .replace(OBJECT_REPLACEMENT, "")
+        .trim()
+        .to_string()
+}
+
+/// The text of a message as Pango markup, with mentions, links and formatting
+pub fn message_markup(message: &models::Message) -> String {
+    let mut segments: Vec<_> = message
+        .body()
+        .into_iter()
+        .filter(|segment| !matches!(segment.kind, models::SegmentKind::Attachment { .. }))
+        .map(|segment| models::TextSegment {
+            text: segment.text.replace(OBJECT_REPLACEMENT, ""),
+            ..segment
+        })
+        .collect();
+    // Attachments leave the whitespace around them behind
+    if let Some(first) = segments.first_mut() {
+        first.text = first.text.trim_start().to_string();
+    }
+    if let Some(last) = segments.last_mut() {
+        last.text = last.text.trim_end().to_string();
+    }
+
+    let mut markup = String::new();
+    for segment in segments {
+        let mut tags: Vec<(String, &str)> = Vec::new();
+        match &segment.kind {
+            models::SegmentKind::Mention { .. } => tags.push(("<b>".to_string(), "</b>")),
+            models::SegmentKind::Link { url } if is_openable_link(url) => tags.push((
+                format!("<a href=\"{}\">", glib::markup_escape_text(url)),
+                "</a>",
+            )),
+            // Other schemes could launch arbitrary handlers, show those as plain text
+            models::SegmentKind::Link { .. }
+            | models::SegmentKind::Text
+            | models::SegmentKind::Attachment { .. } => {}
+        }
+        let style = segment.style;
+        for (enabled, open, close) in [
+            (style.bold, "<b>", "</b>"),
+            (style.italic, "<i>", "</i>"),
+            (style.underline, "<u>", "</u>"),
+            (style.strikethrough, "<s>", "</s>"),
+            (
+                style.effect == Some(models::TextEffect::Big),
+                "<big>",
+                "</big>",
+            ),
+            (
+                style.effect == Some(models::TextEffect::Small),
+                "<small>",
+                "</small>",
+            ),
+        ] {
+            if enabled {
+                tags.push((open.to_string(), close));
+            }
+        }
+
+        for (open, _) in &tags {
+            markup.push_str(open);
+        }
+        markup.push_str(&glib::markup_escape_text(&segment.text));
+        for (_, close) in tags.iter().rev() {
+            markup.push_str(close);
+        }
+    }
+    markup
+}
+
+/// Whether a link uses a scheme that is safe to open when clicked
+fn is_openable_link(url: &str) -> bool {
+    let Some((scheme, _)) = url.split_once(':') else {
+        return false;
+    };
+    ["http", "https", "mailto", "tel"]
+        .iter()
+        .any(|allowed| scheme.eq_ignore_ascii_case(allowed))
+}
+
+/// Description of a group event such as a rename, `sender` is who made the change
+pub fn event_text(message: &models::Message, sender: &str) -> String {
+    let text = match (message.item_type, message.group_action_type) {
+        (1, 0) => gettext("{sender} added someone to the conversation"),
+        (1, _) => gettext("{sender} removed someone from the conversation"),
+        (2, _) => gettext("{sender} named the conversation “{title}”"),
+        (3, 0) => gettext("{sender} left the conversation"),
+        (3, 1) => gettext("{sender} changed the group photo"),
+        (3, _) => gettext("{sender} removed the group photo"),
+        _ => gettext("The conversation was updated"),
+    };
+    text.replace("{sender}", sender).replace(
+        "{title}",
+        message.group_title.as_deref().unwrap_or_default(),
+    )
+}
+
+/// One line summary of a message for the chat list
+pub fn message_preview(message: &models::Message) -> String {
+    let text = message_text(message).replace('\n', " ");
+
+    let preview = if message.date_retracted.is_some() {
+        gettext("This message was unsent")
+    } else if !text.is_empty() {
+        text
+    } else if !message.attachments.is_empty() {
+        let count = message.attachments.len() as u32;
+        ngettext("Attachment", "{count} attachments", count).replace("{count}", &count.to_string())
+    } else {
+        String::new()
+    };
+
+    if message.is_from_me && !preview.is_empty() {
+        gettext("You: {preview}").replace("{preview}", &preview)
+    } else {
+        preview
+    }
+}
+
+/// Convert a server timestamp in milliseconds to local time
+pub fn local_time(timestamp: i64) -> Option<DateTime<Local>> {
+    DateTime::from_timestamp_millis(timestamp).map(|time| time.with_timezone(&Local))
+}
+
+/// Short timestamp for lists: the time for today, the weekday within the last week,
+/// otherwise the date
+pub fn short_timestamp(timestamp: i64) -> String {
+    let Some(time) = local_time(timestamp) else {
+        return String::new();
+    };
+    let now = Local::now();
+
+    if time.date_naive() == now.date_naive() {
+        time.format("%H:%M").to_string()
+    } else if now.signed_duration_since(time) < TimeDelta::days(6) {
+        time.format("%a").to_string()
+    } else if time.year() == now.year() {
+        time.format("%b %-d").to_string()
+    } else {
+        time.format("%b %-d, %Y").to_string()
+    }
+}
+
+/// Time of day, e.g. "14:05"
+pub fn time(timestamp: i64) -> String {
+    local_time(timestamp)
+        .map(|time| time.format("%H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// Heading for a day of messages, e.g. "Today" or "Monday, March 3"
+pub fn day_heading(timestamp: i64) -> String {
+    let Some(time) = local_time(timestamp) else {
+        return String::new();
+    };
+    let today = Local::now().date_naive();
+
+    if time.date_naive() == today {
+        gettext("Today")
+    } else if today.pred_opt() == Some(time.date_naive()) {
+        gettext("Yesterday")
+    } else if time.year() == today.year() {
+        time.format("%A, %B %-d").to_string()
+    } else {
+        time.format("%A, %B %-d, %Y").to_string()
+    }
+}
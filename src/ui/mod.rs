@@ -1,2 +1,5 @@
+pub mod components;
 pub mod dialogs;
+pub mod format;
+pub mod notifications;
 pub mod pages;
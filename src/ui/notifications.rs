@@ -0,0 +1,266 @@
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+
+use anyhow::{Context, Result};
+use bluebubbles_api::models;
+use gettextrs::gettext;
+use relm4::{
+    gtk::{
+        gio, glib,
+        prelude::{ApplicationExt, GtkApplicationExt, GtkWindowExt, SettingsExt, ToVariant},
+    },
+    main_application,
+};
+use uuid::Uuid;
+
+use crate::{
+    app::{APP_BROKER, AppMsg},
+    config::APP_ID,
+    services::{
+        self, contacts,
+        notification_portal::{self, ChatNotification, PortalAction},
+        store::Store,
+    },
+    ui::format,
+};
+
+/// Chats that don't post notifications, by GUID
+const MUTED_CHATS_KEY: &str = "muted-chats";
+
+const OPEN_CHAT_ACTION: &str = "app.open-chat";
+const REPLY_ACTION: &str = "app.reply";
+const MARK_READ_ACTION: &str = "app.mark-read";
+
+/// The chat shown in the window, it gets no notifications while the window is focused
+static OPEN_CHAT: Mutex<Option<models::ChatGuid>> = Mutex::new(None);
+/// The notification currently posted for each chat
+static POSTED: LazyLock<Mutex<HashMap<models::ChatGuid, MessageNotification>>> =
+    LazyLock::new(Default::default);
+
+/// A notification for a new message
+#[derive(Debug, Clone)]
+pub struct MessageNotification {
+    pub chat_guid: models::ChatGuid,
+    /// The sender, or the chat for group chats
+    pub title: String,
+    pub body: String,
+    /// Photo of the sender, as encoded image data
+    pub avatar: Option<glib::Bytes>,
+}
+
+/// Build the notification for a new message, `None` if it doesn't get one.
+///
+/// Reads the store, so it runs off the main thread. Muting and focus are checked when
+/// it is shown.
+pub async fn prepare(message: &models::Message) -> Result<Option<MessageNotification>> {
+    if message.is_from_me || message.item_type != 0 || message.tapback().is_some() {
+        return Ok(None);
+    }
+    let Some(chat) = message.chats.first() else {
+        return Ok(None);
+    };
+    let preview = format::message_preview(message);
+    if preview.is_empty() {
+        return Ok(None);
+    }
+
+    // Event payloads leave out the participants
+    let chat = Store::get()
+        .await?
+        .chat(&chat.guid)
+        .await?
+        .unwrap_or_else(|| chat.clone());
+    let sender = message
+        .handle
+        .as_ref()
+        .map(format::handle_name)
+        .unwrap_or_else(|| format::chat_title(&chat));
+
+    let (title, body) = if chat.participants.len() > 1 {
+        let body = gettext("{sender}: {text}")
+            .replace("{sender}", &sender)
+            .replace("{text}", &preview);
+        (format::chat_title(&chat), body)
+    } else {
+        (sender, preview)
+    };
+
+    Ok(Some(MessageNotification {
+        chat_guid: chat.guid,
+        title,
+        body,
+        avatar: message
+            .handle
+            .as_ref()
+            .and_then(|handle| contacts::avatar(&handle.address, handle.country.as_deref())),
+    }))
+}
+
+/// Post notifications through the notification portal when it can take a typed reply.
+///
+/// Otherwise they are posted with GNotification, where "Reply" only has a button and
+/// opens the quick reply dialog instead.
+pub fn init_portal() {
+    relm4::spawn(async {
+        let result = notification_portal::init(|action| {
+            APP_BROKER.send(match action {
+                PortalAction::OpenChat(chat_guid) => AppMsg::OpenChat(chat_guid),
+                PortalAction::Reply {
+                    chat_guid,
+                    text: Some(text),
+                } => AppMsg::SendReply { chat_guid, text },
+                PortalAction::Reply {
+                    chat_guid,
+                    text: None,
+                } => AppMsg::Reply(chat_guid),
+                PortalAction::MarkRead(chat_guid) => AppMsg::MarkRead(chat_guid),
+            })
+        })
+        .await;
+        match result {
+            Ok(true) => tracing::debug!("Posting notifications through the portal"),
+            Ok(false) => tracing::debug!("The notification portal can't take replies"),
+            Err(e) => tracing::debug!("The notification portal is not available: {}", e),
+        }
+    });
+}
+
+/// Post a notification, replacing the previous one for the chat
+pub fn show(notification: MessageNotification) {
+    let guid = &notification.chat_guid;
+    if is_muted(guid) {
+        return;
+    }
+    let focused = main_application()
+        .active_window()
+        .is_some_and(|window| window.is_active());
+    if focused && OPEN_CHAT.lock().unwrap().as_ref() == Some(guid) {
+        return;
+    }
+    POSTED
+        .lock()
+        .unwrap()
+        .insert(guid.clone(), notification.clone());
+
+    if notification_portal::is_available() {
+        relm4::spawn(async move {
+            let posted = ChatNotification {
+                chat_guid: &notification.chat_guid,
+                title: &notification.title,
+                body: &notification.body,
+                avatar: notification.avatar.as_deref(),
+            };
+            if let Err(e) = notification_portal::add(&id(posted.chat_guid), posted).await {
+                tracing::warn!("Failed to post notification through the portal: {}", e);
+                glib::MainContext::default().invoke(move || post(&notification));
+            }
+        });
+    } else {
+        post(&notification);
+    }
+}
+
+/// Post a notification with GNotification, its reply button opens a dialog
+fn post(notification: &MessageNotification) {
+    let guid = &notification.chat_guid;
+    let target = guid.as_str().to_variant();
+    let posted = gio::Notification::new(&notification.title);
+    posted.set_body(Some(&notification.body));
+    if let Some(avatar) = &notification.avatar {
+        posted.set_icon(&gio::BytesIcon::new(avatar));
+    }
+    posted.set_default_action_and_target_value(OPEN_CHAT_ACTION, Some(&target));
+    posted.add_button_with_target_value(&gettext("Reply"), REPLY_ACTION, Some(&target));
+    posted.add_button_with_target_value(&gettext("Mark as Read"), MARK_READ_ACTION, Some(&target));
+    main_application().send_notification(Some(&id(guid)), &posted);
+}
+
+/// The notification posted for a chat since the app started
+pub fn posted(chat_guid: &models::ChatGuid) -> Option<MessageNotification> {
+    POSTED.lock().unwrap().get(chat_guid).cloned()
+}
+
+/// Remove the notification for a chat
+pub fn withdraw(chat_guid: &models::ChatGuid) {
+    let id = id(chat_guid);
+    main_application().withdraw_notification(&id);
+    if notification_portal::is_available() {
+        relm4::spawn(async move {
+            if let Err(e) = notification_portal::remove(&id).await {
+                tracing::warn!("Failed to withdraw notification: {}", e);
+            }
+        });
+    }
+    POSTED.lock().unwrap().remove(chat_guid);
+}
+
+/// Send a reply typed into a notification or the quick reply dialog
+pub fn send_reply(chat_guid: models::ChatGuid, text: String) {
+    withdraw(&chat_guid);
+    relm4::spawn(async move {
+        let result = async {
+            let client = services::api::client().context("Not signed in")?;
+            let message = client
+                .messages()
+                .send_text()
+                .chat_guid(&chat_guid)
+                .temp_guid(&format!("temp-{}", Uuid::new_v4()))
+                .message(&text)
+                .send()
+                .await?;
+            Store::get()
+                .await?
+                .upsert_messages(&chat_guid, std::slice::from_ref(&message))
+                .await?;
+            anyhow::Ok(())
+        }
+        .await;
+        if let Err(e) = result {
+            tracing::error!("Failed to send reply: {}", e);
+            APP_BROKER.send(AppMsg::ShowToast(gettext("Could not send the reply")));
+        }
+    });
+}
+
+/// Set the chat shown in the window, its notification is removed
+pub fn set_open_chat(chat_guid: Option<&models::ChatGuid>) {
+    if let Some(guid) = chat_guid {
+        withdraw(guid);
+    }
+    *OPEN_CHAT.lock().unwrap() = chat_guid.cloned();
+}
+
+pub fn is_muted(chat_guid: &models::ChatGuid) -> bool {
+    gio::Settings::new(APP_ID)
+        .strv(MUTED_CHATS_KEY)
+        .iter()
+        .any(|guid| chat_guid == guid.as_str())
+}
+
+/// Unmute every chat, used when signing out
+pub fn clear_muted() {
+    gio::Settings::new(APP_ID).reset(MUTED_CHATS_KEY);
+}
+
+pub fn set_muted(chat_guid: &models::ChatGuid, muted: bool) -> Result<(), glib::BoolError> {
+    let settings = gio::Settings::new(APP_ID);
+    let mut chats: Vec<String> = settings
+        .strv(MUTED_CHATS_KEY)
+        .iter()
+        .map(|guid| guid.to_string())
+        .filter(|guid| guid != chat_guid.as_str())
+        .collect();
+    if muted {
+        chats.push(chat_guid.to_string());
+        withdraw(chat_guid);
+    }
+    let chats: Vec<&str> = chats.iter().map(String::as_str).collect();
+    settings.set_strv(MUTED_CHATS_KEY, chats.as_slice())
+}
+
+/// Notifications are replaced per chat, so each only shows the latest message
+fn id(chat_guid: &models::ChatGuid) -> String {
+    format!("chat-{chat_guid}")
+}
@@ -0,0 +1,500 @@
+use bluebubbles_api::{models, websocket::Event};
+use chrono::Utc;
+use gettextrs::gettext;
+use libadwaita::prelude::*;
+use relm4::{
+    Component, ComponentController, ComponentParts, ComponentSender, Controller, adw,
+    gtk::{self, gio},
+    typed_view::list::TypedListView,
+};
+
+use crate::{
+    app::{
+        AboutAction, ImportContactsAction, NewChatAction, PreferencesAction,
+        RemoveImportedContactsAction, ShortcutsAction, SignOutAction,
+    },
+    config::APP_ID,
+    services::{
+        self,
+        events::{self, Update},
+        store::Store,
+    },
+    ui::{
+        components::{
+            chat_row::ChatRow,
+            conversation::{Conversation, ConversationMsg},
+        },
+        dialogs::new_chat::{NewChatDialog, NewChatMsg},
+        notifications,
+        pages::search::{SearchMsg, SearchOutput, SearchPage},
+    },
+};
+
+/// Chats read from the store per query
+const CHAT_PAGE_SIZE: u32 = 200;
+
+#[derive(Debug)]
+pub enum MainPageMsg {
+    /// Reload the chat list from the store
+    Refresh,
+    /// Reload one chat from the store and move it to its place in the list
+    RefreshChat(models::ChatGuid),
+    ChatActivated(u32),
+    /// Show the search in the sidebar and focus it
+    Search,
+    /// Ask for recipients to start a conversation with
+    NewChat,
+    SetSearching(bool),
+    /// Open a chat, e.g. from a search result, scrolled to a message if one is given
+    OpenChat {
+        chat: models::Chat,
+        message_guid: Option<String>,
+    },
+    /// Mute notifications for the open chat
+    SetMuted(bool),
+    CompactModeChanged(bool),
+    /// The window became narrower or wider than the split view breakpoint
+    Narrow(bool),
+}
+
+#[derive(Debug)]
+pub enum MainPageCommandMsg {
+    ChatsLoaded(Result<Vec<models::Chat>, String>),
+    ChatLoaded(Result<Option<models::Chat>, String>),
+    Update(Update),
+}
+
+pub struct MainPage {
+    chats: TypedListView<ChatRow, gtk::SingleSelection>,
+    conversation: Controller<Conversation>,
+    search: Controller<SearchPage>,
+    new_chat_dialog: Controller<NewChatDialog>,
+    /// The sidebar shows the search instead of the chat list
+    searching: bool,
+    selected: Option<ChatRow>,
+    /// GUID of a selected chat that wasn't in the list yet, kept selected until a
+    /// refresh lists it
+    pending: Option<models::ChatGuid>,
+    /// Notifications are muted for the selected chat
+    muted: bool,
+    /// Kept alive to receive change notifications
+    _settings: gio::Settings,
+    compact: bool,
+    narrow: bool,
+}
+
+impl MainPage {
+    fn content_title(&self) -> String {
+        self.selected
+            .as_ref()
+            .map(|chat| chat.title.clone())
+            .unwrap_or_else(|| gettext("Messages"))
+    }
+
+    fn select(&mut self, mut chat: ChatRow) {
+        notifications::set_open_chat(Some(&chat.guid));
+        self.muted = notifications::is_muted(&chat.guid);
+        self.pending = self.pending.take().filter(|guid| *guid == chat.guid);
+
+        // The row passed in may be built from a search result without its last message
+        let unread = chat.unread
+            || self
+                .chats
+                .find(|row| row.guid == chat.guid && row.unread)
+                .is_some();
+        chat.unread = false;
+        self.selected = Some(chat.clone());
+        if unread {
+            mark_read(chat.guid.clone());
+            self.update_row(chat);
+        }
+    }
+
+    /// Replace the row for a chat, moving it to keep the list sorted by last activity
+    fn update_row(&mut self, row: ChatRow) {
+        if let Some(position) = self.chats.find(|chat| chat.guid == row.guid) {
+            self.chats.remove(position);
+        }
+        let position = self.chats.insert_sorted(row.clone(), |a, b| {
+            b.last_message_date.cmp(&a.last_message_date)
+        });
+        if self
+            .selected
+            .as_ref()
+            .is_some_and(|selected| selected.guid == row.guid)
+        {
+            self.chats.selection_model.set_selected(position);
+            self.selected = Some(row);
+        }
+    }
+}
+
+#[relm4::component(pub)]
+impl Component for MainPage {
+    type Input = MainPageMsg;
+    type Output = ();
+    type Init = ();
+    type CommandOutput = MainPageCommandMsg;
+
+    menu! {
+        primary_menu: {
+            section! {
+                "_Preferences" => PreferencesAction,
+                "_Keyboard" => ShortcutsAction,
+                "_About TuxBubbles" => AboutAction,
+            },
+            section! {
+                "_Import Contacts…" => ImportContactsAction,
+                "_Remove Imported Contacts" => RemoveImportedContactsAction,
+                "_Sign Out" => SignOutAction,
+            }
+        }
+    }
+
+    view! {
+        #[root]
+        adw::BreakpointBin {
+            set_width_request: 360,
+            set_height_request: 294,
+            set_child: Some(&split_view),
+        },
+        split_view = &adw::NavigationSplitView {
+            #[watch]
+            set_collapsed: model.compact || model.narrow,
+
+            #[wrap(Some)]
+            set_sidebar = &adw::NavigationPage {
+                set_title: &gettext("Chats"),
+
+                #[wrap(Some)]
+                set_child = &adw::ToolbarView {
+                    add_top_bar = &adw::HeaderBar {
+                        pack_start = &gtk::Button {
+                            set_icon_name: "list-add-symbolic",
+                            set_tooltip_text: Some(&gettext("New Conversation")),
+                            set_action_name: Some("win.new-chat"),
+                        },
+
+                        pack_start = &gtk::ToggleButton {
+                            set_icon_name: "system-search-symbolic",
+                            set_tooltip_text: Some(&gettext("Search")),
+                            #[watch]
+                            #[block_signal(search_toggled)]
+                            set_active: model.searching,
+                            connect_toggled[sender] => move |button| {
+                                sender.input(MainPageMsg::SetSearching(button.is_active()));
+                            } @search_toggled,
+                        },
+
+                        pack_end = &gtk::MenuButton {
+                            set_icon_name: "open-menu-symbolic",
+                            set_menu_model: Some(&primary_menu),
+                            set_primary: true,
+                            set_tooltip_text: Some(&gettext("Main Menu")),
+                        }
+                    },
+
+                    #[wrap(Some)]
+                    set_content = &gtk::Stack {
+                        add_named[Some("chats")] = &gtk::ScrolledWindow {
+                            set_hscrollbar_policy: gtk::PolicyType::Never,
+
+                            #[local_ref]
+                            chat_list -> gtk::ListView {
+                                add_css_class: "navigation-sidebar",
+                                set_single_click_activate: true,
+                                connect_activate[sender] => move |_, position| {
+                                    sender.input(MainPageMsg::ChatActivated(position));
+                                },
+                            }
+                        },
+
+                        add_named: (model.search.widget(), Some("search")),
+                        #[watch]
+                        set_visible_child_name: if model.searching { "search" } else { "chats" },
+                    }
+                }
+            },
+
+            #[wrap(Some)]
+            set_content = &adw::NavigationPage {
+                #[watch]
+                set_title: &model.content_title(),
+
+                #[wrap(Some)]
+                set_child = &adw::ToolbarView {
+                    add_top_bar = &adw::HeaderBar {
+                        pack_end = &gtk::ToggleButton {
+                            set_icon_name: "notifications-disabled-symbolic",
+                            set_tooltip_text: Some(&gettext("Mute Conversation")),
+                            #[watch]
+                            set_visible: model.selected.is_some(),
+                            #[watch]
+                            #[block_signal(mute_toggled)]
+                            set_active: model.muted,
+                            connect_toggled[sender] => move |button| {
+                                sender.input(MainPageMsg::SetMuted(button.is_active()));
+                            } @mute_toggled,
+                        },
+                    },
+
+                    #[wrap(Some)]
+                    set_content = &gtk::Stack {
+                        add_named: (&empty_page, Some("empty")),
+                        add_named: (model.conversation.widget(), Some("conversation")),
+                        #[watch]
+                        set_visible_child_name: if model.selected.is_some() {
+                            "conversation"
+                        } else {
+                            "empty"
+                        },
+                    }
+                }
+            }
+        },
+        empty_page = &adw::StatusPage {
+            set_title: &gettext("No Conversation Selected"),
+            set_description: Some(&gettext("Choose a chat from the list")),
+        }
+    }
+
+    fn init(
+        _: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let settings = gio::Settings::new(APP_ID);
+        let compact_sender = sender.clone();
+        settings.connect_changed(Some("compact-mode"), move |settings, key| {
+            compact_sender.input(MainPageMsg::CompactModeChanged(settings.boolean(key)));
+        });
+
+        let chats = TypedListView::<ChatRow, gtk::SingleSelection>::new();
+        chats.selection_model.set_autoselect(false);
+        chats.selection_model.set_can_unselect(true);
+
+        let search = SearchPage::builder()
+            .launch(())
+            .forward(sender.input_sender(), |output| match output {
+                SearchOutput::Open { chat, message_guid } => {
+                    MainPageMsg::OpenChat { chat, message_guid }
+                }
+                SearchOutput::Close => MainPageMsg::SetSearching(false),
+            });
+
+        let new_chat_dialog = NewChatDialog::builder()
+            .launch(root.clone().upcast())
+            .forward(sender.input_sender(), |chat| MainPageMsg::OpenChat {
+                chat,
+                message_guid: None,
+            });
+
+        let model = Self {
+            chats,
+            conversation: Conversation::builder().launch(()).detach(),
+            search,
+            new_chat_dialog,
+            searching: false,
+            selected: None,
+            pending: None,
+            muted: false,
+            compact: settings.boolean("compact-mode"),
+            narrow: false,
+            _settings: settings,
+        };
+
+        let chat_list = &model.chats.view;
+        let widgets = view_output!();
+
+        // Collapse the split view into a single pane on narrow windows
+        let breakpoint = adw::Breakpoint::new(
+            adw::BreakpointCondition::parse("max-width: 600sp").expect("Invalid breakpoint"),
+        );
+        let apply_sender = sender.clone();
+        breakpoint.connect_apply(move |_| apply_sender.input(MainPageMsg::Narrow(true)));
+        let unapply_sender = sender.clone();
+        breakpoint.connect_unapply(move |_| unapply_sender.input(MainPageMsg::Narrow(false)));
+        root.add_breakpoint(breakpoint);
+
+        sender.command(|out, shutdown| {
+            shutdown
+                .register(events::for_each(move |update| {
+                    out.emit(MainPageCommandMsg::Update(update))
+                }))
+                .drop_on_shutdown()
+        });
+
+        sender.input(MainPageMsg::Refresh);
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update_with_view(
+        &mut self,
+        widgets: &mut Self::Widgets,
+        message: Self::Input,
+        sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match message {
+            MainPageMsg::Refresh => {
+                sender.oneshot_command(async {
+                    let result = async {
+                        let store = Store::get().await?;
+                        let mut chats = Vec::new();
+                        loop {
+                            let page = store.chats(CHAT_PAGE_SIZE, chats.len() as u32).await?;
+                            let done = page.len() < CHAT_PAGE_SIZE as usize;
+                            chats.extend(page);
+                            if done {
+                                break;
+                            }
+                        }
+                        anyhow::Ok(chats)
+                    }
+                    .await;
+                    MainPageCommandMsg::ChatsLoaded(result.map_err(|e| e.to_string()))
+                });
+            }
+            MainPageMsg::RefreshChat(guid) => {
+                sender.oneshot_command(async move {
+                    let result = async { Store::get().await?.listed_chat(&guid).await }.await;
+                    MainPageCommandMsg::ChatLoaded(result.map_err(|e| e.to_string()))
+                });
+            }
+            MainPageMsg::ChatActivated(position) => {
+                if let Some(item) = self.chats.get(position) {
+                    let chat = item.borrow().clone();
+                    self.conversation
+                        .emit(ConversationMsg::Open(chat.guid.clone()));
+                    self.select(chat);
+                    widgets.split_view.set_show_content(true);
+                }
+            }
+            MainPageMsg::Search => {
+                self.searching = true;
+                self.search.emit(SearchMsg::Focus);
+            }
+            MainPageMsg::NewChat => self.new_chat_dialog.emit(NewChatMsg::Show),
+            MainPageMsg::SetSearching(searching) => {
+                self.searching = searching;
+                if searching {
+                    self.search.emit(SearchMsg::Focus);
+                }
+            }
+            MainPageMsg::OpenChat { chat, message_guid } => {
+                match self.chats.find(|row| row.guid == chat.guid) {
+                    Some(position) => self.chats.selection_model.set_selected(position),
+                    None => {
+                        // e.g. a chat that was just started, shown until the refresh has it
+                        self.chats.insert(0, ChatRow::new(&chat));
+                        self.chats.selection_model.set_selected(0);
+                        self.pending = Some(chat.guid.clone());
+                        sender.input(MainPageMsg::Refresh);
+                    }
+                }
+                self.conversation
+                    .emit(ConversationMsg::Open(chat.guid.clone()));
+                if let Some(message_guid) = message_guid {
+                    self.conversation
+                        .emit(ConversationMsg::JumpTo(message_guid));
+                }
+                self.select(ChatRow::new(&chat));
+                widgets.split_view.set_show_content(true);
+            }
+            MainPageMsg::SetMuted(muted) => {
+                let Some(chat) = &self.selected else {
+                    return;
+                };
+                match notifications::set_muted(&chat.guid, muted) {
+                    Ok(()) => self.muted = muted,
+                    Err(e) => tracing::error!("Failed to save muted chats: {}", e),
+                }
+            }
+            MainPageMsg::CompactModeChanged(compact) => self.compact = compact,
+            MainPageMsg::Narrow(narrow) => self.narrow = narrow,
+        }
+
+        self.update_view(widgets, sender);
+    }
+
+    fn update_cmd(
+        &mut self,
+        message: Self::CommandOutput,
+        sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match message {
+            MainPageCommandMsg::ChatsLoaded(Ok(chats)) => {
+                self.chats.clear();
+                self.chats.extend_from_iter(chats.iter().map(ChatRow::new));
+
+                // Keep the open chat highlighted across reloads
+                if let Some(selected) = &self.selected {
+                    match self.chats.find(|chat| chat.guid == selected.guid) {
+                        Some(position) => {
+                            self.chats.selection_model.set_selected(position);
+                            self.pending = None;
+                        }
+                        None if self.pending.as_ref() == Some(&selected.guid) => {
+                            self.chats.insert(0, selected.clone());
+                            self.chats.selection_model.set_selected(0);
+                        }
+                        None => {
+                            notifications::set_open_chat(None);
+                            self.selected = None;
+                        }
+                    }
+                }
+            }
+            MainPageCommandMsg::ChatsLoaded(Err(e)) => {
+                tracing::error!("Failed to load chats: {}", e);
+            }
+            MainPageCommandMsg::ChatLoaded(Ok(Some(chat))) => self.update_row(ChatRow::new(&chat)),
+            MainPageCommandMsg::ChatLoaded(Ok(None)) => {}
+            MainPageCommandMsg::ChatLoaded(Err(e)) => {
+                tracing::error!("Failed to load chat: {}", e);
+            }
+            // Events are in the store by the time they arrive here, only their chat
+            // changed
+            MainPageCommandMsg::Update(Update::Event(
+                Event::NewMessage(message)
+                | Event::UpdatedMessage(message)
+                | Event::MessageSendError(message)
+                | Event::GroupNameChange(message)
+                | Event::ParticipantAdded(message)
+                | Event::ParticipantRemoved(message)
+                | Event::ParticipantLeft(message),
+            )) => {
+                if let Some(chat) = message.chats.first() {
+                    sender.input(MainPageMsg::RefreshChat(chat.guid.clone()));
+                }
+            }
+            MainPageCommandMsg::Update(Update::CaughtUp | Update::ContactsChanged) => {
+                sender.input(MainPageMsg::Refresh)
+            }
+            MainPageCommandMsg::Update(_) => {}
+        }
+    }
+}
+
+/// Mark a chat as read in the store, and on the server if it has the Private API
+fn mark_read(chat_guid: models::ChatGuid) {
+    relm4::spawn(async move {
+        if let Some(client) = services::api::client()
+            && let Err(e) = client.chats().mark_read().guid(&chat_guid).send().await
+        {
+            tracing::warn!("Failed to mark chat as read on the server: {}", e);
+        }
+        let result = async {
+            Store::get()
+                .await?
+                .mark_chat_read(&chat_guid, Utc::now().timestamp_millis())
+                .await
+        }
+        .await;
+        if let Err(e) = result {
+            tracing::error!("Failed to mark chat as read: {}", e);
+        }
+    });
+}
@@ -1 +1,3 @@
+pub mod main;
 pub mod onboarding;
+pub mod search;
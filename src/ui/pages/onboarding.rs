@@ -1,5 +1,6 @@
-use std::{fmt, sync::LazyLock};
+use std::{error::Error as _, fmt, sync::LazyLock};
 
+use bluebubbles_api::{client::Client, error::Error};
 use fancy_regex::Regex;
 use gettextrs::gettext;
 use libadwaita::prelude::{EntryRowExt, NavigationPageExt, PreferencesRowExt};
@@ -19,6 +20,12 @@ use relm4::{
 use crate::{
     app::{APP_BROKER, AboutAction, AppMsg},
     config::APP_ID,
+    services::{
+        self,
+        credentials::Credentials,
+        store::Store,
+        sync::{HistoryRange, SyncEngine, SyncProgress},
+    },
 };
 
 static ALLOWED_URL_CHARS_REGEX: LazyLock<Regex> =
@@ -58,6 +65,23 @@ pub enum OnboardingPageMsg {
     UrlEntered,
     PasswordChanged(String),
     Connect,
+    HistoryChanged(f64),
+    Sync,
+    /// Return to the welcome page with empty fields, used after signing out
+    Reset,
+}
+
+#[derive(Debug)]
+pub enum OnboardingOutput {
+    /// The server is connected and the initial sync has completed
+    Finished,
+}
+
+#[derive(Debug)]
+pub enum OnboardingCommandMsg {
+    Connected(Result<(), Error>),
+    SyncProgress(SyncProgress),
+    SyncFinished(Result<(), String>),
 }
 
 pub struct OnboardingPage {
@@ -68,6 +92,32 @@ pub struct OnboardingPage {
     password_error: Option<String>,
     connecting: bool,
     sync_all: bool,
+    /// Position of the history slider, 0-100 maps to 0-12 months
+    history: f64,
+    syncing: bool,
+    sync_progress: SyncProgress,
+}
+
+impl OnboardingPage {
+    fn history_range(&self) -> HistoryRange {
+        if self.sync_all {
+            return HistoryRange::All;
+        }
+        match (self.history / 100.0 * 12.0).round() as u32 {
+            0 => HistoryRange::None,
+            months => HistoryRange::Months(months),
+        }
+    }
+
+    fn sync_progress_text(&self) -> String {
+        gettext("{done} of {total} chats, {messages} messages")
+            .replace("{done}", &self.sync_progress.chats_done.to_string())
+            .replace("{total}", &self.sync_progress.total_chats.to_string())
+            .replace(
+                "{messages}",
+                &self.sync_progress.messages_fetched.to_string(),
+            )
+    }
 }
 
 #[relm4::widget_template(pub)]
@@ -88,9 +138,9 @@ impl WidgetTemplate for OnboardingToolbar {
 #[relm4::component(pub)]
 impl Component for OnboardingPage {
     type Input = OnboardingPageMsg;
-    type Output = ();
+    type Output = OnboardingOutput;
     type Init = ();
-    type CommandOutput = ();
+    type CommandOutput = OnboardingCommandMsg;
 
     view! {
         // #[root]
@@ -258,7 +308,7 @@ impl Component for OnboardingPage {
                             set_orientation: gtk::Orientation::Horizontal,
                             set_hexpand: true,
                             #[watch]
-                            set_sensitive: !model.sync_all,
+                            set_sensitive: !model.sync_all && !model.syncing,
                             set_adjustment: &history_scale_adjustment,
 
                             add_mark: (0.0, gtk::PositionType::Bottom, Some(&gettext("none"))),
@@ -272,6 +322,8 @@ impl Component for OnboardingPage {
                     adw::SwitchRow {
                         set_title: &gettext("Sync all messages"),
                         #[watch]
+                        set_sensitive: !model.syncing,
+                        #[watch]
                         #[block_signal(toggle_handler)]
                         set_active: model.sync_all,
                         connect_active_notify => OnboardingPageMsg::ToggleSyncAll @toggle_handler,
@@ -282,12 +334,24 @@ impl Component for OnboardingPage {
                     set_height_request: 12
                 },
 
+                gtk::ProgressBar {
+                    set_show_text: true,
+                    #[watch]
+                    set_visible: model.syncing,
+                    #[watch]
+                    set_fraction: model.sync_progress.fraction(),
+                    #[watch]
+                    set_text: Some(model.sync_progress_text().as_str()),
+                },
+
                 gtk::Button {
                     set_label: &gettext("Sync"),
                     set_halign: gtk::Align::Center,
                     set_width_request: 120,
                     set_css_classes: &["suggested-action", "pill"],
-                    connect_clicked => OnboardingPageMsg::NextPage,
+                    #[watch]
+                    set_sensitive: !model.syncing,
+                    connect_clicked => OnboardingPageMsg::Sync,
                 }
             }
         },
@@ -304,6 +368,10 @@ impl Component for OnboardingPage {
             .lower(0.0)
             .step_increment(0.1)
             .build();
+        let history_sender = sender.clone();
+        history_scale_adjustment.connect_value_changed(move |adjustment| {
+            history_sender.input(OnboardingPageMsg::HistoryChanged(adjustment.value()));
+        });
 
         let url_focus_controller = gtk::EventControllerFocus::new();
         let focus_sender = sender.clone();
@@ -319,6 +387,9 @@ impl Component for OnboardingPage {
             url: String::new(),
             password: String::new(),
             connecting: false,
+            history: 0.0,
+            syncing: false,
+            sync_progress: SyncProgress::default(),
         };
         let widgets = view_output!();
 
@@ -387,27 +458,156 @@ impl Component for OnboardingPage {
                 self.password_error = None;
             }
             OnboardingPageMsg::Connect => {
+                if !URL_REGEX.is_match(&self.url).unwrap_or(false) {
+                    self.url_error = Some(gettext("Invalid URL"));
+                }
+
                 if let Some(url_error) = self.url_error.clone() {
                     APP_BROKER.send(AppMsg::ShowToast(url_error));
-                } else {
+                } else if !self.connecting {
                     self.connecting = true;
+                    self.password_error = None;
+
+                    let client = Client::builder()
+                        .server_url(self.url.clone())
+                        .password(self.password.clone())
+                        .build();
+                    sender.oneshot_command(async move {
+                        let result = client.ping().send().await.map(|_| ());
+                        OnboardingCommandMsg::Connected(result)
+                    });
+                }
+            }
+            OnboardingPageMsg::HistoryChanged(history) => self.history = history,
+            OnboardingPageMsg::Reset => {
+                self.step = OnboardingStep::Welcome;
+                self.url_error = None;
+                self.password_error = None;
+                self.password.clear();
+                self.syncing = false;
+                widgets.password_entry.set_text("");
+                root.pop_to_tag(&OnboardingStep::Welcome.to_string());
+            }
+            OnboardingPageMsg::Sync => {
+                self.syncing = true;
+                self.sync_progress = SyncProgress::default();
+
+                let client = Client::builder()
+                    .server_url(self.url.clone())
+                    .password(self.password.clone())
+                    .build();
+                let range = self.history_range();
+                sender.command(move |out, shutdown| {
+                    shutdown
+                        .register(async move {
+                            let result = async {
+                                let store = Store::get().await?;
+                                SyncEngine::new(client, store)
+                                    .start_backfill(range, |progress| {
+                                        out.emit(OnboardingCommandMsg::SyncProgress(progress))
+                                    })
+                                    .await
+                            }
+                            .await;
+                            out.emit(OnboardingCommandMsg::SyncFinished(
+                                result.map_err(|e| e.to_string()),
+                            ));
+                        })
+                        .drop_on_shutdown()
+                });
+            }
+        }
 
-                    // try to connect to the server
+        // Update the view
+        self.update_view(widgets, sender);
+    }
 
-                    // Simulate an invalid password
-                    if false {
+    fn update_cmd(
+        &mut self,
+        message: Self::CommandOutput,
+        sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match message {
+            OnboardingCommandMsg::Connected(result) => {
+                self.connecting = false;
+                match result {
+                    Ok(()) => {
+                        let credentials = Credentials {
+                            server_url: self.url.clone(),
+                            password: self.password.clone(),
+                        };
+                        services::api::set_client(Some(credentials.client()));
+                        relm4::spawn(async move {
+                            if let Err(e) = credentials.save().await {
+                                tracing::error!("Failed to save credentials: {}", e);
+                                APP_BROKER.send(AppMsg::ShowToast(gettext(
+                                    "Could not save the password to the keyring",
+                                )));
+                            }
+                        });
+                        sender.input(OnboardingPageMsg::NextPage);
+                    }
+                    Err(Error::AuthenticationError { .. }) => {
                         self.password_error = Some(gettext("Invalid credentials"));
                         APP_BROKER.send(AppMsg::ShowToast(gettext("Invalid credentials")));
-                    } else {
-                        sender.input(OnboardingPageMsg::NextPage);
                     }
-
-                    self.connecting = false;
+                    Err(Error::HTTPError(e)) if is_tls_error(&e) => {
+                        tracing::warn!("TLS error connecting to server: {}", e);
+                        self.url_error = Some(gettext(
+                            "Could not establish a secure connection, check the server certificate",
+                        ));
+                        APP_BROKER.send(AppMsg::ShowToast(gettext(
+                            "Could not establish a secure connection",
+                        )));
+                    }
+                    Err(Error::HTTPError(e)) => {
+                        tracing::warn!("Failed to reach server: {}", e);
+                        self.url_error = Some(gettext("Could not reach the server"));
+                        APP_BROKER.send(AppMsg::ShowToast(gettext("Could not reach the server")));
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to connect to server: {}", e);
+                        APP_BROKER.send(AppMsg::ShowToast(gettext(
+                            "The server returned an unexpected response",
+                        )));
+                    }
+                }
+            }
+            OnboardingCommandMsg::SyncProgress(progress) => self.sync_progress = progress,
+            OnboardingCommandMsg::SyncFinished(result) => {
+                self.syncing = false;
+                match result {
+                    Ok(()) => sender.output(OnboardingOutput::Finished).unwrap(),
+                    Err(e) => {
+                        tracing::error!("Sync failed: {}", e);
+                        APP_BROKER.send(AppMsg::ShowToast(gettext("Sync failed")));
+                    }
                 }
             }
         }
+    }
+}
 
-        // Update the view
-        self.update_view(widgets, sender);
+/// Whether a request failed while negotiating TLS, e.g. because of an untrusted certificate
+fn is_tls_error(error: &reqwest::Error) -> bool {
+    if !error.is_connect() {
+        return false;
+    }
+    let mut source = error.source();
+    while let Some(error) = source {
+        if error.is::<rustls::Error>() {
+            return true;
+        }
+        // `io::Error::source` skips the error it wraps, which is where rustls puts its own
+        if let Some(error) = error.downcast_ref::<std::io::Error>()
+            && error
+                .get_ref()
+                .is_some_and(|inner| inner.is::<rustls::Error>())
+        {
+            return true;
+        }
+        source = error.source();
     }
+    false
 }
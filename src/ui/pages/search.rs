@@ -0,0 +1,493 @@
+use std::collections::HashSet;
+
+use anyhow::Context;
+use bluebubbles_api::models;
+use gettextrs::gettext;
+use libadwaita::prelude::*;
+use relm4::{
+    Component, ComponentParts, ComponentSender, adw,
+    gtk::{self, glib},
+};
+
+use crate::{
+    app::{APP_BROKER, AppMsg},
+    services::{
+        self,
+        store::{MATCH_END, MATCH_START, SearchHit, Store},
+    },
+    ui::format,
+};
+
+/// Local message matches shown per search
+const MESSAGE_LIMIT: u32 = 200;
+/// Chats matched by name shown per search
+const CHAT_LIMIT: u32 = 20;
+/// Messages requested from the server per search
+const SERVER_LIMIT: u32 = 50;
+
+#[derive(Debug)]
+pub enum SearchMsg {
+    /// Focus the search entry
+    Focus,
+    Changed(String),
+    /// Search the history on the server, for messages that were never synced
+    SearchServer,
+    Activated(usize),
+}
+
+#[derive(Debug)]
+pub enum SearchOutput {
+    /// Open a chat, scrolled to a message if one was picked
+    Open {
+        chat: models::Chat,
+        message_guid: Option<String>,
+    },
+    /// The search was dismissed
+    Close,
+}
+
+#[derive(Debug)]
+pub enum SearchCommandMsg {
+    LocalResults {
+        query: String,
+        result: Result<LocalResults, String>,
+    },
+    ServerResults {
+        query: String,
+        result: Result<Vec<models::Message>, String>,
+    },
+}
+
+#[derive(Debug)]
+pub struct LocalResults {
+    chats: Vec<models::Chat>,
+    hits: Vec<(models::Chat, Vec<SearchHit>)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ServerSearch {
+    Available,
+    Searching,
+    Done,
+}
+
+/// What a row of the result list stands for
+#[derive(Debug)]
+enum ResultRow {
+    Heading,
+    Chat(models::Chat),
+    Message { chat: models::Chat, guid: String },
+    SearchServer,
+}
+
+/// Messages of one chat matching the search
+#[derive(Debug)]
+struct ChatMatches {
+    chat: models::Chat,
+    hits: Vec<Hit>,
+}
+
+#[derive(Debug)]
+struct Hit {
+    guid: String,
+    /// Pango markup with the matched terms in bold
+    snippet: String,
+    sender: String,
+    date: i64,
+}
+
+/// Message search, grouped by chat
+pub struct SearchPage {
+    entry: gtk::SearchEntry,
+    list: gtk::ListBox,
+    query: String,
+    chats: Vec<models::Chat>,
+    matches: Vec<ChatMatches>,
+    server: ServerSearch,
+    rows: Vec<ResultRow>,
+}
+
+impl SearchPage {
+    fn add_hit(&mut self, chat: &models::Chat, hit: Hit) {
+        match self
+            .matches
+            .iter_mut()
+            .find(|matches| matches.chat.guid == chat.guid)
+        {
+            Some(matches) => matches.hits.push(hit),
+            None => self.matches.push(ChatMatches {
+                chat: chat.clone(),
+                hits: vec![hit],
+            }),
+        }
+    }
+
+    /// Rebuild the result list from the current matches
+    fn show_results(&mut self) {
+        self.list.remove_all();
+        self.rows.clear();
+        if self.query.is_empty() {
+            return;
+        }
+
+        if !self.chats.is_empty() {
+            self.push_heading(&gettext("Conversations"));
+            for chat in self.chats.clone() {
+                let row = adw::ActionRow::builder()
+                    .title(format::chat_title(&chat))
+                    .use_markup(false)
+                    .activatable(true)
+                    .build();
+                self.push_row(&row, ResultRow::Chat(chat));
+            }
+        }
+
+        let matches = std::mem::take(&mut self.matches);
+        for chat_matches in &matches {
+            self.push_heading(&format::chat_title(&chat_matches.chat));
+            for hit in &chat_matches.hits {
+                let row = adw::ActionRow::builder()
+                    .title(&hit.snippet)
+                    .title_lines(2)
+                    .subtitle(glib::markup_escape_text(&format!(
+                        "{} · {}",
+                        hit.sender,
+                        format::short_timestamp(hit.date)
+                    )))
+                    .subtitle_lines(1)
+                    .activatable(true)
+                    .build();
+                let result = ResultRow::Message {
+                    chat: chat_matches.chat.clone(),
+                    guid: hit.guid.clone(),
+                };
+                self.push_row(&row, result);
+            }
+        }
+        self.matches = matches;
+
+        if self.chats.is_empty() && self.matches.is_empty() {
+            self.push_heading(&gettext("No Results on This Device"));
+        }
+
+        let server_row = adw::ActionRow::builder()
+            .title(gettext("Search on Server"))
+            .subtitle(match self.server {
+                ServerSearch::Available => gettext("Find messages that are not on this device"),
+                ServerSearch::Searching => gettext("Searching…"),
+                ServerSearch::Done => gettext("Results from the server are included"),
+            })
+            .activatable(self.server == ServerSearch::Available)
+            .build();
+        server_row.add_prefix(&gtk::Image::from_icon_name("network-server-symbolic"));
+        self.push_row(&server_row, ResultRow::SearchServer);
+    }
+
+    fn push_heading(&mut self, title: &str) {
+        let label = gtk::Label::builder()
+            .label(title)
+            .xalign(0.0)
+            .ellipsize(gtk::pango::EllipsizeMode::End)
+            .margin_top(12)
+            .margin_start(6)
+            .css_classes(["heading"])
+            .build();
+        let row = gtk::ListBoxRow::builder()
+            .child(&label)
+            .activatable(false)
+            .selectable(false)
+            .build();
+        self.list.append(&row);
+        self.rows.push(ResultRow::Heading);
+    }
+
+    fn push_row(&mut self, row: &impl IsA<gtk::Widget>, result: ResultRow) {
+        self.list.append(row);
+        self.rows.push(result);
+    }
+}
+
+#[relm4::component(pub)]
+impl Component for SearchPage {
+    type Input = SearchMsg;
+    type Output = SearchOutput;
+    type Init = ();
+    type CommandOutput = SearchCommandMsg;
+
+    view! {
+        #[root]
+        gtk::Box {
+            set_orientation: gtk::Orientation::Vertical,
+
+            #[local_ref]
+            search_entry -> gtk::SearchEntry {
+                set_placeholder_text: Some(&gettext("Search Messages")),
+                set_margin_start: 6,
+                set_margin_end: 6,
+                set_margin_bottom: 6,
+                connect_search_changed[sender] => move |entry| {
+                    sender.input(SearchMsg::Changed(entry.text().to_string()));
+                },
+                connect_stop_search[sender] => move |_| {
+                    sender.output(SearchOutput::Close).unwrap();
+                },
+            },
+
+            gtk::Stack {
+                add_named[Some("hint")] = &adw::StatusPage {
+                    set_icon_name: Some("system-search-symbolic"),
+                    set_title: &gettext("Search Messages"),
+                    set_description: Some(&gettext("Find messages, attachments and conversations")),
+                    add_css_class: "compact",
+                },
+
+                add_named[Some("results")] = &gtk::ScrolledWindow {
+                    set_vexpand: true,
+                    set_hscrollbar_policy: gtk::PolicyType::Never,
+
+                    #[local_ref]
+                    result_list -> gtk::ListBox {
+                        add_css_class: "navigation-sidebar",
+                        set_selection_mode: gtk::SelectionMode::None,
+                        connect_row_activated[sender] => move |_, row| {
+                            sender.input(SearchMsg::Activated(row.index() as usize));
+                        },
+                    },
+                },
+                #[watch]
+                set_visible_child_name: if model.query.is_empty() { "hint" } else { "results" },
+            },
+        }
+    }
+
+    fn init(
+        _: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = Self {
+            entry: gtk::SearchEntry::new(),
+            list: gtk::ListBox::new(),
+            query: String::new(),
+            chats: Vec::new(),
+            matches: Vec::new(),
+            server: ServerSearch::Available,
+            rows: Vec::new(),
+        };
+
+        let search_entry = &model.entry;
+        let result_list = &model.list;
+        let widgets = view_output!();
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, _root: &Self::Root) {
+        match message {
+            SearchMsg::Focus => {
+                self.entry.grab_focus();
+                self.entry.select_region(0, -1);
+            }
+            SearchMsg::Changed(query) => {
+                let query = query.trim().to_string();
+                if query == self.query {
+                    return;
+                }
+                self.query = query.clone();
+                self.server = ServerSearch::Available;
+                // The previous results stay until the new ones arrive
+                if query.is_empty() {
+                    self.chats.clear();
+                    self.matches.clear();
+                    self.show_results();
+                    return;
+                }
+
+                sender.oneshot_command(async move {
+                    let result = search_local(&query).await.map_err(|e| e.to_string());
+                    SearchCommandMsg::LocalResults { query, result }
+                });
+            }
+            SearchMsg::SearchServer => {
+                if self.server != ServerSearch::Available || self.query.is_empty() {
+                    return;
+                }
+                self.server = ServerSearch::Searching;
+                self.show_results();
+
+                let query = self.query.clone();
+                sender.oneshot_command(async move {
+                    let result = async {
+                        let client = services::api::client().context("Not signed in")?;
+                        let messages = client
+                            .messages()
+                            .search()
+                            .term(&query)
+                            .limit(SERVER_LIMIT)
+                            .send()
+                            .await?;
+                        anyhow::Ok(messages)
+                    }
+                    .await;
+                    SearchCommandMsg::ServerResults {
+                        query,
+                        result: result.map_err(|e| e.to_string()),
+                    }
+                });
+            }
+            SearchMsg::Activated(index) => match self.rows.get(index) {
+                Some(ResultRow::Chat(chat)) => sender
+                    .output(SearchOutput::Open {
+                        chat: chat.clone(),
+                        message_guid: None,
+                    })
+                    .unwrap(),
+                Some(ResultRow::Message { chat, guid }) => sender
+                    .output(SearchOutput::Open {
+                        chat: chat.clone(),
+                        message_guid: Some(guid.clone()),
+                    })
+                    .unwrap(),
+                Some(ResultRow::SearchServer) => sender.input(SearchMsg::SearchServer),
+                Some(ResultRow::Heading) | None => {}
+            },
+        }
+    }
+
+    fn update_cmd(
+        &mut self,
+        message: Self::CommandOutput,
+        _sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match message {
+            SearchCommandMsg::LocalResults { query, result } => {
+                if query != self.query {
+                    return;
+                }
+                match result {
+                    Ok(results) => {
+                        self.chats = results.chats;
+                        self.matches.clear();
+                        for (chat, hits) in results.hits {
+                            for hit in hits {
+                                let hit = Hit {
+                                    snippet: snippet_markup(&hit.snippet),
+                                    sender: sender_name(&hit.message),
+                                    date: hit.message.date_created,
+                                    guid: hit.message.guid,
+                                };
+                                self.add_hit(&chat, hit);
+                            }
+                        }
+                    }
+                    Err(e) => tracing::error!("Failed to search messages: {}", e),
+                }
+                self.show_results();
+            }
+            SearchCommandMsg::ServerResults { query, result } => {
+                if query != self.query {
+                    return;
+                }
+                self.server = ServerSearch::Done;
+                match result {
+                    Ok(messages) => {
+                        let known: HashSet<String> = self
+                            .matches
+                            .iter()
+                            .flat_map(|matches| matches.hits.iter().map(|hit| hit.guid.clone()))
+                            .collect();
+                        for message in messages {
+                            if known.contains(&message.guid) {
+                                continue;
+                            }
+                            let Some(chat) = message.chats.first().cloned() else {
+                                continue;
+                            };
+                            let hit = Hit {
+                                snippet: highlight(&format::message_text(&message), &query),
+                                sender: sender_name(&message),
+                                date: message.date_created,
+                                guid: message.guid.clone(),
+                            };
+                            self.add_hit(&chat, hit);
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to search the server: {}", e);
+                        self.server = ServerSearch::Available;
+                        APP_BROKER.send(AppMsg::ShowToast(gettext("Could not search the server")));
+                    }
+                }
+                self.show_results();
+            }
+        }
+    }
+}
+
+/// Search the store, grouping message matches by chat in order of their newest match
+async fn search_local(query: &str) -> anyhow::Result<LocalResults> {
+    let store = Store::get().await?;
+    let chats = store.search_chats(query, CHAT_LIMIT).await?;
+
+    let mut hits: Vec<(models::Chat, Vec<SearchHit>)> = Vec::new();
+    for hit in store.search_messages(query, MESSAGE_LIMIT).await? {
+        if let Some((_, chat_hits)) = hits.iter_mut().find(|(chat, _)| chat.guid == hit.chat_guid) {
+            chat_hits.push(hit);
+            continue;
+        }
+        let Some(chat) = store.chat(&hit.chat_guid).await? else {
+            continue;
+        };
+        hits.push((chat, vec![hit]));
+    }
+    Ok(LocalResults { chats, hits })
+}
+
+fn sender_name(message: &models::Message) -> String {
+    if message.is_from_me {
+        gettext("You")
+    } else {
+        message
+            .handle
+            .as_ref()
+            .map(format::handle_name)
+            .unwrap_or_else(|| gettext("Unknown"))
+    }
+}
+
+/// Turn the match markers of a store snippet into bold markup
+fn snippet_markup(snippet: &str) -> String {
+    let mut markup = String::new();
+    for (index, part) in snippet.split([MATCH_START, MATCH_END]).enumerate() {
+        // Parts alternate between text around matches and the matches themselves
+        let part = glib::markup_escape_text(part);
+        if index % 2 == 1 {
+            markup.push_str(&format!("<b>{part}</b>"));
+        } else {
+            markup.push_str(&part);
+        }
+    }
+    markup.replace('\n', " ")
+}
+
+/// Markup for a server result, the first occurrence of `term` in bold
+fn highlight(text: &str, term: &str) -> String {
+    let lower = text.to_lowercase();
+    let term = term.to_lowercase();
+    // Lowercasing can change byte offsets outside of ASCII, leave such text as is
+    let found = (lower.len() == text.len())
+        .then(|| lower.find(&term))
+        .flatten()
+        .and_then(|start| {
+            let end = start + term.len();
+            Some((text.get(..start)?, text.get(start..end)?, text.get(end..)?))
+        });
+    let snippet = match found {
+        Some((before, matched, after)) => {
+            format!("{before}{MATCH_START}{matched}{MATCH_END}{after}")
+        }
+        None => text.to_string(),
+    };
+    snippet_markup(&snippet)
+}